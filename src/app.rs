@@ -1,18 +1,45 @@
 use arboard::Clipboard;
 use eframe::egui;
+use egui::text::LayoutJob;
 use sled::Db;
+use std::collections::HashSet;
 use std::env;
 use std::fs;
 use std::io::Write;
+use std::ops::Range;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Receiver;
 use std::sync::Arc;
 use url::Url;
 
-use crate::file_processing::{self, Progress};
+use crate::calibration;
+use crate::diff::{self, DiffRow, RowKind};
+use crate::discovery::{self, DiscoveryFilters, DiscoveryHit, DiscoveryOptions, DiscoveryProgress};
+use crate::export::{self, ExportFormat, ExportScope};
+use crate::file_processing::{self, NodeValue, Progress, Source};
 use crate::file_saver::{self, SaveProgress};
-use crate::network::{self, NetworkResult};
+use crate::graph::{ColorBy, Graph, GraphKind, GraphOptions};
+use crate::har;
+use crate::fuzzer::{self, Attack, AttackMode, Encoder, FuzzOptions, FuzzProgress, FuzzResult, PayloadKind, PayloadSet};
+use crate::intercept::{self, InterceptEvent, InterceptOptions};
+use crate::js_endpoints::{self, JsProgress};
+use crate::link_checker::{self, BrokenLink, LinkCheckOptions, LinkCheckProgress};
+use crate::match_rules::{self, MatchRule, RuleKind};
+use crate::network::{self, NetworkConfig, NetworkResult, RequestOverride};
+use crate::response_store::{self, StoredResponse};
+use crate::robots::{self, RobotsProgress, RobotsReport};
+use crate::secrets::{self, EndpointFindings};
+use crate::seo::{self, AuditProgress, HostSummary, PageAudit, Severity};
+use crate::sitemap::{self, ChangeFreq, HreflangMode, SitemapOptions};
+use crate::snippets::{RequestSpec, SnippetFormat};
+use crate::spider::{self, SpiderOptions, SpiderProgress};
+use crate::proxy::{
+    ErrorKind, HealthCheckProgress, ProxyConfig, ProxyProgress, ProxyResult, Rotation, PROXY_PROTOCOLS,
+};
 use crate::syntax_highlighter::CodeTheme;
+use crate::tool_lists::{self, ToolPreset};
+use crate::tree_filter::{self, FilterResult, MatchMode, ParameterFilter, TreeFilter};
 
 #[derive(Default, Clone, PartialEq)]
 enum AppMode {
@@ -25,14 +52,90 @@ enum Action {
     Select(Vec<String>),
     Delete(Vec<String>),
     Copy(String),
+    /// Copies a request as a snippet: the newest stored request of the URL,
+    /// or the displayed one when `None`.
+    CopyAs(SnippetFormat, Option<String>),
     SendRequest(String),
     ShowSaveDialog,
     SaveToFile(String),
+    SaveSitemap(SitemapOptions),
+    Export(Box<dyn ExportFormat>, ExportScope, String),
+    ExportHar(ExportScope, String, bool),
+    /// The preset, the scope choosing which endpoints are listed (node,
+    /// tag, soft-404 and filter bar) and the file name.
+    ExportToolList(ToolPreset, ExportScope, String),
+    ShowHarExport(Vec<String>),
     SendToProxy(String),
     ShowProxyWindow,
+    ShowNetworkWindow,
+    ShowInterceptWindow,
+    ShowHistoryWindow,
+    ShowSpiderWindow(Vec<String>),
+    ShowDiscoveryWindow(Vec<String>),
+    CalibrateHost(String),
+    ExtractJsEndpoints(String),
+    ShowFuzzerWindow,
+    ShowMatchRulesWindow,
+    ShowFindingsWindow,
+    ShowAuditWindow,
+    ShowRobotsWindow,
+    ShowLinkCheckWindow(Vec<String>),
+    CheckRobots(Vec<String>),
+    RevealInTree(Vec<String>),
+    ShowFuzzResult(usize),
+    ShowCapturedExchange(usize),
+    ShowStoredResponse(String),
+    ShowHistoryEntry(usize),
+    SetDiffBase,
+    DiffAgainstBase,
+    DiffWithPrevious,
+    ShowDiff(Box<(NetworkResult, NetworkResult)>),
+    CloseDiff,
     SaveDisplayedUrls,
     SendDisplayedUrlsToProxy(u32),
     ShowThreadWindow,
+    ShowProxyResultsWindow,
+    RetryFailedProxyUrls,
+    ExportProxyResults(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+enum ResultsFilter {
+    #[default]
+    All,
+    Succeeded,
+    Failed,
+    Kind(ErrorKind),
+}
+
+struct JsExtraction {
+    script: String,
+    found: Vec<String>,
+    new_endpoints: usize,
+    source_map: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+enum SaveFormat {
+    #[default]
+    Text,
+    Sitemap,
+    /// An index into `export::FORMATS`.
+    Export(usize),
+    Graph,
+    Har,
+    ToolList,
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+enum FuzzSort {
+    #[default]
+    Index,
+    Payload,
+    Status,
+    Length,
+    Time,
+    Grep,
 }
 
 #[derive(Clone, Default)]
@@ -41,6 +144,25 @@ enum RightPanelView {
     Empty,
     Loading,
     Response(String, String, String, String, String, bool, u64, u128),
+    Diff(Box<ResponseDiff>),
+}
+
+/// Headers or body of two responses, aligned line by line.
+#[derive(Clone)]
+struct DiffSection {
+    title: &'static str,
+    rows: Vec<DiffRow>,
+    left: Vec<LayoutJob>,
+    right: Vec<LayoutJob>,
+}
+
+#[derive(Clone)]
+struct ResponseDiff {
+    left: NetworkResult,
+    right: NetworkResult,
+    sections: [DiffSection; 2],
+    changed_rows: usize,
+    only_changes: bool,
 }
 
 
@@ -51,27 +173,173 @@ pub struct SiteMapperApp {
     save_receiver: Option<Receiver<SaveProgress>>,
     is_loading_file: bool,
     is_saving_file: bool,
+    /// Stops the network requests of the running save, for saves that make them.
+    save_stop: Option<Arc<AtomicBool>>,
     progress: f32,
     time_remaining: Option<std::time::Duration>,
     total_url_count: usize,
     selected_path: Option<Vec<String>>,
+    /// Folders on this path are opened and the node scrolled into view.
+    reveal_path: Option<Vec<String>>,
     error_message: Option<String>,
     file_picker_path: PathBuf,
     file_picker_error: Option<String>,
     clipboard: Option<Clipboard>,
     right_panel_view: RightPanelView,
     network_receiver: Option<Receiver<NetworkResult>>,
+    network_config: NetworkConfig,
+    show_network_window: bool,
+    network_headers_text: String,
+    last_request_url: Option<String>,
+    request_override_enabled: bool,
+    request_override: RequestOverride,
+    request_override_headers_text: String,
     highlighter: CodeTheme,
+    /// Match rule ranges of the shown body and whether that body is the
+    /// pretty one; cleared when the response, its pretty body or the rules
+    /// change.
+    response_highlights: Option<(bool, Vec<Range<usize>>)>,
     show_save_dialog: bool,
     save_file_name: String,
-    proxy_address: String,
+    save_format: SaveFormat,
+    export_file_name: String,
+    export_scope: ExportScope,
+    export_selection_only: bool,
+    graph_options: GraphOptions,
+    har_fetch_missing: bool,
+    tool_preset: ToolPreset,
+    tool_list_file_name: String,
+    sitemap_options: SitemapOptions,
+    sitemap_directory: String,
     proxy_receiver: Option<Receiver<Result<(), String>>>,
-    proxy_progress_receiver: Option<Receiver<crate::proxy::ProxyProgress>>,
+    proxy_progress_receiver: Option<Receiver<ProxyProgress>>,
+    proxy_completed: usize,
+    proxy_total: usize,
+    proxy_rate: f32,
+    proxy_eta: Option<std::time::Duration>,
+    proxy_results: Vec<ProxyResult>,
+    show_proxy_results_window: bool,
+    proxy_results_filter: ResultsFilter,
+    proxy_results_search: String,
+    proxy_results_file_name: String,
     show_proxy_window: bool,
     proxy_protocol: String,
     proxy_ip: String,
     proxy_port: String,
+    proxy_username: String,
+    proxy_password: String,
+    proxy_form_error: Option<String>,
+    proxy_test_url: String,
+    proxy_test_receiver: Option<Receiver<Result<(u16, std::time::Duration), String>>>,
+    proxy_test_result: Option<Result<String, String>>,
+    proxy_pool_file: String,
+    pool_check_receiver: Option<Receiver<HealthCheckProgress>>,
+    show_intercept_window: bool,
+    intercept_bind_address: String,
+    intercept_tls: bool,
+    intercept_receiver: Option<Receiver<InterceptEvent>>,
+    intercept_stop: Option<Arc<AtomicBool>>,
+    intercept_status: Option<String>,
+    proxy_history: Vec<StoredResponse>,
+    response_history: Vec<StoredResponse>,
+    response_history_index: usize,
+    show_history_window: bool,
+    displayed_response: Option<NetworkResult>,
+    diff_base: Option<NetworkResult>,
+    diff_noise_text: String,
+    diff_noise_error: Option<String>,
+    show_spider_window: bool,
+    spider_root: Vec<String>,
+    spider_max_depth: u32,
+    spider_max_pages: usize,
+    spider_respect_robots: bool,
+    spider_receiver: Option<Receiver<SpiderProgress>>,
+    spider_stop: Option<Arc<AtomicBool>>,
+    spider_pages: usize,
+    spider_queued: usize,
+    spider_new_endpoints: usize,
+    spider_log: Vec<String>,
+    spider_status: Option<String>,
+    calibration_receiver: Option<Receiver<Result<usize, String>>>,
+    show_match_rules_window: bool,
+    match_rules: Vec<MatchRule>,
+    match_rules_receiver: Option<Receiver<Result<usize, String>>>,
+    match_rules_status: Option<String>,
+    show_js_window: bool,
+    js_receiver: Option<Receiver<JsProgress>>,
+    js_results: Vec<JsExtraction>,
+    js_status: Option<String>,
+    show_audit_window: bool,
+    audit_receiver: Option<Receiver<AuditProgress>>,
+    audit_stop: Option<Arc<AtomicBool>>,
+    audit_completed: usize,
+    audit_total: usize,
+    audit_pages: Vec<PageAudit>,
+    audit_hosts: Vec<HostSummary>,
+    audit_host: Option<String>,
+    audit_severities: [bool; 3],
+    audit_status: Option<String>,
+    show_link_check_window: bool,
+    link_check_root: Vec<String>,
+    link_check_external: bool,
+    link_check_receiver: Option<Receiver<LinkCheckProgress>>,
+    link_check_stop: Option<Arc<AtomicBool>>,
+    tree_filter: TreeFilter,
+    /// The filter `tree_filter_result` was (or is being) computed for.
+    tree_filter_applied: TreeFilter,
+    tree_filter_url_count: usize,
+    tree_filter_result: Option<Arc<FilterResult>>,
+    tree_filter_receiver: Option<Receiver<Result<FilterResult, String>>>,
+    tree_filter_cancel: Option<Arc<AtomicBool>>,
+    tree_filter_error: Option<String>,
+    /// Bumped with every new result so the tree's open state starts over.
+    tree_filter_generation: u64,
+    show_tree_filter_options: bool,
+    link_check_completed: usize,
+    link_check_total: usize,
+    broken_links: Vec<BrokenLink>,
+    link_check_csv: String,
+    link_check_status: Option<String>,
+    show_robots_window: bool,
+    robots_user_agent: String,
+    robots_receiver: Option<Receiver<RobotsProgress>>,
+    robots_reports: Vec<RobotsReport>,
+    robots_status: Option<String>,
+    show_findings_window: bool,
+    findings: Vec<EndpointFindings>,
+    findings_filter: String,
+    findings_receiver: Option<Receiver<Result<usize, String>>>,
+    findings_status: Option<String>,
+    show_fuzzer_window: bool,
+    fuzz_template: String,
+    fuzz_scheme: String,
+    fuzz_selection: Option<(usize, usize)>,
+    fuzz_mode: AttackMode,
+    fuzz_sets: Vec<PayloadSet>,
+    fuzz_grep: String,
+    fuzz_receiver: Option<Receiver<FuzzProgress>>,
+    fuzz_stop: Option<Arc<AtomicBool>>,
+    fuzz_completed: usize,
+    fuzz_total: usize,
+    fuzz_results: Vec<FuzzResult>,
+    fuzz_sort: FuzzSort,
+    fuzz_sort_ascending: bool,
+    fuzz_status: Option<String>,
+    show_discovery_window: bool,
+    discovery_root: Vec<String>,
+    discovery_wordlist: String,
+    discovery_extensions: String,
+    discovery_statuses: String,
+    discovery_hide_sizes: String,
+    discovery_hide_words: String,
+    discovery_receiver: Option<Receiver<DiscoveryProgress>>,
+    discovery_stop: Option<Arc<AtomicBool>>,
+    discovery_completed: usize,
+    discovery_total: usize,
+    discovery_hits: Vec<DiscoveryHit>,
+    discovery_status: Option<String>,
     proxy_threads: u32,
+    rate_limit: u32,
     show_thread_window: bool,
     action_sender: std::sync::mpsc::Sender<Action>,
     action_receiver: std::sync::mpsc::Receiver<Action>,
@@ -80,6 +348,11 @@ pub struct SiteMapperApp {
 impl Default for SiteMapperApp {
     fn default() -> Self {
         let (action_sender, action_receiver) = std::sync::mpsc::channel();
+        let (match_rules, match_rules_status) = match match_rules::load() {
+            Ok(rules) => (rules, None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+        let findings_status = secrets::reload_rules().err();
         Self {
             app_mode: AppMode::default(),
             db: None,
@@ -87,27 +360,168 @@ impl Default for SiteMapperApp {
             save_receiver: None,
             is_loading_file: false,
             is_saving_file: false,
+            save_stop: None,
             progress: 0.0,
             time_remaining: None,
             total_url_count: 0,
             selected_path: None,
+            reveal_path: None,
             error_message: None,
             file_picker_path: env::current_dir().unwrap_or_else(|_| PathBuf::from("/")),
             file_picker_error: None,
             clipboard: Clipboard::new().ok(),
             right_panel_view: RightPanelView::default(),
             network_receiver: None,
+            network_config: NetworkConfig::default(),
+            show_network_window: false,
+            network_headers_text: network::format_header_lines(&NetworkConfig::default().default_headers),
+            last_request_url: None,
+            request_override_enabled: false,
+            request_override: RequestOverride::from_config(&NetworkConfig::default()),
+            request_override_headers_text: network::format_header_lines(
+                &NetworkConfig::default().default_headers,
+            ),
             highlighter: CodeTheme::default(),
+            response_highlights: None,
             show_save_dialog: false,
             save_file_name: "sitemap.txt".to_string(),
-            proxy_address: "http://127.0.0.1:8080".to_string(),
+            save_format: SaveFormat::default(),
+            export_file_name: "export".to_string(),
+            export_scope: ExportScope::default(),
+            export_selection_only: true,
+            graph_options: GraphOptions::default(),
+            har_fetch_missing: true,
+            tool_preset: ToolPreset::Nuclei,
+            tool_list_file_name: ToolPreset::Nuclei.default_file_name().to_string(),
+            sitemap_options: SitemapOptions::default(),
+            sitemap_directory: "sitemaps".to_string(),
             proxy_receiver: None,
             proxy_progress_receiver: None,
+            proxy_completed: 0,
+            proxy_total: 0,
+            proxy_rate: 0.0,
+            proxy_eta: None,
+            proxy_results: Vec::new(),
+            show_proxy_results_window: false,
+            proxy_results_filter: ResultsFilter::default(),
+            proxy_results_search: String::new(),
+            proxy_results_file_name: "proxy_results.csv".to_string(),
             show_proxy_window: false,
             proxy_protocol: "http".to_string(),
             proxy_ip: "127.0.0.1".to_string(),
             proxy_port: "8080".to_string(),
+            proxy_username: String::new(),
+            proxy_password: String::new(),
+            proxy_form_error: None,
+            proxy_test_url: "https://example.com/".to_string(),
+            proxy_test_receiver: None,
+            proxy_test_result: None,
+            proxy_pool_file: "proxies.txt".to_string(),
+            pool_check_receiver: None,
+            show_intercept_window: false,
+            intercept_bind_address: "127.0.0.1:8081".to_string(),
+            intercept_tls: true,
+            intercept_receiver: None,
+            intercept_stop: None,
+            intercept_status: None,
+            proxy_history: Vec::new(),
+            response_history: Vec::new(),
+            response_history_index: 0,
+            show_history_window: false,
+            displayed_response: None,
+            diff_base: None,
+            diff_noise_text: String::new(),
+            diff_noise_error: None,
+            show_spider_window: false,
+            spider_root: Vec::new(),
+            spider_max_depth: 3,
+            spider_max_pages: 500,
+            spider_respect_robots: true,
+            spider_receiver: None,
+            spider_stop: None,
+            spider_pages: 0,
+            spider_queued: 0,
+            spider_new_endpoints: 0,
+            spider_log: Vec::new(),
+            spider_status: None,
+            calibration_receiver: None,
+            show_match_rules_window: false,
+            match_rules,
+            match_rules_receiver: None,
+            match_rules_status,
+            show_js_window: false,
+            js_receiver: None,
+            js_results: Vec::new(),
+            js_status: None,
+            show_audit_window: false,
+            audit_receiver: None,
+            audit_stop: None,
+            audit_completed: 0,
+            audit_total: 0,
+            audit_pages: Vec::new(),
+            audit_hosts: Vec::new(),
+            audit_host: None,
+            audit_severities: [true; 3],
+            audit_status: None,
+            show_link_check_window: false,
+            link_check_root: Vec::new(),
+            link_check_external: false,
+            link_check_receiver: None,
+            link_check_stop: None,
+            tree_filter: TreeFilter::default(),
+            tree_filter_applied: TreeFilter::default(),
+            tree_filter_url_count: 0,
+            tree_filter_result: None,
+            tree_filter_receiver: None,
+            tree_filter_cancel: None,
+            tree_filter_error: None,
+            tree_filter_generation: 0,
+            show_tree_filter_options: false,
+            link_check_completed: 0,
+            link_check_total: 0,
+            broken_links: Vec::new(),
+            link_check_csv: "broken_links.csv".to_string(),
+            link_check_status: None,
+            show_robots_window: false,
+            robots_user_agent: NetworkConfig::default().user_agent().to_string(),
+            robots_receiver: None,
+            robots_reports: Vec::new(),
+            robots_status: None,
+            show_findings_window: false,
+            findings: Vec::new(),
+            findings_filter: String::new(),
+            findings_receiver: None,
+            findings_status,
+            show_fuzzer_window: false,
+            fuzz_template: String::new(),
+            fuzz_scheme: "https".to_string(),
+            fuzz_selection: None,
+            fuzz_mode: AttackMode::default(),
+            fuzz_sets: vec![PayloadSet::default()],
+            fuzz_grep: String::new(),
+            fuzz_receiver: None,
+            fuzz_stop: None,
+            fuzz_completed: 0,
+            fuzz_total: 0,
+            fuzz_results: Vec::new(),
+            fuzz_sort: FuzzSort::default(),
+            fuzz_sort_ascending: true,
+            fuzz_status: None,
+            show_discovery_window: false,
+            discovery_root: Vec::new(),
+            discovery_wordlist: "wordlist.txt".to_string(),
+            discovery_extensions: String::new(),
+            discovery_statuses: "200-299,301,302,307,308,401,403,405".to_string(),
+            discovery_hide_sizes: String::new(),
+            discovery_hide_words: String::new(),
+            discovery_receiver: None,
+            discovery_stop: None,
+            discovery_completed: 0,
+            discovery_total: 0,
+            discovery_hits: Vec::new(),
+            discovery_status: None,
             proxy_threads: 1,
+            rate_limit: 0,
             show_thread_window: false,
             action_sender,
             action_receiver,
@@ -123,6 +537,20 @@ impl eframe::App for SiteMapperApp {
         self.handle_network_receiver(ctx);
         self.handle_proxy_receiver(ctx);
         self.handle_proxy_progress_receiver(ctx);
+        self.handle_proxy_test_receiver(ctx);
+        self.handle_pool_check_receiver(ctx);
+        self.handle_intercept_receiver(ctx);
+        self.handle_spider_receiver(ctx);
+        self.handle_discovery_receiver(ctx);
+        self.handle_calibration_receiver(ctx);
+        self.handle_fuzz_receiver(ctx);
+        self.handle_match_rules_receiver(ctx);
+        self.handle_findings_receiver(ctx);
+        self.handle_js_receiver(ctx);
+        self.handle_audit_receiver(ctx);
+        self.handle_robots_receiver(ctx);
+        self.handle_link_check_receiver(ctx);
+        self.handle_tree_filter_receiver(ctx);
 
         let current_mode = self.app_mode.clone();
         match current_mode {
@@ -143,10 +571,62 @@ impl eframe::App for SiteMapperApp {
             self.show_proxy_window(ctx);
         }
 
+        if self.show_network_window {
+            self.show_network_window(ctx);
+        }
+
+        if self.show_intercept_window {
+            self.show_intercept_window(ctx);
+        }
+
+        if self.show_history_window {
+            self.show_history_window(ctx);
+        }
+
+        if self.show_spider_window {
+            self.show_spider_window(ctx);
+        }
+
+        if self.show_discovery_window {
+            self.show_discovery_window(ctx);
+        }
+
+        if self.show_fuzzer_window {
+            self.show_fuzzer_window(ctx);
+        }
+
+        if self.show_match_rules_window {
+            self.show_match_rules_window(ctx);
+        }
+
+        if self.show_findings_window {
+            self.show_findings_window(ctx);
+        }
+
+        if self.show_js_window {
+            self.show_js_window(ctx);
+        }
+
+        if self.show_audit_window {
+            self.show_audit_window(ctx);
+        }
+
+        if self.show_robots_window {
+            self.show_robots_window(ctx);
+        }
+
+        if self.show_link_check_window {
+            self.show_link_check_window(ctx);
+        }
+
         if self.show_thread_window {
             self.show_thread_window(ctx);
         }
 
+        if self.show_proxy_results_window {
+            self.show_proxy_results_window(ctx);
+        }
+
         if let Ok(action) = self.action_receiver.try_recv() {
             self.execute_action(action);
         }
@@ -168,8 +648,12 @@ impl SiteMapperApp {
                 if let (Some(selected_path), Some(db)) = (&self.selected_path, &self.db) {
                     let key = selected_path.join("/");
                     if let Some(_node_value) = file_processing::get_node_value(db, &key) {
+                        // Labels are read with the rows so drawing them does
+                        // not go back to the database.
                         let mut all_children = Vec::new();
-                        get_all_children(db, &key, &mut all_children);
+                        visit_endpoints(db, &key, self.tree_filter_result.as_deref(), &mut |url, node| {
+                            all_children.push((url, sources_label(&node.sources), tags_label(&node.tags)));
+                        });
                         all_children.sort(); 
                         if all_children.is_empty() {
                             ui.centered_and_justified(|ui| {
@@ -184,7 +668,7 @@ impl SiteMapperApp {
                                 )
                                 .show(ui, |ui| {
                                     let grid = egui::Grid::new(selected_path.join("/"));
-                                    grid.num_columns(3)
+                                    grid.num_columns(5)
                                         .striped(true)
                                         .min_col_width(100.0)
                                         .max_col_width(1150.0)
@@ -194,10 +678,12 @@ impl SiteMapperApp {
                                             ui.label("Extension");
                                             ui.set_min_width(100.0);
                                             ui.label("Parameters");
+                                            ui.label("Source");
+                                            ui.label("Tags");
                                             ui.end_row();
 
                                             
-                                            for endpoint in &all_children {
+                                            for (endpoint, sources, tags) in &all_children {
                                                 let full_url = endpoint.to_string();
                                                 let extension = self
                                                     .get_extension_from_url(&full_url)
@@ -206,6 +692,12 @@ impl SiteMapperApp {
                                                     .get_parameters_from_url(&full_url);
 
                                                 let response = ui.add(egui::SelectableLabel::new(false, &full_url));
+                                                if response.clicked() {
+                                                    let key = full_url
+                                                        .split_once("://")
+                                                        .map_or(full_url.as_str(), |(_, rest)| rest);
+                                                    let _ = self.action_sender.send(Action::ShowStoredResponse(key.to_string()));
+                                                }
                                                 response.context_menu(|ui| {
                                                     if ui.button("Send Request").clicked() {
                                                         let _ = self.action_sender.send(Action::SendRequest(full_url.clone()));
@@ -215,6 +707,12 @@ impl SiteMapperApp {
                                                         let _ = self.action_sender.send(Action::Copy(full_url.clone()));
                                                         ui.close_menu();
                                                     }
+                                                    for format in SnippetFormat::ALL {
+                                                        if ui.button(format.label()).clicked() {
+                                                            let _ = self.action_sender.send(Action::CopyAs(format, Some(full_url.clone())));
+                                                            ui.close_menu();
+                                                        }
+                                                    }
                                                     if ui.button("Delete").clicked() {
                                                         let _ = self.action_sender.send(Action::Delete(vec![endpoint.clone()]));
                                                         ui.close_menu();
@@ -231,6 +729,8 @@ impl SiteMapperApp {
                                                 } else {
                                                     ui.label("");
                                                 }
+                                                ui.label(sources);
+                                                ui.label(tags);
                                                 ui.end_row();
                                             }
                                         });
@@ -251,6 +751,9 @@ impl SiteMapperApp {
             ui.add_space(10.0);
 
             ui.group(|ui| {
+                if self.last_request_url.is_some() {
+                    self.show_request_options(ui);
+                }
                 match &mut self.right_panel_view {
                     RightPanelView::Empty => {
                         ui.centered_and_justified(|ui| {
@@ -282,16 +785,55 @@ impl SiteMapperApp {
                         ui.separator();
                         ui.horizontal(|ui| {
                             ui.heading("Response");
-                            if *language == "json" {
-                                if ui.button("Beautify").clicked() {
-                                    if let Ok(json) =
-                                        serde_json::from_str::<serde_json::Value>(raw_body)
-                                    {
-                                        if let Ok(pretty) = serde_json::to_string_pretty(&json) {
-                                            *pretty_body = pretty;
+                            if self.response_history.len() > 1 {
+                                let selected = self
+                                    .response_history
+                                    .get(self.response_history_index)
+                                    .map(history_entry_label)
+                                    .unwrap_or_default();
+                                egui::ComboBox::from_id_source("response_history")
+                                    .selected_text(selected)
+                                    .show_ui(ui, |ui| {
+                                        for (index, entry) in self.response_history.iter().enumerate() {
+                                            let is_current = index == self.response_history_index;
+                                            if ui.selectable_label(is_current, history_entry_label(entry)).clicked() {
+                                                let _ = self.action_sender.send(Action::ShowHistoryEntry(index));
+                                            }
                                         }
+                                    });
+                            }
+                            if ui.button("Fuzz").clicked() {
+                                let _ = self.action_sender.send(Action::ShowFuzzerWindow);
+                            }
+                            ui.menu_button("Copy as", |ui| {
+                                for format in SnippetFormat::ALL {
+                                    if ui.button(format.label()).clicked() {
+                                        let _ = self.action_sender.send(Action::CopyAs(format, None));
+                                        ui.close_menu();
                                     }
                                 }
+                            });
+                            if ui.button("Set Diff Base").clicked() {
+                                let _ = self.action_sender.send(Action::SetDiffBase);
+                            }
+                            if ui
+                                .add_enabled(self.diff_base.is_some(), egui::Button::new("Diff vs Base"))
+                                .clicked()
+                            {
+                                let _ = self.action_sender.send(Action::DiffAgainstBase);
+                            }
+                            if self.response_history_index + 1 < self.response_history.len()
+                                && ui.button("Diff vs Previous").clicked()
+                            {
+                                let _ = self.action_sender.send(Action::DiffWithPrevious);
+                            }
+                            if *language == "json"
+                                && ui.button("Beautify").clicked()
+                                && let Ok(json) = serde_json::from_str::<serde_json::Value>(raw_body)
+                                && let Ok(pretty) = serde_json::to_string_pretty(&json)
+                            {
+                                *pretty_body = pretty;
+                                self.response_highlights = None;
                             }
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                                 if ui.selectable_label(*is_pretty, "Pretty").clicked() {
@@ -307,7 +849,14 @@ impl SiteMapperApp {
                             .show(ui, |ui| {
                                 ui.code(headers);
                                 let body_to_show = if *is_pretty { pretty_body } else { raw_body };
-                                let job = self.highlighter.highlight(ui, language, body_to_show);
+                                let mut job = self.highlighter.highlight(ui, language, body_to_show);
+                                if self.response_highlights.as_ref().is_none_or(|(pretty, _)| pretty != is_pretty) {
+                                    self.response_highlights =
+                                        Some((*is_pretty, match_rules::highlight_ranges(body_to_show)));
+                                }
+                                if let Some((_, ranges)) = &self.response_highlights {
+                                    CodeTheme::mark_ranges(&mut job, ranges, MATCH_HIGHLIGHT_COLOR);
+                                }
                                 ui.label(job);
                             });
                         ui.separator();
@@ -315,6 +864,15 @@ impl SiteMapperApp {
                             ui.label(format!("{} bytes | {} ms", content_length, elapsed_ms));
                         });
                     }
+                    RightPanelView::Diff(response_diff) => {
+                        show_response_diff(
+                            ui,
+                            response_diff,
+                            &mut self.diff_noise_text,
+                            self.diff_noise_error.as_deref(),
+                            &self.action_sender,
+                        );
+                    }
                 }
             });
         });
@@ -330,6 +888,13 @@ impl SiteMapperApp {
             .show(ctx, |ui| {
                 ui.set_enabled(is_enabled);
                 ui.heading(format!("Sitemap ({} URLs)", self.total_url_count));
+                self.show_tree_filter_bar(ui);
+                let url_count_changed = self.tree_filter_url_count != self.total_url_count;
+                if self.tree_filter != self.tree_filter_applied
+                    || (url_count_changed && self.tree_filter_receiver.is_none())
+                {
+                    self.start_tree_filter();
+                }
                 ui.separator();
 
                 if let Some(db) = self.db.clone() {
@@ -347,401 +912,3131 @@ impl SiteMapperApp {
             .inner
     }
 
-    fn handle_proxy_receiver(&mut self, _ctx: &egui::Context) {
-        if let Some(receiver) = &self.proxy_receiver {
-            if let Ok(result) = receiver.try_recv() {
-                match result {
-                    Ok(_) => {
-                        self.error_message =
-                            Some("Request sent to proxy successfully.".to_string());
-                    }
-                    Err(e) => {
-                        self.error_message = Some(format!("Failed to send to proxy: {}", e));
+    fn show_tree_filter_bar(&mut self, ui: &mut egui::Ui) {
+        let filter = &mut self.tree_filter;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("tree_filter_mode")
+                .width(80.0)
+                .selected_text(filter.mode.label())
+                .show_ui(ui, |ui| {
+                    for mode in MatchMode::ALL {
+                        ui.selectable_value(&mut filter.mode, mode, mode.label());
                     }
+                });
+            let hint = match filter.mode {
+                MatchMode::Substring => "Filter URLs",
+                MatchMode::Glob => "e.g. *example.com/api/*.json",
+                MatchMode::Regex => r"e.g. /v\d+/users/",
+            };
+            ui.add(egui::TextEdit::singleline(&mut filter.text).hint_text(hint).desired_width(f32::INFINITY));
+        });
+        ui.horizontal(|ui| {
+            ui.toggle_value(&mut self.show_tree_filter_options, "More filters");
+            if self.tree_filter.is_active() {
+                if ui.small_button("Clear").clicked() {
+                    self.tree_filter = TreeFilter::default();
                 }
-                self.proxy_receiver = None;
+                if ui.small_button("Refresh").on_hover_text("Filter again, including URLs added since").clicked() {
+                    self.tree_filter_applied = TreeFilter::default();
+                }
+            }
+            if self.tree_filter_receiver.is_some() {
+                ui.spinner();
             }
+            if let Some(error) = &self.tree_filter_error {
+                ui.colored_label(egui::Color32::RED, error);
+            } else if let Some(result) = &self.tree_filter_result {
+                ui.label(format!("{} match{}", result.matches, if result.matches == 1 { "" } else { "es" }));
+            }
+        });
+        if self.show_tree_filter_options {
+            let filter = &mut self.tree_filter;
+            egui::Grid::new("tree_filter_options").num_columns(2).show(ui, |ui| {
+                ui.label("Extensions:");
+                ui.add(egui::TextEdit::singleline(&mut filter.extensions).hint_text("js, json"));
+                ui.end_row();
+                ui.label("Parameters:");
+                egui::ComboBox::from_id_source("tree_filter_parameters")
+                    .selected_text(filter.parameters.label())
+                    .show_ui(ui, |ui| {
+                        for parameters in ParameterFilter::ALL {
+                            ui.selectable_value(&mut filter.parameters, parameters, parameters.label());
+                        }
+                    });
+                ui.end_row();
+                ui.label("Scheme:");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut filter.scheme, String::new(), "any");
+                    ui.radio_value(&mut filter.scheme, "http".to_string(), "http");
+                    ui.radio_value(&mut filter.scheme, "https".to_string(), "https");
+                });
+                ui.end_row();
+                ui.label("Status:");
+                ui.add(egui::TextEdit::singleline(&mut filter.statuses).hint_text("200, 3xx"));
+                ui.end_row();
+                ui.label("Tag:");
+                ui.add(egui::TextEdit::singleline(&mut filter.tag).hint_text("any"));
+                ui.end_row();
+            });
         }
     }
 
-    fn handle_proxy_progress_receiver(&mut self, _ctx: &egui::Context) {
-        if let Some(receiver) = &self.proxy_progress_receiver {
-            if let Ok(progress) = receiver.try_recv() {
-                match progress {
-                    crate::proxy::ProxyProgress::Advanced(percent) => {
-                        self.progress = percent;
-                    }
-                    crate::proxy::ProxyProgress::Finished => {
-                        self.proxy_progress_receiver = None;
-                        self.error_message = Some("Sent all URLs to proxy.".to_string());
-                    }
-                    crate::proxy::ProxyProgress::Errored(err) => {
-                        self.proxy_progress_receiver = None;
-                        self.error_message = Some(format!("Failed to send to proxy: {}", err));
-                    }
-                }
-            }
+    /// Abandons the running filter scan, if any, and starts one for the
+    /// current filter.
+    fn start_tree_filter(&mut self) {
+        if let Some(cancel) = self.tree_filter_cancel.take() {
+            cancel.store(true, Ordering::SeqCst);
+        }
+        self.tree_filter_receiver = None;
+        self.tree_filter_error = None;
+        self.tree_filter_applied = self.tree_filter.clone();
+        self.tree_filter_url_count = self.total_url_count;
+        let Some(db) = &self.db else {
+            return;
+        };
+        if !self.tree_filter.is_active() {
+            self.tree_filter_result = None;
+            return;
         }
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.tree_filter_receiver = Some(tree_filter::spawn_filter_thread(
+            Arc::clone(db),
+            self.tree_filter.clone(),
+            Arc::clone(&cancel),
+        ));
+        self.tree_filter_cancel = Some(cancel);
     }
 
-    fn handle_network_receiver(&mut self, _ctx: &egui::Context) {
-        if let Some(receiver) = &self.network_receiver {
-            if let Ok((
-                request,
-                headers,
-                raw_body,
-                pretty_body,
-                language,
-                content_length,
-                elapsed_ms,
-            )) = receiver.try_recv()
-            {
-                self.right_panel_view = RightPanelView::Response(
-                    request,
-                    headers,
-                    raw_body,
-                    pretty_body,
-                    language,
-                    true,
-                    content_length,
-                    elapsed_ms,
-                );
-                self.network_receiver = None;
+    fn handle_tree_filter_receiver(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.tree_filter_receiver else {
+            return;
+        };
+        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        match receiver.try_recv() {
+            Ok(Ok(result)) => {
+                self.tree_filter_result = Some(Arc::new(result));
+                self.tree_filter_generation += 1;
             }
+            Ok(Err(error)) => {
+                self.tree_filter_result = None;
+                self.tree_filter_error = Some(error);
+            }
+            Err(std::sync::mpsc::TryRecvError::Empty) => return,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => {}
         }
+        self.tree_filter_receiver = None;
+        self.tree_filter_cancel = None;
     }
 
-    fn handle_save_receiver(&mut self, _ctx: &egui::Context) {
-        if let Some(receiver) = &self.save_receiver {
-            if let Ok(progress) = receiver.try_recv() {
-                match progress {
-                    SaveProgress::Finished => {
-                        self.is_saving_file = false;
-                        self.error_message = Some("File saved successfully.".to_string());
-                    }
-                    SaveProgress::Errored(err) => {
-                        self.is_saving_file = false;
-                        self.error_message = Some(format!("Failed to save file: {}", err));
-                    }
+    fn handle_proxy_receiver(&mut self, _ctx: &egui::Context) {
+        if let Some(receiver) = &self.proxy_receiver
+            && let Ok(result) = receiver.try_recv()
+        {
+            match result {
+                Ok(_) => {
+                    self.error_message =
+                        Some("Request sent to proxy successfully.".to_string());
+                }
+                Err(e) => {
+                    self.error_message = Some(format!("Failed to send to proxy: {}", e));
                 }
-                self.save_receiver = None;
             }
+            self.proxy_receiver = None;
         }
     }
 
-    fn handle_file_receiver(&mut self, _ctx: &egui::Context) {
-        if let Some(receiver) = &self.file_receiver {
-            if let Ok(progress) = receiver.try_recv() {
+    fn handle_proxy_progress_receiver(&mut self, ctx: &egui::Context) {
+        if let Some(receiver) = &self.proxy_progress_receiver {
+            ctx.request_repaint();
+            let mut finished = false;
+            while let Ok(progress) = receiver.try_recv() {
                 match progress {
-                    Progress::Advanced(percent, time, count) => {
-                        self.progress = percent;
-                        self.time_remaining = time;
-                        self.total_url_count = count;
+                    ProxyProgress::Advanced {
+                        completed,
+                        total,
+                        rate,
+                        eta,
+                        result,
+                    } => {
+                        self.proxy_completed = completed;
+                        self.proxy_total = total;
+                        self.proxy_rate = rate;
+                        self.proxy_eta = eta;
+                        self.proxy_results.push(result);
                     }
-                    Progress::Finished(db, count) => {
-                        self.db = Some(db);
-                        self.total_url_count = count;
-                        self.is_loading_file = false;
+                    ProxyProgress::Finished => {
+                        let failed = self.proxy_results.iter().filter(|r| r.is_failure()).count();
+                        self.error_message = Some(format!(
+                            "Sent {} URLs to proxy ({} failed).",
+                            self.proxy_completed, failed
+                        ));
+                        finished = true;
                     }
-                    Progress::Errored(err) => {
-                        self.error_message = Some(err);
-                        self.is_loading_file = false;
+                    ProxyProgress::Errored(err) => {
+                        self.error_message = Some(format!("Failed to send to proxy: {}", err));
+                        finished = true;
                     }
                 }
             }
+            if finished {
+                self.proxy_progress_receiver = None;
+            }
         }
     }
 
-    fn execute_action(&mut self, action: Action) {
-        match action {
-            Action::Select(path) => {
-                self.selected_path = Some(path);
+    fn handle_proxy_test_receiver(&mut self, ctx: &egui::Context) {
+        if let Some(receiver) = &self.proxy_test_receiver {
+            ctx.request_repaint();
+            if let Ok(result) = receiver.try_recv() {
+                self.proxy_test_result = Some(result.map(|(status, elapsed)| {
+                    format!("Connected: HTTP {} in {} ms", status, elapsed.as_millis())
+                }));
+                self.proxy_test_receiver = None;
             }
-            Action::Delete(path) => {
-                if let Some(db) = &self.db {
-                    match delete_node_from_db(db, &path) {
-                        Ok(deleted_count) => {
-                            self.total_url_count -= deleted_count;
-                            self.error_message = Some(format!("Deleted {} URLs.", deleted_count));
-                            if self.selected_path.as_ref() == Some(&path) {
-                                self.selected_path = None;
-                            }
-                        }
-                        Err(e) => {
-                            self.error_message = Some(format!("Failed to delete: {}", e));
-                        }
-                    }
+        }
+    }
+
+    fn handle_fuzz_receiver(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.fuzz_receiver else {
+            return;
+        };
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        let mut finished = false;
+        let mut received = false;
+        while let Ok(progress) = receiver.try_recv() {
+            match progress {
+                FuzzProgress::Advanced { completed, total, result } => {
+                    self.fuzz_completed = completed;
+                    self.fuzz_total = total;
+                    self.fuzz_results.push(*result);
+                    received = true;
                 }
-            }
-            Action::Copy(url) => {
-                if let Some(clipboard) = &mut self.clipboard {
-                    if let Err(err) = clipboard.set_text(url) {
-                        self.error_message = Some(format!("Failed to copy URL: {}", err));
-                    }
+                FuzzProgress::Finished => {
+                    self.fuzz_status = Some(format!("Finished: {} requests", self.fuzz_completed));
+                    finished = true;
                 }
-            }
-            Action::SendRequest(url) => {
-                self.right_panel_view = RightPanelView::Loading;
-                self.network_receiver = Some(network::spawn_request_thread(url));
-            }
-            Action::ShowSaveDialog => {
-                self.show_save_dialog = true;
-            }
-            Action::SaveToFile(file_name) => {
-                if let Some(db) = &self.db {
-                    self.is_saving_file = true;
-                    self.error_message = None;
-                    let path = PathBuf::from(file_name);
-                    self.save_receiver = Some(file_saver::spawn_file_saving_thread(Arc::clone(db), path));
+                FuzzProgress::Errored(err) => {
+                    self.fuzz_status = Some(err);
+                    finished = true;
                 }
-                self.show_save_dialog = false;
             }
-            Action::SendToProxy(url) => {
-                let proxy_address = self.proxy_address.clone();
-                let (sender, receiver) = std::sync::mpsc::channel();
-                self.proxy_receiver = Some(receiver);
-                self.error_message = Some("Sending to proxy...".to_string());
-                std::thread::spawn(move || {
-                    let result = crate::proxy::send_to_proxy(&url, &proxy_address);
-                    let _ = sender.send(result);
-                });
+        }
+        if received {
+            self.sort_fuzz_results();
+        }
+        if finished {
+            self.fuzz_receiver = None;
+            self.fuzz_stop = None;
+        }
+    }
+
+    fn sort_fuzz_results(&mut self) {
+        let sort = self.fuzz_sort;
+        self.fuzz_results.sort_by(|a, b| {
+            let ordering = match sort {
+                FuzzSort::Index => a.index.cmp(&b.index),
+                FuzzSort::Payload => a.payloads.cmp(&b.payloads),
+                FuzzSort::Status => a.status.cmp(&b.status),
+                FuzzSort::Length => a.length.cmp(&b.length),
+                FuzzSort::Time => a.elapsed_ms.cmp(&b.elapsed_ms),
+                FuzzSort::Grep => a.matches.len().cmp(&b.matches.len()),
+            };
+            ordering.then(a.index.cmp(&b.index))
+        });
+        if !self.fuzz_sort_ascending {
+            self.fuzz_results.reverse();
+        }
+    }
+
+    fn start_fuzz(&mut self) {
+        let defaults = match fuzzer::position_defaults(&self.fuzz_template) {
+            Ok(defaults) if !defaults.is_empty() => defaults,
+            Ok(_) => {
+                self.fuzz_status = Some(format!("Mark at least one position with {}", fuzzer::MARKER));
+                return;
             }
-            Action::ShowProxyWindow => {
-                self.show_proxy_window = true;
+            Err(e) => {
+                self.fuzz_status = Some(e);
+                return;
             }
-            Action::ShowThreadWindow => {
-                self.show_thread_window = true;
+        };
+        let set_count = self.fuzz_mode.set_count(defaults.len());
+        let sets = match self.fuzz_sets[..set_count]
+            .iter()
+            .map(PayloadSet::payloads)
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(sets) => sets,
+            Err(e) => {
+                self.fuzz_status = Some(e);
+                return;
             }
-            Action::SaveDisplayedUrls => {
-                if let (Some(selected_path), Some(db)) = (&self.selected_path, &self.db) {
-                    let key = selected_path.join("/");
-                    let mut all_children = Vec::new();
-                    get_all_children(db, &key, &mut all_children);
-                    all_children.sort();
+        };
 
-                    let mut file_name = self.save_file_name.clone();
-                    if !file_name.ends_with(".txt") {
-                        file_name.push_str(".txt");
-                    }
+        let attack = Attack {
+            mode: self.fuzz_mode,
+            defaults,
+            sets,
+        };
+        if let Err(e) = attack.len() {
+            self.fuzz_status = Some(e);
+            return;
+        }
 
-                    match std::fs::File::create(&file_name) {
-                        Ok(mut file) => {
-                            for url in all_children {
-                                if let Err(e) = writeln!(file, "{}", url) {
-                                    self.error_message = Some(format!("Failed to write to file: {}", e));
-                                    return;
-                                }
-                            }
-                            self.error_message = Some(format!("Saved to {}", file_name));
-                        }
-                        Err(e) => {
-                            self.error_message = Some(format!("Failed to create file: {}", e));
-                        }
-                    }
-                }
-            }
-            Action::SendDisplayedUrlsToProxy(threads) => {
-                if let (Some(selected_path), Some(db)) = (&self.selected_path, &self.db) {
-                    let key = selected_path.join("/");
-                    let mut all_children = Vec::new();
-                    get_all_children(db, &key, &mut all_children);
+        let stop = Arc::new(AtomicBool::new(false));
+        self.fuzz_receiver = Some(fuzzer::spawn_fuzz_thread(
+            FuzzOptions {
+                template: self.fuzz_template.clone(),
+                scheme: self.fuzz_scheme.clone(),
+                attack,
+                grep: self
+                    .fuzz_grep
+                    .lines()
+                    .map(str::trim)
+                    .filter(|term| !term.is_empty())
+                    .map(String::from)
+                    .collect(),
+                threads: self.proxy_threads,
+                rate_limit: self.rate_limit,
+                network: self.network_config.clone(),
+            },
+            Arc::clone(&stop),
+        ));
+        self.fuzz_stop = Some(stop);
+        self.fuzz_completed = 0;
+        self.fuzz_total = 0;
+        self.fuzz_results.clear();
+        self.fuzz_status = Some("Running...".to_string());
+    }
 
-                    self.proxy_progress_receiver = Some(crate::proxy::spawn_proxy_thread(
-                        all_children,
-                        self.proxy_address.clone(),
-                        threads,
-                    ));
+    fn handle_js_receiver(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.js_receiver else {
+            return;
+        };
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        let mut finished = false;
+        while let Ok(progress) = receiver.try_recv() {
+            match progress {
+                JsProgress::Extracted {
+                    url,
+                    found,
+                    new_endpoints,
+                    source_map,
+                } => {
+                    self.js_results.retain(|result| result.script != url);
+                    self.js_results.insert(
+                        0,
+                        JsExtraction {
+                            script: url,
+                            found,
+                            new_endpoints,
+                            source_map,
+                        },
+                    );
+                }
+                JsProgress::Finished(new_endpoints) => {
+                    self.js_status = Some(format!("Finished: {} new endpoints added", new_endpoints));
+                    self.total_url_count += new_endpoints;
+                    finished = true;
+                }
+                JsProgress::Errored(err) => {
+                    self.js_status = Some(err);
                 }
             }
         }
+        if finished {
+            self.js_receiver = None;
+        }
     }
 
-    fn get_parameters_from_url(&self, url_str: &str) -> String {
-        if let Ok(url) = Url::parse(url_str) {
-            if let Some(query) = url.query() {
-                return query.to_string();
-            }
+    fn show_js_window(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
+        let mut action = None;
+        egui::Window::new("JS Endpoints")
+            .open(&mut is_open)
+            .resizable(true)
+            .default_width(700.0)
+            .default_height(500.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if self.js_receiver.is_some() {
+                        ui.spinner();
+                    }
+                    if let Some(status) = &self.js_status {
+                        ui.label(status);
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .id_source("js_endpoints_scroll")
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for result in &self.js_results {
+                            egui::CollapsingHeader::new(format!(
+                                "{} ({} found, {} new)",
+                                result.script,
+                                result.found.len(),
+                                result.new_endpoints
+                            ))
+                            .id_source(("js_result", &result.script))
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                ui.label(format!(
+                                    "Source map: {}",
+                                    result.source_map.as_deref().unwrap_or("none")
+                                ));
+                                for url in &result.found {
+                                    if ui.link(url).on_hover_text("Show in the sitemap").clicked()
+                                        && let Some(key) = response_store::endpoint_key(url)
+                                    {
+                                        action = Some(Action::RevealInTree(
+                                            key.split('/').map(String::from).collect(),
+                                        ));
+                                    }
+                                }
+                            });
+                        }
+                    });
+            });
+        if !is_open {
+            self.show_js_window = false;
+        }
+        if let Some(action) = action {
+            self.execute_action(action);
         }
-        String::new()
     }
 
-    fn get_extension_from_url<'a>(&self, url: &'a str) -> Option<&'a str> {
-        let path = url.split('/').last().unwrap_or("");
-        if !path.contains('.') {
-            return None;
-        }
-        let parts: Vec<&str> = path.split('.').collect();
-        if parts.len() > 1 {
-            let ext = parts.last().unwrap_or(&"");
-            if ext.contains('?') {
-                let ext_parts: Vec<&str> = ext.split('?').collect();
-                return Some(ext_parts[0]);
-            }
-            if ext.contains('#') {
-                let ext_parts: Vec<&str> = ext.split('#').collect();
-                return Some(ext_parts[0]);
+    fn handle_link_check_receiver(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.link_check_receiver else {
+            return;
+        };
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        let mut finished = false;
+        while let Ok(progress) = receiver.try_recv() {
+            match progress {
+                LinkCheckProgress::Advanced { completed, total } => {
+                    self.link_check_completed = completed;
+                    self.link_check_total = total;
+                }
+                LinkCheckProgress::Finished { pages, links, broken } => {
+                    self.link_check_status = Some(format!(
+                        "Finished: {} pages, {} links, {} broken targets",
+                        pages, links, broken
+                    ));
+                    finished = true;
+                }
+                LinkCheckProgress::Errored(err) => {
+                    self.link_check_status = Some(err);
+                    finished = true;
+                }
             }
-            return Some(ext);
         }
-        None
+        if finished {
+            self.link_check_receiver = None;
+            self.link_check_stop = None;
+            self.broken_links = self.db.as_ref().map(|db| link_checker::broken(db)).unwrap_or_default();
+        }
     }
 
-    fn start_file_processing(&mut self, path: PathBuf) {
-        self.is_loading_file = true;
-        self.error_message = None;
-        self.progress = 0.0;
-        self.total_url_count = 0;
-        self.db = None; 
-        self.file_receiver = Some(file_processing::spawn_file_processing_thread(path));
-        self.app_mode = AppMode::Main;
+    fn start_link_check(&mut self) {
+        let Some(db) = &self.db else {
+            return;
+        };
+        let key = self.link_check_root.join("/");
+        let mut start_urls = Vec::new();
+        get_all_children(db, &key, None, &mut start_urls);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        self.link_check_receiver = Some(link_checker::spawn_link_check_thread(
+            Arc::clone(db),
+            LinkCheckOptions {
+                start_urls,
+                external: self.link_check_external,
+                threads: self.proxy_threads,
+                rate_limit: self.rate_limit,
+                network: self.network_config.clone(),
+            },
+            Arc::clone(&stop),
+        ));
+        self.link_check_stop = Some(stop);
+        self.link_check_completed = 0;
+        self.link_check_total = 0;
+        self.link_check_status = Some("Running...".to_string());
     }
 
-    
-    fn show_top_panel(&mut self, ctx: &egui::Context, is_enabled: bool) -> Option<Action> {
+    fn show_link_check_window(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
         let mut action = None;
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            ui.set_enabled(is_enabled);
-            ui.horizontal(|ui| {
+        egui::Window::new("Link Checker")
+            .open(&mut is_open)
+            .resizable(true)
+            .default_width(700.0)
+            .default_height(500.0)
+            .show(ctx, |ui| {
+                let is_running = self.link_check_receiver.is_some();
+                ui.label(format!("Pages under: {}/", self.link_check_root.join("/")));
+                ui.add_enabled_ui(!is_running, |ui| {
+                    ui.checkbox(&mut self.link_check_external, "Check links to other hosts");
+                });
+                ui.label(format!(
+                    "Uses {} threads and {} from the Threads window. Redirects are followed.",
+                    self.proxy_threads,
+                    rate_limit_label(self.rate_limit)
+                ));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if is_running {
+                        if ui.button("Stop").clicked()
+                            && let Some(stop) = &self.link_check_stop
+                        {
+                            stop.store(true, Ordering::SeqCst);
+                        }
+                    } else if ui.add_enabled(self.db.is_some(), egui::Button::new("Start")).clicked() {
+                        self.start_link_check();
+                    }
+                    if let Some(status) = &self.link_check_status {
+                        ui.label(status);
+                    }
+                });
+                if self.link_check_total > 0 {
+                    ui.add(
+                        egui::ProgressBar::new(self.link_check_completed as f32 / self.link_check_total as f32)
+                            .text(format!("{}/{}", self.link_check_completed, self.link_check_total)),
+                    );
+                }
+                ui.horizontal(|ui| {
+                    ui.label("CSV file:");
+                    ui.text_edit_singleline(&mut self.link_check_csv);
+                    let can_export = !is_running && !self.broken_links.is_empty();
+                    if ui.add_enabled(can_export, egui::Button::new("Export CSV")).clicked() {
+                        let path = PathBuf::from(&self.link_check_csv);
+                        self.link_check_status = Some(match link_checker::write_csv(&path, &self.broken_links) {
+                            Ok(rows) => format!("Saved {} rows to {}", rows, path.display()),
+                            Err(e) => e,
+                        });
+                    }
+                });
+                ui.separator();
+                ui.label(format!("{} broken targets", self.broken_links.len()));
+                egui::ScrollArea::vertical()
+                    .id_source("broken_links_scroll")
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for link in &self.broken_links {
+                            egui::CollapsingHeader::new(format!(
+                                "{} {} ({} pages)",
+                                link.status.label(),
+                                link.target,
+                                link.referrers.len()
+                            ))
+                            .id_source(("broken_link", &link.target))
+                            .show(ui, |ui| {
+                                for referrer in &link.referrers {
+                                    if ui.link(referrer).on_hover_text("Show in the sitemap").clicked()
+                                        && let Some(key) = response_store::endpoint_key(referrer)
+                                    {
+                                        action = Some(Action::RevealInTree(
+                                            key.split('/').map(String::from).collect(),
+                                        ));
+                                    }
+                                }
+                            });
+                        }
+                    });
+            });
+        if !is_open {
+            self.show_link_check_window = false;
+        }
+        if let Some(action) = action {
+            self.execute_action(action);
+        }
+    }
+
+    fn handle_robots_receiver(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.robots_receiver else {
+            return;
+        };
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        let mut finished = false;
+        while let Ok(progress) = receiver.try_recv() {
+            match progress {
+                RobotsProgress::Checked(report) => {
+                    self.robots_reports.retain(|known| known.host != report.host);
+                    self.robots_reports.push(*report);
+                    self.robots_reports.sort_by(|a, b| a.host.cmp(&b.host));
+                }
+                RobotsProgress::Finished(new_endpoints) => {
+                    let blocked: usize = self.robots_reports.iter().map(|report| report.blocked.len()).sum();
+                    self.robots_status = Some(format!(
+                        "Finished: {} new endpoints added, {} endpoints disallowed",
+                        new_endpoints, blocked
+                    ));
+                    self.total_url_count += new_endpoints;
+                    finished = true;
+                }
+                RobotsProgress::Errored(err) => {
+                    self.robots_status = Some(err);
+                }
+            }
+        }
+        if finished {
+            self.robots_receiver = None;
+        }
+    }
+
+    /// Every host in the tree as a root URL, for checking robots.txt.
+    fn host_roots(&self) -> Vec<String> {
+        let Some(db) = &self.db else {
+            return Vec::new();
+        };
+        let mut hosts = file_processing::get_children(db, "__ROOT__");
+        hosts.sort();
+        hosts
+            .into_iter()
+            .map(|host| {
+                let scheme = file_processing::get_node_value(db, &host)
+                    .and_then(|node| node.scheme)
+                    .unwrap_or_else(|| "https".to_string());
+                format!("{}://{}/", scheme, host)
+            })
+            .collect()
+    }
+
+    fn show_robots_window(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
+        let mut action = None;
+        egui::Window::new("robots.txt")
+            .open(&mut is_open)
+            .resizable(true)
+            .default_width(700.0)
+            .default_height(500.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("User agent:");
+                    ui.text_edit_singleline(&mut self.robots_user_agent);
+                    let idle = self.robots_receiver.is_none();
+                    if ui.add_enabled(idle, egui::Button::new("Check all hosts")).clicked() {
+                        action = Some(Action::CheckRobots(self.host_roots()));
+                    }
+                });
+                ui.label("Endpoints the user agent may not fetch are tagged \"robots-blocked\".");
+                ui.horizontal(|ui| {
+                    if self.robots_receiver.is_some() {
+                        ui.spinner();
+                    }
+                    if let Some(status) = &self.robots_status {
+                        ui.label(status);
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .id_source("robots_scroll")
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        if self.robots_reports.is_empty() {
+                            ui.label("No hosts checked yet. Right-click a host and choose \"Check robots.txt\".");
+                        }
+                        for report in &self.robots_reports {
+                            let status = report.status.map_or("no response".to_string(), |status| status.to_string());
+                            egui::CollapsingHeader::new(format!(
+                                "{} ({}, {} disallowed for {})",
+                                report.host,
+                                status,
+                                report.blocked.len(),
+                                report.user_agent
+                            ))
+                            .id_source(("robots_report", &report.host))
+                            .show(ui, |ui| {
+                                ui.label(format!("{} ({} new endpoints added)", report.url, report.new_endpoints));
+                                if let Some(delay) = report.crawl_delay {
+                                    ui.label(format!("Crawl-delay: {} s", delay));
+                                }
+                                for (agents, rules) in &report.groups {
+                                    ui.label(format!("User-agent: {} ({} rules)", agents, rules));
+                                }
+                                for (heading, urls) in [("Sitemaps", &report.sitemaps), ("Disallowed paths", &report.disallowed)] {
+                                    if !urls.is_empty() {
+                                        ui.strong(heading);
+                                        for url in urls {
+                                            ui.monospace(url);
+                                        }
+                                    }
+                                }
+                                if !report.blocked.is_empty() {
+                                    ui.strong("Disallowed endpoints");
+                                    for key in &report.blocked {
+                                        if ui.link(key).on_hover_text("Show in the sitemap").clicked() {
+                                            action = Some(Action::RevealInTree(key.split('/').map(String::from).collect()));
+                                        }
+                                    }
+                                }
+                            });
+                        }
+                    });
+            });
+        if !is_open {
+            self.show_robots_window = false;
+        }
+        if let Some(action) = action {
+            self.execute_action(action);
+        }
+    }
+
+    fn refresh_audit(&mut self) {
+        self.audit_pages = self.db.as_ref().map(|db| seo::all(db)).unwrap_or_default();
+        self.audit_hosts = seo::summarize(&self.audit_pages);
+        if self
+            .audit_host
+            .as_ref()
+            .is_none_or(|host| !self.audit_hosts.iter().any(|summary| &summary.host == host))
+        {
+            self.audit_host = self.audit_hosts.first().map(|summary| summary.host.clone());
+        }
+    }
+
+    fn handle_audit_receiver(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.audit_receiver else {
+            return;
+        };
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        let mut finished = false;
+        while let Ok(progress) = receiver.try_recv() {
+            match progress {
+                AuditProgress::Advanced { completed, total } => {
+                    self.audit_completed = completed;
+                    self.audit_total = total;
+                }
+                AuditProgress::Finished(pages) => {
+                    self.audit_status = Some(format!("Audited {} HTML pages", pages));
+                    finished = true;
+                }
+                AuditProgress::Errored(err) => {
+                    self.audit_status = Some(err);
+                    finished = true;
+                }
+            }
+        }
+        if finished {
+            self.audit_receiver = None;
+            self.audit_stop = None;
+            self.refresh_audit();
+        }
+    }
+
+    fn show_audit_window(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
+        let mut action = None;
+        egui::Window::new("SEO Audit")
+            .open(&mut is_open)
+            .resizable(true)
+            .default_width(850.0)
+            .default_height(550.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if self.audit_receiver.is_some() {
+                        if ui.button("Stop").clicked()
+                            && let Some(stop) = &self.audit_stop
+                        {
+                            stop.store(true, Ordering::SeqCst);
+                        }
+                        ui.add(
+                            egui::ProgressBar::new(self.audit_completed as f32 / self.audit_total.max(1) as f32)
+                                .text(format!("{}/{}", self.audit_completed, self.audit_total))
+                                .desired_width(200.0),
+                        );
+                    } else if ui
+                        .button("Run Audit")
+                        .on_hover_text("Audits the latest stored response of every HTML page")
+                        .clicked()
+                        && let Some(db) = &self.db
+                    {
+                        let stop = Arc::new(AtomicBool::new(false));
+                        self.audit_receiver = Some(seo::spawn_audit_thread(Arc::clone(db), Arc::clone(&stop)));
+                        self.audit_stop = Some(stop);
+                        self.audit_completed = 0;
+                        self.audit_total = 0;
+                        self.audit_status = None;
+                    }
+                    if let Some(status) = &self.audit_status {
+                        ui.label(status);
+                    }
+                });
+                ui.separator();
+
+                egui::Grid::new("audit_hosts_grid")
+                    .num_columns(5)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Host");
+                        ui.strong("Pages");
+                        for severity in Severity::ALL {
+                            ui.strong(format!("{}s", severity.label()));
+                        }
+                        ui.end_row();
+                        for summary in &self.audit_hosts {
+                            let is_selected = self.audit_host.as_ref() == Some(&summary.host);
+                            if ui.selectable_label(is_selected, &summary.host).clicked() {
+                                self.audit_host = Some(summary.host.clone());
+                            }
+                            ui.label(summary.pages.to_string());
+                            for (count, severity) in summary.counts.iter().zip(Severity::ALL) {
+                                ui.colored_label(severity_color(severity), count.to_string());
+                            }
+                            ui.end_row();
+                        }
+                    });
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("Show:");
+                    for (shown, severity) in self.audit_severities.iter_mut().zip(Severity::ALL) {
+                        ui.checkbox(shown, severity.label());
+                    }
+                });
+                let rows: Vec<(&PageAudit, &seo::Issue)> = self
+                    .audit_pages
+                    .iter()
+                    .filter(|page| self.audit_host.as_ref() == Some(&page.host))
+                    .flat_map(|page| page.issues.iter().map(move |issue| (page, issue)))
+                    .filter(|(_, issue)| {
+                        Severity::ALL
+                            .iter()
+                            .position(|severity| *severity == issue.severity)
+                            .is_some_and(|index| self.audit_severities[index])
+                    })
+                    .collect();
+
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::both()
+                    .id_source("audit_issues_scroll")
+                    .auto_shrink([false, false])
+                    .show_rows(ui, row_height, rows.len(), |ui, row_range| {
+                        egui::Grid::new("audit_issues_grid")
+                            .num_columns(4)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (page, issue) in &rows[row_range] {
+                                    ui.colored_label(severity_color(issue.severity), issue.severity.label());
+                                    ui.label(&issue.check);
+                                    if ui.link(&page.url).on_hover_text("Show in the sitemap").clicked() {
+                                        action = Some(Action::RevealInTree(
+                                            page.key.split('/').map(String::from).collect(),
+                                        ));
+                                    }
+                                    ui.label(&issue.detail);
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        if !is_open {
+            self.show_audit_window = false;
+        }
+        if let Some(action) = action {
+            self.execute_action(action);
+        }
+    }
+
+    fn refresh_findings(&mut self) {
+        self.findings = self.db.as_ref().map(|db| secrets::all(db)).unwrap_or_default();
+    }
+
+    fn handle_findings_receiver(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.findings_receiver else {
+            return;
+        };
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        if let Ok(result) = receiver.try_recv() {
+            self.findings_status = Some(match result {
+                Ok(count) => format!("Scan finished: {} endpoints with findings", count),
+                Err(e) => format!("Scan failed: {}", e),
+            });
+            self.findings_receiver = None;
+            self.refresh_findings();
+        }
+    }
+
+    fn show_findings_window(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
+        let mut action = None;
+        egui::Window::new("Findings")
+            .open(&mut is_open)
+            .resizable(true)
+            .default_width(800.0)
+            .default_height(500.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Refresh").clicked() {
+                        self.refresh_findings();
+                    }
+                    if ui
+                        .add_enabled(self.findings_receiver.is_none(), egui::Button::new("Rescan Stored Responses"))
+                        .clicked()
+                        && let Some(db) = &self.db
+                    {
+                        self.findings_receiver = Some(secrets::spawn_scan_thread(Arc::clone(db)));
+                        self.findings_status = Some("Scanning...".to_string());
+                    }
+                    if ui
+                        .button("Reload Rules")
+                        .on_hover_text("Built-in rules plus Name = regex lines from secret_rules.txt next to the executable")
+                        .clicked()
+                    {
+                        self.findings_status = Some(match secrets::reload_rules() {
+                            Ok(count) => format!("Loaded {} user rules", count),
+                            Err(e) => e,
+                        });
+                    }
+                    ui.label("Filter:");
+                    ui.text_edit_singleline(&mut self.findings_filter);
+                });
+                if let Some(status) = &self.findings_status {
+                    ui.label(status);
+                }
+                ui.separator();
+
+                let filter = self.findings_filter.to_lowercase();
+                let rows: Vec<(&EndpointFindings, &secrets::Finding)> = self
+                    .findings
+                    .iter()
+                    .flat_map(|endpoint| endpoint.findings.iter().map(move |finding| (endpoint, finding)))
+                    .filter(|(endpoint, finding)| {
+                        filter.is_empty()
+                            || endpoint.url.to_lowercase().contains(&filter)
+                            || finding.rule.to_lowercase().contains(&filter)
+                            || finding.evidence.to_lowercase().contains(&filter)
+                    })
+                    .collect();
+                ui.label(format!("{} findings", rows.len()));
+
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::both()
+                    .id_source("findings_scroll")
+                    .auto_shrink([false, false])
+                    .show_rows(ui, row_height, rows.len(), |ui, row_range| {
+                        egui::Grid::new("findings_grid")
+                            .num_columns(4)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (endpoint, finding) in &rows[row_range] {
+                                    if ui
+                                        .link(&endpoint.url)
+                                        .on_hover_text("Show in the sitemap")
+                                        .clicked()
+                                    {
+                                        action = Some(Action::RevealInTree(
+                                            endpoint.key.split('/').map(String::from).collect(),
+                                        ));
+                                    }
+                                    ui.label(&finding.rule);
+                                    ui.label(format!("line {} @{}", finding.line, finding.offset));
+                                    ui.monospace(&finding.evidence);
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        if !is_open {
+            self.show_findings_window = false;
+        }
+        if let Some(action) = action {
+            self.execute_action(action);
+        }
+    }
+
+    fn handle_match_rules_receiver(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.match_rules_receiver else {
+            return;
+        };
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        if let Ok(result) = receiver.try_recv() {
+            self.match_rules_status = Some(match result {
+                Ok(matched) => format!("Applied to stored responses: {} endpoints matched", matched),
+                Err(e) => format!("Failed to apply rules: {}", e),
+            });
+            self.match_rules_receiver = None;
+        }
+    }
+
+    /// Re-tags the stored responses, removing `stale_tags` from the tree.
+    fn rescan_match_rules(&mut self, stale_tags: Vec<String>) {
+        if let Some(db) = &self.db {
+            self.match_rules_receiver = Some(match_rules::spawn_rescan_thread(Arc::clone(db), stale_tags));
+            self.match_rules_status = Some("Applying to stored responses...".to_string());
+        }
+    }
+
+    fn show_match_rules_window(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
+        egui::Window::new("Match Rules")
+            .open(&mut is_open)
+            .resizable(true)
+            .default_width(700.0)
+            .show(ctx, |ui| {
+                ui.label("Every stored response is checked against the enabled rules; matching endpoints get the rule's tag and matches are highlighted in the body.");
+                ui.separator();
+                let mut removed = None;
+                egui::Grid::new("match_rules_grid")
+                    .num_columns(6)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("On");
+                        ui.strong("Type");
+                        ui.strong("Header");
+                        ui.strong("Pattern");
+                        ui.strong("Tag");
+                        ui.end_row();
+                        for (index, rule) in self.match_rules.iter_mut().enumerate() {
+                            ui.checkbox(&mut rule.enabled, "");
+                            egui::ComboBox::from_id_source(("match_rule_kind", index))
+                                .selected_text(rule.kind.label())
+                                .show_ui(ui, |ui| {
+                                    for kind in RuleKind::ALL {
+                                        ui.selectable_value(&mut rule.kind, kind, kind.label());
+                                    }
+                                });
+                            ui.add_enabled(
+                                rule.kind == RuleKind::Header,
+                                egui::TextEdit::singleline(&mut rule.header)
+                                    .hint_text("Server")
+                                    .desired_width(100.0),
+                            );
+                            let hint = match rule.kind {
+                                RuleKind::Text => "stack trace",
+                                RuleKind::Regex => "Index of /",
+                                RuleKind::Header => "regex, empty = present",
+                            };
+                            ui.add(
+                                egui::TextEdit::singleline(&mut rule.pattern)
+                                    .hint_text(hint)
+                                    .desired_width(250.0),
+                            );
+                            ui.add(egui::TextEdit::singleline(&mut rule.tag).desired_width(120.0));
+                            if ui.button("🗑").clicked() {
+                                removed = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if let Some(index) = removed {
+                    self.match_rules.remove(index);
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Add Rule").clicked() {
+                        self.match_rules.push(MatchRule::default());
+                    }
+                    if ui.button("Save").clicked() {
+                        match match_rules::save(&self.match_rules) {
+                            Ok(stale_tags) => {
+                                self.match_rules_status = Some("Saved".to_string());
+                                self.response_highlights = None;
+                                if self.match_rules_receiver.is_none() {
+                                    self.rescan_match_rules(stale_tags);
+                                }
+                            }
+                            Err(e) => self.match_rules_status = Some(e),
+                        }
+                    }
+                    if ui
+                        .add_enabled(
+                            self.db.is_some() && self.match_rules_receiver.is_none(),
+                            egui::Button::new("Apply to Stored Responses"),
+                        )
+                        .clicked()
+                    {
+                        self.rescan_match_rules(Vec::new());
+                    }
+                });
+                if let Some(status) = &self.match_rules_status {
+                    ui.label(status);
+                }
+            });
+        if !is_open {
+            self.show_match_rules_window = false;
+        }
+    }
+
+    fn handle_calibration_receiver(&mut self, ctx: &egui::Context) {
+        if let Some(receiver) = &self.calibration_receiver {
+            ctx.request_repaint();
+            if let Ok(result) = receiver.try_recv() {
+                self.error_message = Some(match result {
+                    Ok(fingerprints) => format!(
+                        "Calibrated: {} distinct not-found responses. Matching endpoints are tagged soft-404.",
+                        fingerprints
+                    ),
+                    Err(e) => format!("Calibration failed: {}", e),
+                });
+                self.calibration_receiver = None;
+            }
+        }
+    }
+
+    fn handle_pool_check_receiver(&mut self, ctx: &egui::Context) {
+        if let Some(receiver) = &self.pool_check_receiver {
+            ctx.request_repaint();
+            while let Ok(progress) = receiver.try_recv() {
+                match progress {
+                    HealthCheckProgress::Checked(address, Err(e)) => {
+                        self.proxy_test_result = Some(Err(format!("{}: {}", address, e)));
+                    }
+                    HealthCheckProgress::Checked(..) => {}
+                    HealthCheckProgress::Finished(alive, dead) => {
+                        self.proxy_test_result = Some(Ok(format!(
+                            "Health check finished: {} alive, {} dead",
+                            alive, dead
+                        )));
+                        self.pool_check_receiver = None;
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_spider_receiver(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.spider_receiver else {
+            return;
+        };
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        let mut finished = false;
+        while let Ok(progress) = receiver.try_recv() {
+            match progress {
+                SpiderProgress::Fetched {
+                    url,
+                    status,
+                    new_endpoints,
+                    pages,
+                    queued,
+                } => {
+                    self.spider_pages = pages;
+                    self.spider_queued = queued;
+                    self.spider_new_endpoints += new_endpoints;
+                    self.total_url_count += new_endpoints;
+                    let status = status.map_or("error".to_string(), |s| s.to_string());
+                    self.spider_log.push(format!("{} {} (+{})", status, url, new_endpoints));
+                }
+                SpiderProgress::Blocked(url) => {
+                    self.spider_log.push(format!("robots.txt {}", url));
+                }
+                SpiderProgress::Finished(pages) => {
+                    self.spider_status = Some(format!("Finished after {} pages", pages));
+                    finished = true;
+                }
+                SpiderProgress::Errored(err) => {
+                    self.spider_status = Some(err);
+                    finished = true;
+                }
+            }
+        }
+        if finished {
+            self.spider_receiver = None;
+            self.spider_stop = None;
+        }
+    }
+
+    fn handle_discovery_receiver(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.discovery_receiver else {
+            return;
+        };
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        let mut finished = false;
+        while let Ok(progress) = receiver.try_recv() {
+            match progress {
+                DiscoveryProgress::Advanced { completed, total, hit } => {
+                    self.discovery_completed = completed;
+                    self.discovery_total = total;
+                    if let Some(hit) = hit {
+                        if hit.is_new {
+                            self.total_url_count += 1;
+                        }
+                        self.discovery_hits.push(hit);
+                    }
+                }
+                DiscoveryProgress::Calibrated(Ok(fingerprints)) => {
+                    self.discovery_status = Some(format!(
+                        "Calibrated ({} not-found responses), running...",
+                        fingerprints
+                    ));
+                }
+                DiscoveryProgress::Calibrated(Err(err)) => {
+                    self.discovery_status = Some(format!("Calibration failed ({}), running...", err));
+                }
+                DiscoveryProgress::Finished { hits, soft_404s } => {
+                    self.discovery_status = Some(format!(
+                        "Finished with {} hits, {} soft-404s dropped",
+                        hits, soft_404s
+                    ));
+                    finished = true;
+                }
+                DiscoveryProgress::Errored(err) => {
+                    self.discovery_status = Some(err);
+                    finished = true;
+                }
+            }
+        }
+        if finished {
+            self.discovery_receiver = None;
+            self.discovery_stop = None;
+        }
+    }
+
+    fn start_discovery(&mut self) {
+        let Some(db) = &self.db else {
+            return;
+        };
+        let filters = match DiscoveryFilters::parse(
+            &self.discovery_statuses,
+            &self.discovery_hide_sizes,
+            &self.discovery_hide_words,
+        ) {
+            Ok(filters) => filters,
+            Err(e) => {
+                self.discovery_status = Some(e);
+                return;
+            }
+        };
+        let key = self.discovery_root.join("/");
+        let scheme = file_processing::get_node_value(db, &key)
+            .and_then(|n| n.scheme)
+            .or_else(|| {
+                self.discovery_root
+                    .first()
+                    .and_then(|host| file_processing::get_node_value(db, host))
+                    .and_then(|n| n.scheme)
+            })
+            .unwrap_or_else(|| "https".to_string());
+
+        let stop = Arc::new(AtomicBool::new(false));
+        self.discovery_receiver = Some(discovery::spawn_discovery_thread(
+            Arc::clone(db),
+            DiscoveryOptions {
+                base_url: format!("{}://{}", scheme, key),
+                wordlist: PathBuf::from(&self.discovery_wordlist),
+                extensions: self
+                    .discovery_extensions
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|ext| !ext.is_empty())
+                    .map(String::from)
+                    .collect(),
+                filters,
+                threads: self.proxy_threads,
+                rate_limit: self.rate_limit,
+                network: self.network_config.clone(),
+            },
+            Arc::clone(&stop),
+        ));
+        self.discovery_stop = Some(stop);
+        self.discovery_completed = 0;
+        self.discovery_total = 0;
+        self.discovery_hits.clear();
+        self.discovery_status = Some("Running...".to_string());
+    }
+
+    fn start_spider(&mut self) {
+        let Some(db) = &self.db else {
+            return;
+        };
+        let key = self.spider_root.join("/");
+        let scheme = file_processing::get_node_value(db, &key)
+            .and_then(|n| n.scheme)
+            .unwrap_or_else(|| "https".to_string());
+        let mut start_urls = vec![format!("{}://{}", scheme, key)];
+        get_all_children(db, &key, None, &mut start_urls);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        self.spider_receiver = Some(spider::spawn_spider_thread(
+            Arc::clone(db),
+            SpiderOptions {
+                start_urls,
+                max_depth: self.spider_max_depth,
+                max_pages: self.spider_max_pages,
+                respect_robots: self.spider_respect_robots,
+                threads: self.proxy_threads,
+                rate_limit: self.rate_limit,
+                network: self.network_config.clone(),
+            },
+            Arc::clone(&stop),
+        ));
+        self.spider_stop = Some(stop);
+        self.spider_pages = 0;
+        self.spider_queued = 0;
+        self.spider_new_endpoints = 0;
+        self.spider_log.clear();
+        self.spider_status = Some("Crawling...".to_string());
+    }
+
+    /// Starts the intercepting proxy, reopening the previous project (or
+    /// creating one) so captures have somewhere to go.
+    fn start_intercept(&mut self) {
+        if self.is_loading_file {
+            self.intercept_status = Some("Wait for the current load to finish".to_string());
+            return;
+        }
+        if self.db.is_none() {
+            match file_processing::open_or_create_db() {
+                Ok((db, count)) => {
+                    self.db = Some(Arc::new(db));
+                    self.total_url_count = count;
+                }
+                Err(e) => {
+                    self.intercept_status = Some(e);
+                    return;
+                }
+            }
+        }
+        let Some(db) = &self.db else {
+            return;
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        self.intercept_receiver = Some(intercept::spawn_intercept_thread(
+            InterceptOptions {
+                bind_address: self.intercept_bind_address.clone(),
+                intercept_tls: self.intercept_tls,
+                network: self.network_config.clone(),
+                db: Arc::clone(db),
+            },
+            Arc::clone(&stop),
+        ));
+        self.intercept_stop = Some(stop);
+        self.intercept_status = Some("Starting...".to_string());
+    }
+
+    fn handle_intercept_receiver(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.intercept_receiver else {
+            return;
+        };
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        let mut stopped = false;
+        while let Ok(event) = receiver.try_recv() {
+            match event {
+                InterceptEvent::Listening(address) => {
+                    self.intercept_status = Some(format!("Listening on {}", address));
+                }
+                InterceptEvent::Captured(response, new_endpoints) => {
+                    self.proxy_history.push(response);
+                    self.total_url_count += new_endpoints;
+                }
+                InterceptEvent::Errored(err) => {
+                    self.intercept_status = Some(err);
+                }
+                InterceptEvent::Stopped => {
+                    self.intercept_status = Some("Stopped".to_string());
+                    stopped = true;
+                }
+            }
+        }
+        if stopped {
+            self.intercept_receiver = None;
+            self.intercept_stop = None;
+        }
+    }
+
+    fn handle_network_receiver(&mut self, ctx: &egui::Context) {
+        if let Some(receiver) = &self.network_receiver {
+            ctx.request_repaint();
+            if let Ok((response, body)) = receiver.try_recv() {
+                self.show_response(&response, &body);
+                self.network_receiver = None;
+            }
+        }
+    }
+
+    /// Shows `response` in the right panel and loads the stored history of
+    /// its endpoint for the history dropdown.
+    fn show_response(&mut self, response: &StoredResponse, body: &[u8]) {
+        let raw_body = String::from_utf8_lossy(body).into_owned();
+        let (pretty_body, language) = network::prettify_body(&response.content_type, &raw_body);
+        self.response_highlights = None;
+        self.right_panel_view = RightPanelView::Response(
+            response.request.clone(),
+            response.header_block(),
+            raw_body,
+            pretty_body,
+            language,
+            true,
+            body.len() as u64,
+            response.elapsed_ms,
+        );
+        self.last_request_url = Some(response.url.clone());
+        self.displayed_response = Some((response.clone(), body.to_vec()));
+
+        self.response_history.clear();
+        self.response_history_index = 0;
+        if let (Some(db), Some(key)) = (&self.db, response_store::endpoint_key(&response.url)) {
+            self.response_history = response_store::history(db, &key);
+            self.response_history_index = self
+                .response_history
+                .iter()
+                .position(|r| r.timestamp == response.timestamp)
+                .unwrap_or(0);
+        }
+    }
+
+    fn show_stored_response(&mut self, response: StoredResponse) {
+        let body = self.stored_body(&response);
+        self.show_response(&response, &body);
+    }
+
+    fn stored_body(&self, response: &StoredResponse) -> Vec<u8> {
+        self.db
+            .as_ref()
+            .and_then(|db| response_store::body(db, &response.body_hash))
+            .unwrap_or_default()
+    }
+
+    /// Compares `left` with `right`, ignoring whatever the noise patterns match.
+    fn build_response_diff(&self, left: NetworkResult, right: NetworkResult) -> Result<ResponseDiff, String> {
+        let noise = diff::parse_noise_patterns(&self.diff_noise_text)?;
+        let (left_body, left_language) = diff_body(&left);
+        let (right_body, right_language) = diff_body(&right);
+        let sections = [
+            self.diff_section("Headers", "text", &left.0.header_block(), "text", &right.0.header_block(), &noise),
+            self.diff_section("Body", &left_language, &left_body, &right_language, &right_body, &noise),
+        ];
+        let changed_rows = sections
+            .iter()
+            .flat_map(|section| &section.rows)
+            .filter(|row| row.kind != RowKind::Same)
+            .count();
+        Ok(ResponseDiff {
+            left,
+            right,
+            sections,
+            changed_rows,
+            only_changes: false,
+        })
+    }
+
+    fn diff_section(
+        &self,
+        title: &'static str,
+        left_language: &str,
+        left: &str,
+        right_language: &str,
+        right: &str,
+        noise: &[regex::Regex],
+    ) -> DiffSection {
+        let left_lines: Vec<&str> = left.lines().collect();
+        let right_lines: Vec<&str> = right.lines().collect();
+        DiffSection {
+            title,
+            rows: diff::side_by_side(&left_lines, &right_lines, noise),
+            left: self.highlighter.highlight_lines(left_language, left),
+            right: self.highlighter.highlight_lines(right_language, right),
+        }
+    }
+
+    fn open_diff(&mut self, left: NetworkResult, right: NetworkResult) {
+        match self.build_response_diff(left, right) {
+            Ok(response_diff) => {
+                self.diff_noise_error = None;
+                self.right_panel_view = RightPanelView::Diff(Box::new(response_diff));
+            }
+            Err(e) => {
+                if matches!(self.right_panel_view, RightPanelView::Diff(_)) {
+                    self.diff_noise_error = Some(e);
+                } else {
+                    self.error_message = Some(e);
+                }
+            }
+        }
+    }
+
+    fn handle_save_receiver(&mut self, _ctx: &egui::Context) {
+        if let Some(receiver) = &self.save_receiver
+            && let Ok(progress) = receiver.try_recv()
+        {
+            self.save_stop = None;
+            match progress {
+                SaveProgress::Finished(summary) => {
+                    self.is_saving_file = false;
+                    self.error_message = Some(summary);
+                }
+                SaveProgress::Errored(err) => {
+                    self.is_saving_file = false;
+                    self.error_message = Some(format!("Failed to save file: {}", err));
+                }
+            }
+            self.save_receiver = None;
+        }
+    }
+
+    fn handle_file_receiver(&mut self, _ctx: &egui::Context) {
+        if let Some(receiver) = &self.file_receiver
+            && let Ok(progress) = receiver.try_recv()
+        {
+            match progress {
+                Progress::Advanced(percent, time, count) => {
+                    self.progress = percent;
+                    self.time_remaining = time;
+                    self.total_url_count = count;
+                }
+                Progress::Finished(db, count) => {
+                    self.db = Some(db);
+                    self.total_url_count = count;
+                    self.is_loading_file = false;
+                }
+                Progress::Errored(err) => {
+                    self.error_message = Some(err);
+                    self.is_loading_file = false;
+                }
+            }
+        }
+    }
+
+    fn execute_action(&mut self, action: Action) {
+        match action {
+            Action::Select(path) => {
+                let key = path.join("/");
+                self.selected_path = Some(path);
+                if let Some(response) = self.db.as_ref().and_then(|db| response_store::latest(db, &key)) {
+                    self.show_stored_response(response);
+                }
+            }
+            Action::ShowStoredResponse(key) => {
+                match self.db.as_ref().and_then(|db| response_store::latest(db, &key)) {
+                    Some(response) => self.show_stored_response(response),
+                    None => {
+                        self.error_message = Some("No stored response for this endpoint yet.".to_string());
+                    }
+                }
+            }
+            Action::ShowHistoryEntry(index) => {
+                if let Some(response) = self.response_history.get(index).cloned() {
+                    self.show_stored_response(response);
+                }
+            }
+            Action::SetDiffBase => {
+                if let Some(displayed) = &self.displayed_response {
+                    self.error_message = Some(format!(
+                        "Diff base set to {} ({}).",
+                        displayed.0.url,
+                        history_entry_label(&displayed.0)
+                    ));
+                    self.diff_base = self.displayed_response.clone();
+                }
+            }
+            Action::DiffAgainstBase => {
+                if let (Some(base), Some(displayed)) = (self.diff_base.clone(), self.displayed_response.clone()) {
+                    self.open_diff(base, displayed);
+                }
+            }
+            Action::DiffWithPrevious => {
+                let previous = self.response_history.get(self.response_history_index + 1).cloned();
+                if let (Some(previous), Some(displayed)) = (previous, self.displayed_response.clone()) {
+                    let body = self.stored_body(&previous);
+                    self.open_diff((previous, body), displayed);
+                }
+            }
+            Action::ShowDiff(pair) => {
+                let (left, right) = *pair;
+                self.open_diff(left, right);
+            }
+            Action::CloseDiff => {
+                if let Some((response, body)) = self.displayed_response.clone() {
+                    self.show_response(&response, &body);
+                } else {
+                    self.right_panel_view = RightPanelView::Empty;
+                }
+            }
+            Action::Delete(path) => {
+                if let Some(db) = &self.db {
+                    match delete_node_from_db(db, &path) {
+                        Ok(deleted_count) => {
+                            self.total_url_count -= deleted_count;
+                            self.error_message = Some(format!("Deleted {} URLs.", deleted_count));
+                            if self.selected_path.as_ref() == Some(&path) {
+                                self.selected_path = None;
+                            }
+                        }
+                        Err(e) => {
+                            self.error_message = Some(format!("Failed to delete: {}", e));
+                        }
+                    }
+                }
+            }
+            Action::Copy(url) => {
+                if let Some(clipboard) = &mut self.clipboard
+                    && let Err(err) = clipboard.set_text(url)
+                {
+                    self.error_message = Some(format!("Failed to copy URL: {}", err));
+                }
+            }
+            Action::CopyAs(format, url) => {
+                let spec = match url {
+                    Some(url) => Some(
+                        self.db
+                            .as_ref()
+                            .zip(response_store::endpoint_key(&url))
+                            .and_then(|(db, key)| response_store::latest(db, &key))
+                            .map_or_else(
+                                || RequestSpec::from_url(&url, &self.network_config),
+                                |response| RequestSpec::from_stored(&response),
+                            ),
+                    ),
+                    None => self
+                        .displayed_response
+                        .as_ref()
+                        .map(|(response, _)| RequestSpec::from_stored(response)),
+                };
+                if let (Some(spec), Some(clipboard)) = (spec, &mut self.clipboard)
+                    && let Err(err) = clipboard.set_text(spec.render(format))
+                {
+                    self.error_message = Some(format!("Failed to copy request: {}", err));
+                }
+            }
+            Action::SendRequest(url) => {
+                let config = if self.request_override_enabled {
+                    self.request_override.apply(
+                        &self.network_config,
+                        network::parse_header_lines(&self.request_override_headers_text),
+                    )
+                } else {
+                    self.network_config.clone()
+                };
+                self.right_panel_view = RightPanelView::Loading;
+                self.last_request_url = Some(url.clone());
+                self.network_receiver = Some(network::spawn_request_thread(url, config, self.db.clone()));
+            }
+            Action::ShowSaveDialog => {
+                self.show_save_dialog = true;
+            }
+            Action::SaveToFile(file_name) => {
+                if let Some(db) = &self.db {
+                    self.is_saving_file = true;
+                    self.error_message = None;
+                    let path = PathBuf::from(file_name);
+                    self.save_receiver = Some(file_saver::spawn_file_saving_thread(Arc::clone(db), path));
+                }
+                self.show_save_dialog = false;
+            }
+            Action::SaveSitemap(options) => {
+                if let Some(db) = &self.db {
+                    self.is_saving_file = true;
+                    self.error_message = None;
+                    self.save_receiver = Some(sitemap::spawn_sitemap_thread(Arc::clone(db), options));
+                }
+                self.show_save_dialog = false;
+            }
+            Action::Export(format, scope, file_name) => {
+                if let Some(db) = &self.db {
+                    let mut path = PathBuf::from(file_name);
+                    if path.extension().is_none() {
+                        path.set_extension(format.extension());
+                    }
+                    self.is_saving_file = true;
+                    self.error_message = None;
+                    self.save_receiver = Some(export::spawn_export_thread(Arc::clone(db), format, scope, path));
+                }
+                self.show_save_dialog = false;
+            }
+            Action::ExportHar(scope, file_name, fetch_missing) => {
+                if let Some(db) = &self.db {
+                    let mut path = PathBuf::from(file_name);
+                    if path.extension().is_none() {
+                        path.set_extension("har");
+                    }
+                    self.is_saving_file = true;
+                    self.error_message = None;
+                    let stop = Arc::new(AtomicBool::new(false));
+                    self.save_receiver = Some(har::spawn_har_thread(
+                        Arc::clone(db),
+                        scope,
+                        self.network_config.clone(),
+                        fetch_missing,
+                        self.rate_limit,
+                        Arc::clone(&stop),
+                        path,
+                    ));
+                    self.save_stop = fetch_missing.then_some(stop);
+                }
+                self.show_save_dialog = false;
+            }
+            Action::ExportToolList(preset, scope, file_name) => {
+                if let Some(db) = &self.db {
+                    let mut endpoints = Vec::new();
+                    get_all_children(
+                        db,
+                        scope.root.as_deref().unwrap_or("__ROOT__"),
+                        scope.filter.as_deref(),
+                        &mut endpoints,
+                    );
+                    self.is_saving_file = true;
+                    self.error_message = None;
+                    self.save_receiver = Some(tool_lists::spawn_tool_list_thread(
+                        preset,
+                        endpoints,
+                        PathBuf::from(file_name),
+                    ));
+                }
+                self.show_save_dialog = false;
+            }
+            Action::ShowHarExport(path) => {
+                self.selected_path = Some(path);
+                self.export_selection_only = true;
+                self.save_format = SaveFormat::Har;
+                self.show_save_dialog = true;
+            }
+            Action::SendToProxy(url) => {
+                let config = self.network_config.clone();
+                let (sender, receiver) = std::sync::mpsc::channel();
+                self.proxy_receiver = Some(receiver);
+                self.error_message = Some("Sending to proxy...".to_string());
+                std::thread::spawn(move || {
+                    let result = crate::proxy::send_to_proxy(&url, &config);
+                    let _ = sender.send(result);
+                });
+            }
+            Action::ShowProxyWindow => {
+                self.show_proxy_window = true;
+            }
+            Action::ShowInterceptWindow => {
+                self.show_intercept_window = true;
+            }
+            Action::ShowHistoryWindow => {
+                self.show_history_window = true;
+            }
+            Action::ShowFuzzerWindow => {
+                if self.fuzz_receiver.is_none()
+                    && let Some((response, _)) = &self.displayed_response
+                {
+                    self.fuzz_template = response.request.clone();
+                    self.fuzz_scheme = Url::parse(&response.url)
+                        .map_or("https".to_string(), |url| url.scheme().to_string());
+                    self.fuzz_selection = None;
+                }
+                self.show_fuzzer_window = true;
+            }
+            Action::ShowMatchRulesWindow => {
+                self.show_match_rules_window = true;
+            }
+            Action::ShowAuditWindow => {
+                self.refresh_audit();
+                self.show_audit_window = true;
+            }
+            Action::ShowLinkCheckWindow(path) => {
+                if self.link_check_receiver.is_none() {
+                    self.link_check_root = path;
+                }
+                self.broken_links = self.db.as_ref().map(|db| link_checker::broken(db)).unwrap_or_default();
+                self.show_link_check_window = true;
+            }
+            Action::ShowRobotsWindow => {
+                self.robots_reports = self.db.as_ref().map(|db| robots::all(db)).unwrap_or_default();
+                self.show_robots_window = true;
+            }
+            Action::CheckRobots(roots) => {
+                if self.robots_receiver.is_some() {
+                    self.robots_status = Some("A robots.txt check is already running.".to_string());
+                } else if let Some(db) = &self.db {
+                    let roots: Vec<Url> = roots.iter().filter_map(|root| Url::parse(root).ok()).collect();
+                    self.robots_status = Some(format!("Checking robots.txt of {} hosts...", roots.len()));
+                    self.robots_receiver = Some(robots::spawn_robots_thread(
+                        Arc::clone(db),
+                        self.network_config.clone(),
+                        roots,
+                        self.robots_user_agent.clone(),
+                    ));
+                    self.show_robots_window = true;
+                }
+            }
+            Action::ShowFindingsWindow => {
+                self.refresh_findings();
+                self.show_findings_window = true;
+            }
+            Action::RevealInTree(path) => {
+                self.reveal_path = Some(path.clone());
+                self.execute_action(Action::Select(path));
+            }
+            Action::ShowFuzzResult(index) => {
+                if let Some(result) = self.fuzz_results.get(index) {
+                    let (response, body) = result.response.clone();
+                    self.show_response(&response, &body);
+                }
+            }
+            Action::ExtractJsEndpoints(url) => {
+                if self.js_receiver.is_some() {
+                    self.error_message = Some("An extraction is already running.".to_string());
+                } else if let Some(db) = &self.db {
+                    self.js_receiver = Some(js_endpoints::spawn_js_extract_thread(
+                        Arc::clone(db),
+                        self.network_config.clone(),
+                        vec![url.clone()],
+                    ));
+                    self.js_status = Some(format!("Extracting endpoints from {}...", url));
+                    self.show_js_window = true;
+                }
+            }
+            Action::CalibrateHost(url) => {
+                if let (Some(db), Ok(url)) = (&self.db, Url::parse(&url)) {
+                    self.error_message = Some(format!("Calibrating {}...", url));
+                    self.calibration_receiver = Some(calibration::spawn_calibration_thread(
+                        Arc::clone(db),
+                        self.network_config.clone(),
+                        url,
+                    ));
+                }
+            }
+            Action::ShowDiscoveryWindow(path) => {
+                if self.discovery_receiver.is_none() {
+                    self.discovery_root = path;
+                }
+                self.show_discovery_window = true;
+            }
+            Action::ShowSpiderWindow(path) => {
+                if self.spider_receiver.is_none() {
+                    self.spider_root = path;
+                }
+                self.show_spider_window = true;
+            }
+            Action::ShowCapturedExchange(index) => {
+                if let Some(response) = self.proxy_history.get(index).cloned() {
+                    self.show_stored_response(response);
+                }
+            }
+            Action::ShowNetworkWindow => {
+                self.network_headers_text =
+                    network::format_header_lines(&self.network_config.default_headers);
+                self.show_network_window = true;
+            }
+            Action::ShowThreadWindow => {
+                self.show_thread_window = true;
+            }
+            Action::SaveDisplayedUrls => {
+                if let (Some(selected_path), Some(db)) = (&self.selected_path, &self.db) {
+                    let key = selected_path.join("/");
+                    let mut all_children = Vec::new();
+                    get_all_children(db, &key, self.tree_filter_result.as_deref(), &mut all_children);
+                    all_children.sort();
+
+                    let mut file_name = self.save_file_name.clone();
+                    if !file_name.ends_with(".txt") {
+                        file_name.push_str(".txt");
+                    }
+
+                    match std::fs::File::create(&file_name) {
+                        Ok(mut file) => {
+                            for url in all_children {
+                                if let Err(e) = writeln!(file, "{}", url) {
+                                    self.error_message = Some(format!("Failed to write to file: {}", e));
+                                    return;
+                                }
+                            }
+                            self.error_message = Some(format!("Saved to {}", file_name));
+                        }
+                        Err(e) => {
+                            self.error_message = Some(format!("Failed to create file: {}", e));
+                        }
+                    }
+                }
+            }
+            Action::SendDisplayedUrlsToProxy(threads) => {
+                if let (Some(selected_path), Some(db)) = (&self.selected_path, &self.db) {
+                    let key = selected_path.join("/");
+                    let mut all_children = Vec::new();
+                    get_all_children(db, &key, self.tree_filter_result.as_deref(), &mut all_children);
+
+                    self.proxy_results.clear();
+                    self.start_proxy_send(all_children, threads);
+                }
+            }
+            Action::ShowProxyResultsWindow => {
+                self.show_proxy_results_window = true;
+            }
+            Action::RetryFailedProxyUrls => {
+                let failed: Vec<String> = self
+                    .proxy_results
+                    .iter()
+                    .filter(|r| r.is_failure())
+                    .map(|r| r.url.clone())
+                    .collect();
+                if !failed.is_empty() {
+                    self.proxy_results.retain(|r| !r.is_failure());
+                    self.start_proxy_send(failed, self.proxy_threads);
+                }
+            }
+            Action::ExportProxyResults(file_name) => {
+                let results: Vec<ProxyResult> = self
+                    .proxy_results
+                    .iter()
+                    .filter(|r| self.proxy_result_matches(r))
+                    .cloned()
+                    .collect();
+                match crate::proxy::export_results_csv(&results, &PathBuf::from(&file_name)) {
+                    Ok(()) => {
+                        self.error_message =
+                            Some(format!("Exported {} results to {}", results.len(), file_name));
+                    }
+                    Err(e) => {
+                        self.error_message = Some(format!("Failed to export results: {}", e));
+                    }
+                }
+            }
+        }
+    }
+
+    fn start_proxy_send(&mut self, urls: Vec<String>, threads: u32) {
+        self.proxy_completed = 0;
+        self.proxy_total = urls.len();
+        self.proxy_rate = 0.0;
+        self.proxy_eta = None;
+        self.proxy_progress_receiver = Some(crate::proxy::spawn_proxy_thread(
+            urls,
+            self.network_config.clone(),
+            threads,
+            self.rate_limit,
+        ));
+    }
+
+    fn proxy_result_matches(&self, result: &ProxyResult) -> bool {
+        let filter_matches = match self.proxy_results_filter {
+            ResultsFilter::All => true,
+            ResultsFilter::Succeeded => !result.is_failure(),
+            ResultsFilter::Failed => result.is_failure(),
+            ResultsFilter::Kind(kind) => result.error.as_ref().is_some_and(|(k, _)| *k == kind),
+        };
+        filter_matches
+            && (self.proxy_results_search.is_empty()
+                || result.url.contains(&self.proxy_results_search))
+    }
+
+    fn get_parameters_from_url(&self, url_str: &str) -> String {
+        if let Ok(url) = Url::parse(url_str)
+            && let Some(query) = url.query()
+        {
+            return query.to_string();
+        }
+        String::new()
+    }
+
+    fn get_extension_from_url<'a>(&self, url: &'a str) -> Option<&'a str> {
+        let path = url.split('/').next_back().unwrap_or("");
+        if !path.contains('.') {
+            return None;
+        }
+        let parts: Vec<&str> = path.split('.').collect();
+        if parts.len() > 1 {
+            let ext = parts.last().unwrap_or(&"");
+            if ext.contains('?') {
+                let ext_parts: Vec<&str> = ext.split('?').collect();
+                return Some(ext_parts[0]);
+            }
+            if ext.contains('#') {
+                let ext_parts: Vec<&str> = ext.split('#').collect();
+                return Some(ext_parts[0]);
+            }
+            return Some(ext);
+        }
+        None
+    }
+
+    fn start_file_processing(&mut self, path: PathBuf) {
+        self.is_loading_file = true;
+        self.error_message = None;
+        self.progress = 0.0;
+        self.total_url_count = 0;
+        self.db = None; 
+        self.file_receiver = Some(file_processing::spawn_file_processing_thread(path));
+        self.app_mode = AppMode::Main;
+    }
+
+    
+    fn show_top_panel(&mut self, ctx: &egui::Context, is_enabled: bool) -> Option<Action> {
+        let mut action = None;
+        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+            ui.set_enabled(is_enabled);
+            ui.horizontal(|ui| {
                 if ui.button("Load URL File").clicked() {
                     self.app_mode = AppMode::FilePicker;
                     self.file_picker_path =
                         env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
                 }
 
-                if ui.add_enabled(self.db.is_some(), egui::Button::new("Save")).clicked() {
-                    action = Some(Action::SaveDisplayedUrls);
+                let can_reopen = self.db.is_none() && !self.is_loading_file;
+                if ui.add_enabled(can_reopen, egui::Button::new("Open Project")).clicked() {
+                    match file_processing::open_existing_db() {
+                        Ok((db, count)) => {
+                            self.db = Some(Arc::new(db));
+                            self.total_url_count = count;
+                        }
+                        Err(e) => self.error_message = Some(e),
+                    }
+                }
+
+                if ui.add_enabled(self.db.is_some(), egui::Button::new("Save")).clicked() {
+                    action = Some(Action::SaveDisplayedUrls);
+                }
+
+                if ui.add_enabled(self.db.is_some(), egui::Button::new("Send to Proxy")).clicked() {
+                    action = Some(Action::SendDisplayedUrlsToProxy(self.proxy_threads));
+                }
+
+                if ui.add_enabled(!self.proxy_results.is_empty(), egui::Button::new("Proxy Results")).clicked() {
+                    action = Some(Action::ShowProxyResultsWindow);
+                }
+
+                let intercept_label = if self.intercept_receiver.is_some() {
+                    "Intercept (on)"
+                } else {
+                    "Intercept"
+                };
+                if ui.button(intercept_label).clicked() {
+                    action = Some(Action::ShowInterceptWindow);
+                }
+
+                if ui.add_enabled(!self.proxy_history.is_empty(), egui::Button::new("History")).clicked() {
+                    action = Some(Action::ShowHistoryWindow);
+                }
+
+                if ui.button("Match Rules").clicked() {
+                    action = Some(Action::ShowMatchRulesWindow);
+                }
+
+                if ui.add_enabled(self.db.is_some(), egui::Button::new("Findings")).clicked() {
+                    action = Some(Action::ShowFindingsWindow);
+                }
+
+                if ui.add_enabled(self.db.is_some(), egui::Button::new("SEO Audit")).clicked() {
+                    action = Some(Action::ShowAuditWindow);
+                }
+
+                if ui.add_enabled(self.db.is_some(), egui::Button::new("robots.txt")).clicked() {
+                    action = Some(Action::ShowRobotsWindow);
+                }
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.add_enabled(self.db.is_some(), egui::Button::new("Save All")).clicked() {
+                        action = Some(Action::ShowSaveDialog);
+                    }
+                    if ui.button("Network").clicked() {
+                        action = Some(Action::ShowNetworkWindow);
+                    }
+                    if ui.button("Setup Proxy").clicked() {
+                        action = Some(Action::ShowProxyWindow);
+                    }
+                    let pool_size = self.network_config.proxy_pool.entries().len();
+                    if pool_size > 0 {
+                        ui.label(format!("Proxy: pool of {}", pool_size));
+                    } else {
+                        ui.label(format!("Proxy: {}", self.network_config.proxy.address()));
+                    }
+                    if ui.button("Set Thread").clicked() {
+                        action = Some(Action::ShowThreadWindow);
+                    }
+                    ui.label("Threads:");
+                    ui.add_enabled(false, egui::DragValue::new(&mut self.proxy_threads).speed(1));
+                });
+            });
+        });
+        action
+    }
+
+    fn show_bottom_panel(&mut self, ctx: &egui::Context, is_enabled: bool) {
+        if self.is_loading_file || self.is_saving_file || self.proxy_progress_receiver.is_some() || self.error_message.is_some() {
+            egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
+                ui.set_enabled(is_enabled);
+                ui.vertical(|ui| {
+                    if let Some(err) = &self.error_message {
+                        ui.colored_label(ui.visuals().error_fg_color, err);
+                    }
+
+                    if self.is_loading_file {
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if let Some(time) = self.time_remaining {
+                                ui.label(format!("{:.0}s remaining", time.as_secs_f32()));
+                            }
+                            ui.add(egui::ProgressBar::new(self.progress / 100.0).show_percentage());
+                        });
+                    } else if self.is_saving_file {
+                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if let Some(stop) = &self.save_stop
+                                && ui.button("Stop").on_hover_text("Stop fetching and save what is stored").clicked()
+                            {
+                                stop.store(true, Ordering::SeqCst);
+                            }
+                            ui.spinner();
+                            ui.label("Saving file...");
+                        });
+                    } else if self.proxy_progress_receiver.is_some() {
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if let Some(eta) = self.proxy_eta {
+                                ui.label(format!("{:.0}s remaining", eta.as_secs_f32()));
+                            }
+                            ui.label(format!("{:.1} req/s", self.proxy_rate));
+                            let fraction = if self.proxy_total > 0 {
+                                self.proxy_completed as f32 / self.proxy_total as f32
+                            } else {
+                                0.0
+                            };
+                            ui.add(
+                                egui::ProgressBar::new(fraction)
+                                    .text(format!("{}/{}", self.proxy_completed, self.proxy_total)),
+                            );
+                            ui.label("Sending to proxy...");
+                        });
+                    }
+                });
+            });
+        }
+    }
+
+    fn show_save_dialog(&mut self, ctx: &egui::Context) {
+        let mut action = None;
+        egui::Window::new("Save All URLs")
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.radio_value(&mut self.save_format, SaveFormat::Text, "Text list");
+                    ui.radio_value(&mut self.save_format, SaveFormat::Sitemap, "sitemap.xml");
+                    for (index, format) in export::FORMATS.iter().enumerate() {
+                        ui.radio_value(&mut self.save_format, SaveFormat::Export(index), format.label());
+                    }
+                    ui.radio_value(&mut self.save_format, SaveFormat::Graph, "Graph");
+                    ui.radio_value(&mut self.save_format, SaveFormat::Har, "HAR");
+                    ui.radio_value(&mut self.save_format, SaveFormat::ToolList, "Tool target list");
+                });
+                match self.save_format {
+                    SaveFormat::Text => {
+                        ui.horizontal(|ui| {
+                            ui.label("File name:");
+                            ui.text_edit_singleline(&mut self.save_file_name);
+                        });
+                    }
+                    SaveFormat::Sitemap => self.show_sitemap_options(ui),
+                    SaveFormat::Export(_) => self.show_export_options(ui),
+                    SaveFormat::Graph => {
+                        self.show_graph_options(ui);
+                        self.show_export_options(ui);
+                    }
+                    SaveFormat::ToolList => self.show_tool_list_options(ui),
+                    SaveFormat::Har => {
+                        self.show_export_options(ui);
+                        ui.checkbox(
+                            &mut self.har_fetch_missing,
+                            "Fetch endpoints that have no stored response",
+                        )
+                        .on_hover_text(format!("Uses {} from the Threads window.", rate_limit_label(self.rate_limit)));
+                    }
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        action = Some(match self.save_format {
+                            SaveFormat::Text => Action::SaveToFile(self.save_file_name.clone()),
+                            SaveFormat::Sitemap => {
+                                let mut options = self.sitemap_options.clone();
+                                options.directory = PathBuf::from(&self.sitemap_directory);
+                                Action::SaveSitemap(options)
+                            }
+                            SaveFormat::Export(index) => Action::Export(
+                                Box::new(export::FORMATS[index]),
+                                self.current_export_scope(),
+                                self.export_file_name.clone(),
+                            ),
+                            SaveFormat::Graph => Action::Export(
+                                Box::new(Graph(self.graph_options)),
+                                self.current_export_scope(),
+                                self.export_file_name.clone(),
+                            ),
+                            SaveFormat::Har => Action::ExportHar(
+                                self.current_export_scope(),
+                                self.export_file_name.clone(),
+                                self.har_fetch_missing,
+                            ),
+                            SaveFormat::ToolList => Action::ExportToolList(
+                                self.tool_preset,
+                                self.current_export_scope(),
+                                self.tool_list_file_name.clone(),
+                            ),
+                        });
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.show_save_dialog = false;
+                    }
+                });
+            });
+
+        if let Some(action) = action {
+            self.execute_action(action);
+        }
+    }
+
+    /// The export scope from the save dialog, limited to the selected node
+    /// when asked to and to the sitemap filter's matches while one is shown.
+    fn current_export_scope(&self) -> ExportScope {
+        let mut scope = self.export_scope.clone();
+        scope.root = self
+            .selected_path
+            .as_ref()
+            .filter(|_| self.export_selection_only)
+            .map(|path| path.join("/"));
+        scope.filter = self.tree_filter_result.clone();
+        scope
+    }
+
+    fn show_tool_list_options(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Tool:");
+            for preset in ToolPreset::ALL {
+                if ui.radio_value(&mut self.tool_preset, preset, preset.label()).clicked() {
+                    self.tool_list_file_name = preset.default_file_name().to_string();
                 }
+            }
+        });
+        ui.label(self.tool_preset.description());
+        ui.horizontal(|ui| {
+            ui.label("File name:");
+            ui.text_edit_singleline(&mut self.tool_list_file_name);
+        });
+        match &self.selected_path {
+            Some(path) => {
+                ui.checkbox(
+                    &mut self.export_selection_only,
+                    format!("Only the selected node ({})", path.join("/")),
+                );
+            }
+            None => {
+                ui.label("Lists the whole project; select a node to list only its branch.");
+            }
+        }
+        self.show_filter_scope_note(ui);
+    }
 
-                if ui.add_enabled(self.db.is_some(), egui::Button::new("Send to Proxy")).clicked() {
-                    action = Some(Action::SendDisplayedUrlsToProxy(self.proxy_threads));
+    fn show_graph_options(&mut self, ui: &mut egui::Ui) {
+        let options = &mut self.graph_options;
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut options.kind, GraphKind::Dot, "Graphviz DOT");
+            ui.radio_value(&mut options.kind, GraphKind::Mermaid, "Mermaid");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Colour by:");
+            egui::ComboBox::from_id_source("graph_color_by")
+                .selected_text(options.color_by.label())
+                .show_ui(ui, |ui| {
+                    for color_by in ColorBy::ALL {
+                        ui.selectable_value(&mut options.color_by, color_by, color_by.label());
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Max depth:");
+            ui.add(egui::DragValue::new(&mut options.max_depth).clamp_range(0..=64));
+            ui.label(if options.max_depth == 0 {
+                "(all levels)"
+            } else {
+                "(deeper levels are collapsed into a count)"
+            });
+        });
+        ui.checkbox(&mut options.aggregate_leaves, "Group sibling files by type (e.g. \"12 × .png\")");
+    }
+
+    /// Exports and lists follow the sitemap filter while one is shown.
+    fn show_filter_scope_note(&self, ui: &mut egui::Ui) {
+        if let Some(result) = &self.tree_filter_result {
+            ui.label(format!(
+                "Limited to the {} endpoints matching the sitemap filter; clear it to include all.",
+                result.matches
+            ));
+        }
+    }
+
+    fn show_export_options(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("File name:");
+            ui.text_edit_singleline(&mut self.export_file_name);
+        });
+        match &self.selected_path {
+            Some(path) => {
+                ui.checkbox(
+                    &mut self.export_selection_only,
+                    format!("Only the selected node ({})", path.join("/")),
+                );
+            }
+            None => {
+                ui.label("Exports the whole project; select a node to export only its branch.");
+            }
+        }
+        self.show_filter_scope_note(ui);
+        ui.checkbox(&mut self.export_scope.skip_soft_404, "Skip probable soft-404s");
+        ui.horizontal(|ui| {
+            ui.label("Only endpoints tagged:");
+            ui.add(egui::TextEdit::singleline(&mut self.export_scope.tag).hint_text("any"));
+        });
+    }
+
+    fn show_sitemap_options(&mut self, ui: &mut egui::Ui) {
+        let options = &mut self.sitemap_options;
+        ui.horizontal(|ui| {
+            ui.label("Folder:");
+            ui.text_edit_singleline(&mut self.sitemap_directory);
+        });
+        ui.label("One sitemap per host; hosts over 50,000 URLs or 50 MB are split with a sitemap index.");
+        ui.checkbox(&mut options.pages_only, "Pages only (skip images, scripts, styles and other assets)");
+        ui.checkbox(&mut options.lastmod, "lastmod from the newest stored response");
+        ui.checkbox(
+            &mut options.exclude_disallowed,
+            "Exclude URLs disallowed by robots.txt (from the last robots.txt check)",
+        );
+        ui.horizontal(|ui| {
+            ui.label("changefreq:");
+            egui::ComboBox::from_id_source("sitemap_changefreq")
+                .selected_text(options.changefreq.map_or("none", ChangeFreq::label))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut options.changefreq, None, "none");
+                    for changefreq in ChangeFreq::ALL {
+                        ui.selectable_value(&mut options.changefreq, Some(changefreq), changefreq.label());
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            let mut has_priority = options.priority.is_some();
+            if ui.checkbox(&mut has_priority, "priority").changed() {
+                options.priority = has_priority.then_some(0.5);
+            }
+            if let Some(priority) = &mut options.priority {
+                ui.add(egui::DragValue::new(priority).speed(0.1).clamp_range(0.0..=1.0));
+            }
+        });
+        ui.checkbox(&mut options.gzip, "Compress with gzip (.xml.gz)");
+        ui.separator();
+        ui.label("Extensions read the newest stored HTML of each page.");
+        ui.checkbox(&mut options.images, "Image entries (referenced images and unlinked images in the tree)");
+        ui.checkbox(&mut options.videos, "Video entries (<video> elements with a poster)");
+        ui.horizontal(|ui| {
+            ui.label("hreflang:");
+            egui::ComboBox::from_id_source("sitemap_hreflang")
+                .selected_text(options.hreflang.label())
+                .show_ui(ui, |ui| {
+                    for mode in HreflangMode::ALL {
+                        ui.selectable_value(&mut options.hreflang, mode, mode.label());
+                    }
+                });
+        });
+        if options.hreflang == HreflangMode::Manual {
+            ui.label("One \"language = /prefix/\" per line, e.g. \"de-AT = /at/\":");
+            ui.add(
+                egui::TextEdit::multiline(&mut options.hreflang_mapping)
+                    .desired_rows(4)
+                    .hint_text("en = /\nde = /de/"),
+            );
+        }
+    }
+
+    fn show_proxy_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Setup Proxy")
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Protocol:");
+                    egui::ComboBox::from_id_source("proxy_protocol")
+                        .selected_text(self.proxy_protocol.clone())
+                        .show_ui(ui, |ui| {
+                            for protocol in PROXY_PROTOCOLS {
+                                ui.selectable_value(
+                                    &mut self.proxy_protocol,
+                                    protocol.to_string(),
+                                    protocol,
+                                );
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("IP Address:");
+                    ui.text_edit_singleline(&mut self.proxy_ip);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Port:");
+                    ui.text_edit_singleline(&mut self.proxy_port);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Username:");
+                    ui.text_edit_singleline(&mut self.proxy_username);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Password:");
+                    ui.add(egui::TextEdit::singleline(&mut self.proxy_password).password(true));
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Test URL:");
+                    ui.text_edit_singleline(&mut self.proxy_test_url);
+                    let is_testing = self.proxy_test_receiver.is_some();
+                    if ui.add_enabled(!is_testing, egui::Button::new("Test connection")).clicked() {
+                        match self.proxy_form_config() {
+                            Ok(config) => {
+                                self.proxy_test_result = None;
+                                self.proxy_test_receiver = Some(crate::proxy::spawn_proxy_test_thread(
+                                    self.network_config.clone(),
+                                    config,
+                                    self.proxy_test_url.clone(),
+                                ));
+                            }
+                            Err(e) => self.proxy_test_result = Some(Err(e)),
+                        }
+                    }
+                    if is_testing {
+                        ui.spinner();
+                    }
+                });
+                match &self.proxy_test_result {
+                    Some(Ok(message)) => {
+                        ui.colored_label(egui::Color32::from_rgb(180, 255, 180), message);
+                    }
+                    Some(Err(err)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, err);
+                    }
+                    None => {}
+                }
+                ui.separator();
+                self.show_proxy_pool_section(ui);
+                if let Some(err) = &self.proxy_form_error {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
                 }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("OK").clicked() {
+                        match self.proxy_form_config() {
+                            Ok(config) => {
+                                self.network_config.proxy = config;
+                                self.network_config.route_through_proxy = true;
+                                self.network_config.health_check_url = self.proxy_test_url.clone();
+                                self.proxy_form_error = None;
+                                self.show_proxy_window = false;
+                            }
+                            Err(e) => self.proxy_form_error = Some(e),
+                        }
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.proxy_form_error = None;
+                        self.show_proxy_window = false;
+                    }
+                });
+            });
+    }
 
-                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    if ui.add_enabled(self.db.is_some(), egui::Button::new("Save All")).clicked() {
-                        action = Some(Action::ShowSaveDialog);
+    fn show_proxy_pool_section(&mut self, ui: &mut egui::Ui) {
+        let pool = Arc::clone(&self.network_config.proxy_pool);
+        ui.label("Proxy pool (overrides the proxy above when loaded):");
+        ui.horizontal(|ui| {
+            ui.label("File:");
+            ui.text_edit_singleline(&mut self.proxy_pool_file);
+            if ui.button("Load").clicked() {
+                match pool.load_file(&PathBuf::from(&self.proxy_pool_file)) {
+                    Ok(count) => {
+                        self.network_config.route_through_proxy = true;
+                        self.network_config.health_check_url = self.proxy_test_url.clone();
+                        self.proxy_form_error = None;
+                        self.proxy_test_result = Some(Ok(format!("Loaded {} proxies", count)));
                     }
-                    if ui.button("Setup Proxy").clicked() {
-                        action = Some(Action::ShowProxyWindow);
+                    Err(e) => self.proxy_form_error = Some(e),
+                }
+            }
+        });
+        if pool.is_empty() {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label("Rotation:");
+            let mut rotation = pool.rotation();
+            egui::ComboBox::from_id_source("proxy_rotation")
+                .selected_text(rotation.label())
+                .show_ui(ui, |ui| {
+                    for option in Rotation::ALL {
+                        ui.selectable_value(&mut rotation, option, option.label());
                     }
-                    ui.label(format!("Proxy: {}", self.proxy_address));
-                    if ui.button("Set Thread").clicked() {
-                        action = Some(Action::ShowThreadWindow);
+                });
+            if rotation != pool.rotation() {
+                pool.set_rotation(rotation);
+            }
+        });
+        let entries = pool.entries();
+        egui::ScrollArea::vertical()
+            .id_source("proxy_pool_scroll")
+            .max_height(150.0)
+            .show(ui, |ui| {
+                egui::Grid::new("proxy_pool_grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for entry in &entries {
+                            ui.label(entry.proxy.address());
+                            if entry.alive {
+                                ui.colored_label(egui::Color32::from_rgb(180, 255, 180), "alive");
+                            } else {
+                                ui.colored_label(ui.visuals().error_fg_color, "dead");
+                            }
+                            ui.label(format!("{} requests", entry.requests));
+                            ui.end_row();
+                        }
+                    });
+            });
+        ui.horizontal(|ui| {
+            let is_checking = self.pool_check_receiver.is_some();
+            if ui.add_enabled(!is_checking, egui::Button::new("Health check")).clicked() {
+                self.pool_check_receiver = Some(crate::proxy::spawn_pool_health_check_thread(
+                    self.network_config.clone(),
+                    self.proxy_test_url.clone(),
+                ));
+            }
+            if is_checking {
+                ui.spinner();
+            }
+            if ui.button("Remove dead").clicked() {
+                let removed = pool.remove_dead();
+                self.proxy_test_result = Some(Ok(format!("Removed {} dead proxies", removed)));
+            }
+            if ui.button("Clear pool").clicked() {
+                pool.clear();
+            }
+        });
+    }
+
+    fn proxy_form_config(&self) -> Result<ProxyConfig, String> {
+        ProxyConfig::from_form(
+            &self.proxy_protocol,
+            &self.proxy_ip,
+            &self.proxy_port,
+            &self.proxy_username,
+            &self.proxy_password,
+        )
+    }
+
+    fn show_network_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Network Settings")
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.checkbox(
+                    &mut self.network_config.route_through_proxy,
+                    "Route all requests through proxy",
+                );
+                ui.checkbox(&mut self.network_config.verify_tls, "Verify TLS certificates");
+                ui.horizontal(|ui| {
+                    ui.label("Connect timeout (s):");
+                    ui.add(egui::DragValue::new(&mut self.network_config.connect_timeout_secs).clamp_range(1..=300));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Request timeout (s, 0 = none):");
+                    ui.add(egui::DragValue::new(&mut self.network_config.timeout_secs).clamp_range(0..=3600));
+                });
+                ui.label("Default headers (Name: value per line):");
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.network_headers_text)
+                        .code_editor()
+                        .desired_rows(4),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("OK").clicked() {
+                        self.network_config.default_headers =
+                            network::parse_header_lines(&self.network_headers_text);
+                        self.show_network_window = false;
+                    }
+                });
+            });
+    }
+
+    fn show_intercept_window(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
+        egui::Window::new("Intercepting Proxy")
+            .open(&mut is_open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let is_running = self.intercept_receiver.is_some();
+                ui.add_enabled_ui(!is_running, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Listen on:");
+                        ui.text_edit_singleline(&mut self.intercept_bind_address);
+                    });
+                    ui.checkbox(&mut self.intercept_tls, "Intercept HTTPS (CONNECT) with local CA");
+                });
+                if self.intercept_tls {
+                    ui.label(format!(
+                        "Import {} into your browser to trust intercepted sites.",
+                        intercept::ca_cert_path().display()
+                    ));
+                }
+                ui.label("Upstream traffic uses the Network settings, including the proxy when enabled.");
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if is_running {
+                        if ui.button("Stop").clicked()
+                            && let Some(stop) = &self.intercept_stop
+                        {
+                            stop.store(true, Ordering::SeqCst);
+                        }
+                    } else if ui.button("Start").clicked() {
+                        self.start_intercept();
+                    }
+                    if let Some(status) = &self.intercept_status {
+                        ui.label(status);
+                    }
+                });
+                ui.label(format!("{} requests captured", self.proxy_history.len()));
+            });
+        if !is_open {
+            self.show_intercept_window = false;
+        }
+    }
+
+    fn show_spider_window(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
+        egui::Window::new("Spider")
+            .open(&mut is_open)
+            .resizable(true)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                let is_running = self.spider_receiver.is_some();
+                ui.label(format!("Start from: {}", self.spider_root.join("/")));
+                ui.add_enabled_ui(!is_running, |ui| {
+                    egui::Grid::new("spider_options_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Max depth:");
+                        ui.add(egui::DragValue::new(&mut self.spider_max_depth).clamp_range(0..=50));
+                        ui.end_row();
+                        ui.label("Max pages:");
+                        ui.add(egui::DragValue::new(&mut self.spider_max_pages).clamp_range(1..=1_000_000));
+                        ui.end_row();
+                    });
+                    ui.checkbox(&mut self.spider_respect_robots, "Respect robots.txt");
+                });
+                ui.label(format!(
+                    "Uses {} threads and {} from the Threads window; only links on the start host are followed.",
+                    self.proxy_threads,
+                    rate_limit_label(self.rate_limit)
+                ));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if is_running {
+                        if ui.button("Stop").clicked()
+                            && let Some(stop) = &self.spider_stop
+                        {
+                            stop.store(true, Ordering::SeqCst);
+                        }
+                    } else if ui.add_enabled(self.db.is_some(), egui::Button::new("Start")).clicked() {
+                        self.start_spider();
+                    }
+                    if let Some(status) = &self.spider_status {
+                        ui.label(status);
+                    }
+                });
+                ui.label(format!(
+                    "{} pages fetched | {} queued | {} new endpoints",
+                    self.spider_pages, self.spider_queued, self.spider_new_endpoints
+                ));
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::vertical()
+                    .id_source("spider_log_scroll")
+                    .max_height(250.0)
+                    .stick_to_bottom(true)
+                    .show_rows(ui, row_height, self.spider_log.len(), |ui, row_range| {
+                        for line in &self.spider_log[row_range] {
+                            ui.label(line);
+                        }
+                    });
+            });
+        if !is_open {
+            self.show_spider_window = false;
+        }
+    }
+
+    fn show_fuzzer_window(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
+        let mut action = None;
+        egui::Window::new("Fuzzer")
+            .open(&mut is_open)
+            .resizable(true)
+            .default_width(800.0)
+            .default_height(600.0)
+            .show(ctx, |ui| {
+                let is_running = self.fuzz_receiver.is_some();
+                ui.add_enabled_ui(!is_running, |ui| {
+                    self.show_fuzz_setup(ui);
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if is_running {
+                        if ui.button("Stop").clicked()
+                            && let Some(stop) = &self.fuzz_stop
+                        {
+                            stop.store(true, Ordering::SeqCst);
+                        }
+                    } else if ui.button("Start Attack").clicked() {
+                        self.start_fuzz();
+                    }
+                    if let Some(status) = &self.fuzz_status {
+                        ui.label(status);
+                    }
+                });
+                if self.fuzz_total > 0 {
+                    ui.add(
+                        egui::ProgressBar::new(self.fuzz_completed as f32 / self.fuzz_total as f32)
+                            .text(format!("{}/{}", self.fuzz_completed, self.fuzz_total)),
+                    );
+                }
+                ui.separator();
+                if let Some(inner) = self.show_fuzz_results(ui) {
+                    action = Some(inner);
+                }
+            });
+        if !is_open {
+            self.show_fuzzer_window = false;
+        }
+        if let Some(action) = action {
+            self.execute_action(action);
+        }
+    }
+
+    fn show_fuzz_setup(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Scheme:");
+            egui::ComboBox::from_id_source("fuzz_scheme")
+                .selected_text(self.fuzz_scheme.clone())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.fuzz_scheme, "http".to_string(), "http");
+                    ui.selectable_value(&mut self.fuzz_scheme, "https".to_string(), "https");
+                });
+            if ui.button(format!("Add {}", fuzzer::MARKER)).clicked()
+                && let Some((start, end)) = self.fuzz_selection
+            {
+                let (start, end) = (start.min(end), start.max(end));
+                let byte_index = |text: &str, char_index: usize| {
+                    text.char_indices().nth(char_index).map_or(text.len(), |(i, _)| i)
+                };
+                let end_byte = byte_index(&self.fuzz_template, end);
+                self.fuzz_template.insert(end_byte, fuzzer::MARKER);
+                let start_byte = byte_index(&self.fuzz_template, start);
+                self.fuzz_template.insert(start_byte, fuzzer::MARKER);
+                self.fuzz_selection = None;
+            }
+            if ui.button(format!("Clear {}", fuzzer::MARKER)).clicked() {
+                self.fuzz_template = self.fuzz_template.replace(fuzzer::MARKER, "");
+            }
+            if ui.button(format!("Auto {}", fuzzer::MARKER)).clicked() {
+                self.fuzz_template = fuzzer::mark_query_values(&self.fuzz_template);
+            }
+        });
+        let output = egui::TextEdit::multiline(&mut self.fuzz_template)
+            .code_editor()
+            .desired_rows(8)
+            .desired_width(f32::INFINITY)
+            .show(ui);
+        if let Some(range) = output.cursor_range {
+            self.fuzz_selection = Some((range.primary.ccursor.index, range.secondary.ccursor.index));
+        }
+
+        let positions = match fuzzer::position_defaults(&self.fuzz_template) {
+            Ok(defaults) => {
+                ui.label(format!(
+                    "{} positions. Select text and press Add {} to mark one; headers and body can be marked too.",
+                    defaults.len(),
+                    fuzzer::MARKER
+                ));
+                defaults.len()
+            }
+            Err(e) => {
+                ui.colored_label(egui::Color32::RED, e);
+                0
+            }
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Attack mode:");
+            egui::ComboBox::from_id_source("fuzz_mode")
+                .selected_text(self.fuzz_mode.label())
+                .show_ui(ui, |ui| {
+                    for mode in AttackMode::ALL {
+                        ui.selectable_value(&mut self.fuzz_mode, mode, mode.label());
+                    }
+                });
+        });
+
+        let set_count = self.fuzz_mode.set_count(positions).max(1);
+        if self.fuzz_sets.len() < set_count {
+            self.fuzz_sets.resize_with(set_count, PayloadSet::default);
+        }
+        egui::ScrollArea::vertical()
+            .id_source("fuzz_sets_scroll")
+            .max_height(220.0)
+            .show(ui, |ui| {
+                for (index, set) in self.fuzz_sets[..set_count].iter_mut().enumerate() {
+                    ui.group(|ui| {
+                        ui.label(format!("Payload set {}", index + 1));
+                        show_payload_set(ui, index, set);
+                    });
+                }
+            });
+        ui.label("Grep (one term per line, case-insensitive):");
+        ui.add(
+            egui::TextEdit::multiline(&mut self.fuzz_grep)
+                .desired_rows(2)
+                .desired_width(f32::INFINITY),
+        );
+    }
+
+    fn show_fuzz_results(&mut self, ui: &mut egui::Ui) -> Option<Action> {
+        let mut action = None;
+        let mut resort = false;
+        ui.horizontal(|ui| {
+            ui.label("Sort by:");
+            for (sort, label) in [
+                (FuzzSort::Index, "#"),
+                (FuzzSort::Payload, "Payload"),
+                (FuzzSort::Status, "Status"),
+                (FuzzSort::Length, "Length"),
+                (FuzzSort::Time, "Time"),
+                (FuzzSort::Grep, "Grep"),
+            ] {
+                let label = if self.fuzz_sort == sort {
+                    format!("{} {}", label, if self.fuzz_sort_ascending { "⏶" } else { "⏷" })
+                } else {
+                    label.to_string()
+                };
+                if ui.selectable_label(self.fuzz_sort == sort, label).clicked() {
+                    if self.fuzz_sort == sort {
+                        self.fuzz_sort_ascending = !self.fuzz_sort_ascending;
+                    } else {
+                        self.fuzz_sort = sort;
+                        self.fuzz_sort_ascending = true;
                     }
-                    ui.label("Threads:");
-                    ui.add_enabled(false, egui::DragValue::new(&mut self.proxy_threads).speed(1));
-                });
-            });
+                    resort = true;
+                }
+            }
         });
+        if resort {
+            self.sort_fuzz_results();
+        }
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::both()
+            .id_source("fuzz_results_scroll")
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, self.fuzz_results.len(), |ui, row_range| {
+                egui::Grid::new("fuzz_results_grid")
+                    .num_columns(6)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for row in row_range {
+                            let result = &self.fuzz_results[row];
+                            if ui.selectable_label(false, result.index.to_string()).clicked() {
+                                action = Some(Action::ShowFuzzResult(row));
+                            }
+                            ui.label(result.payloads.join(" | "));
+                            ui.label(result.status.map_or("error".to_string(), |s| s.to_string()));
+                            ui.label(result.length.to_string());
+                            ui.label(format!("{} ms", result.elapsed_ms));
+                            ui.label(result.matches.join(", "));
+                            ui.end_row();
+                        }
+                    });
+            });
         action
     }
 
-    fn show_bottom_panel(&mut self, ctx: &egui::Context, is_enabled: bool) {
-        if self.is_loading_file || self.is_saving_file || self.proxy_progress_receiver.is_some() || self.error_message.is_some() {
-            egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
-                ui.set_enabled(is_enabled);
-                ui.vertical(|ui| {
-                    if let Some(err) = &self.error_message {
-                        ui.colored_label(ui.visuals().error_fg_color, err);
+    fn show_discovery_window(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
+        let mut action = None;
+        egui::Window::new("Discover Content")
+            .open(&mut is_open)
+            .resizable(true)
+            .default_width(600.0)
+            .show(ctx, |ui| {
+                let is_running = self.discovery_receiver.is_some();
+                ui.label(format!("Target: {}/", self.discovery_root.join("/")));
+                ui.add_enabled_ui(!is_running, |ui| {
+                    egui::Grid::new("discovery_options_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Wordlist:");
+                        ui.text_edit_singleline(&mut self.discovery_wordlist);
+                        ui.end_row();
+                        ui.label("Extensions:");
+                        ui.add(egui::TextEdit::singleline(&mut self.discovery_extensions).hint_text("php,html,bak"));
+                        ui.end_row();
+                        ui.label("Match status codes:");
+                        ui.text_edit_singleline(&mut self.discovery_statuses);
+                        ui.end_row();
+                        ui.label("Hide sizes:");
+                        ui.add(egui::TextEdit::singleline(&mut self.discovery_hide_sizes).hint_text("e.g. 1234,0"));
+                        ui.end_row();
+                        ui.label("Hide word counts:");
+                        ui.add(egui::TextEdit::singleline(&mut self.discovery_hide_words).hint_text("e.g. 57"));
+                        ui.end_row();
+                    });
+                });
+                ui.label(format!(
+                    "Uses {} threads and {} from the Threads window. Redirects are not followed.",
+                    self.proxy_threads,
+                    rate_limit_label(self.rate_limit)
+                ));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if is_running {
+                        if ui.button("Stop").clicked()
+                            && let Some(stop) = &self.discovery_stop
+                        {
+                            stop.store(true, Ordering::SeqCst);
+                        }
+                    } else if ui.add_enabled(self.db.is_some(), egui::Button::new("Start")).clicked() {
+                        self.start_discovery();
                     }
-
-                    if self.is_loading_file {
-                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            if let Some(time) = self.time_remaining {
-                                ui.label(format!("{:.0}s remaining", time.as_secs_f32()));
-                            }
-                            ui.add(egui::ProgressBar::new(self.progress / 100.0).show_percentage());
-                        });
-                    } else if self.is_saving_file {
-                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            ui.spinner();
-                            ui.label("Saving file...");
-                        });
-                    } else if self.proxy_progress_receiver.is_some() {
-                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            ui.add(egui::ProgressBar::new(self.progress / 100.0).show_percentage());
-                            ui.label("Sending to proxy...");
-                        });
+                    if let Some(status) = &self.discovery_status {
+                        ui.label(status);
                     }
                 });
+                if self.discovery_total > 0 {
+                    ui.add(
+                        egui::ProgressBar::new(self.discovery_completed as f32 / self.discovery_total as f32)
+                            .text(format!(
+                                "{}/{} | {} hits",
+                                self.discovery_completed,
+                                self.discovery_total,
+                                self.discovery_hits.len()
+                            )),
+                    );
+                }
+                ui.separator();
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::both()
+                    .id_source("discovery_hits_scroll")
+                    .max_height(300.0)
+                    .auto_shrink([false, true])
+                    .show_rows(ui, row_height, self.discovery_hits.len(), |ui, row_range| {
+                        egui::Grid::new("discovery_hits_grid")
+                            .num_columns(4)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for hit in &self.discovery_hits[row_range] {
+                                    ui.label(hit.status.to_string());
+                                    ui.label(format!("{} B", hit.size));
+                                    ui.label(format!("{} words", hit.words));
+                                    if ui.selectable_label(false, &hit.url).clicked()
+                                        && let Some(key) = response_store::endpoint_key(&hit.url)
+                                    {
+                                        action = Some(Action::ShowStoredResponse(key));
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
             });
+        if !is_open {
+            self.show_discovery_window = false;
+        }
+        if let Some(action) = action {
+            self.execute_action(action);
         }
     }
 
-    fn show_save_dialog(&mut self, ctx: &egui::Context) {
+    fn show_history_window(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
         let mut action = None;
-        egui::Window::new("Save All URLs")
-            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
-            .resizable(false)
-            .collapsible(false)
+        egui::Window::new("Proxy History")
+            .open(&mut is_open)
+            .resizable(true)
+            .default_width(700.0)
+            .default_height(400.0)
             .show(ctx, |ui| {
                 ui.horizontal(|ui| {
-                    ui.label("File name:");
-                    ui.text_edit_singleline(&mut self.save_file_name);
-                });
-                ui.horizontal(|ui| {
-                    if ui.button("Save").clicked() {
-                        action = Some(Action::SaveToFile(self.save_file_name.clone()));
-                    }
-                    if ui.button("Cancel").clicked() {
-                        self.show_save_dialog = false;
+                    ui.label(format!("{} requests", self.proxy_history.len()));
+                    if ui.button("Clear").clicked() {
+                        self.proxy_history.clear();
                     }
                 });
+                ui.separator();
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::both()
+                    .id_source("proxy_history_scroll")
+                    .auto_shrink([false, false])
+                    .show_rows(ui, row_height, self.proxy_history.len(), |ui, row_range| {
+                        egui::Grid::new("proxy_history_grid")
+                            .num_columns(4)
+                            .striped(true)
+                            .min_col_width(40.0)
+                            .show(ui, |ui| {
+                                for index in row_range {
+                                    let exchange = &self.proxy_history[index];
+                                    ui.label(&exchange.method);
+                                    if ui.add(egui::SelectableLabel::new(false, &exchange.url)).clicked() {
+                                        action = Some(Action::ShowCapturedExchange(index));
+                                    }
+                                    match exchange.status {
+                                        Some(status) => ui.label(status.to_string()),
+                                        None => ui.colored_label(ui.visuals().error_fg_color, "error"),
+                                    };
+                                    ui.label(format!("{} ms", exchange.elapsed_ms));
+                                    ui.end_row();
+                                }
+                            });
+                    });
             });
-
+        if !is_open {
+            self.show_history_window = false;
+        }
         if let Some(action) = action {
             self.execute_action(action);
         }
     }
 
-    fn show_proxy_window(&mut self, ctx: &egui::Context) {
-        egui::Window::new("Setup Proxy")
+    fn show_request_options(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if let Some(url) = &self.last_request_url {
+                if ui.button("Resend").clicked() {
+                    let _ = self.action_sender.send(Action::SendRequest(url.clone()));
+                }
+                ui.label(url);
+            }
+        });
+        egui::CollapsingHeader::new("Request options")
+            .id_source("request_options")
+            .default_open(false)
+            .show(ui, |ui| {
+                if ui
+                    .checkbox(&mut self.request_override_enabled, "Override network settings for this request")
+                    .changed()
+                    && self.request_override_enabled
+                {
+                    self.request_override = RequestOverride::from_config(&self.network_config);
+                    self.request_override_headers_text =
+                        network::format_header_lines(&self.network_config.default_headers);
+                }
+                ui.add_enabled_ui(self.request_override_enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.request_override.route_through_proxy, "Use proxy");
+                        ui.checkbox(&mut self.request_override.verify_tls, "Verify TLS");
+                        ui.label("Timeout (s):");
+                        ui.add(egui::DragValue::new(&mut self.request_override.timeout_secs).clamp_range(0..=3600));
+                    });
+                    ui.add(
+                        egui::TextEdit::multiline(&mut self.request_override_headers_text)
+                            .code_editor()
+                            .desired_rows(3),
+                    );
+                });
+            });
+        ui.separator();
+    }
+
+    fn show_thread_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Set Threads")
             .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
             .resizable(false)
             .collapsible(false)
             .show(ctx, |ui| {
                 ui.horizontal(|ui| {
-                    ui.label("Protocol:");
-                    ui.text_edit_singleline(&mut self.proxy_protocol);
-                });
-                ui.horizontal(|ui| {
-                    ui.label("IP Address:");
-                    ui.text_edit_singleline(&mut self.proxy_ip);
+                    ui.label("Threads:");
+                    ui.add(egui::DragValue::new(&mut self.proxy_threads).speed(1));
                 });
                 ui.horizontal(|ui| {
-                    ui.label("Port:");
-                    ui.text_edit_singleline(&mut self.proxy_port);
+                    ui.label("Requests per second (0 = unlimited):");
+                    ui.add(egui::DragValue::new(&mut self.rate_limit).speed(1));
                 });
                 ui.horizontal(|ui| {
                     if ui.button("OK").clicked() {
-                        self.proxy_address = format!(
-                            "{}://{}:{}",
-                            self.proxy_protocol, self.proxy_ip, self.proxy_port
-                        );
-                        self.show_proxy_window = false;
-                    }
-                    if ui.button("Cancel").clicked() {
-                        self.show_proxy_window = false;
+                        self.show_thread_window = false;
                     }
                 });
             });
     }
 
-    fn show_thread_window(&mut self, ctx: &egui::Context) {
-        egui::Window::new("Set Threads")
-            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
-            .resizable(false)
-            .collapsible(false)
+    fn show_proxy_results_window(&mut self, ctx: &egui::Context) {
+        let mut is_open = true;
+        let mut action = None;
+
+        egui::Window::new("Proxy Results")
+            .open(&mut is_open)
+            .resizable(true)
+            .default_width(700.0)
+            .default_height(400.0)
             .show(ctx, |ui| {
+                let failed = self.proxy_results.iter().filter(|r| r.is_failure()).count();
+                ui.label(format!(
+                    "{} results | {} succeeded | {} failed",
+                    self.proxy_results.len(),
+                    self.proxy_results.len() - failed,
+                    failed
+                ));
                 ui.horizontal(|ui| {
-                    ui.label("Threads:");
-                    ui.add(egui::DragValue::new(&mut self.proxy_threads).speed(1));
+                    ui.label("Filter:");
+                    ui.text_edit_singleline(&mut self.proxy_results_search);
+                    egui::ComboBox::from_id_source("proxy_results_filter")
+                        .selected_text(results_filter_label(self.proxy_results_filter))
+                        .show_ui(ui, |ui| {
+                            let mut filters = vec![
+                                ResultsFilter::All,
+                                ResultsFilter::Succeeded,
+                                ResultsFilter::Failed,
+                            ];
+                            filters.extend(ErrorKind::ALL.iter().map(|k| ResultsFilter::Kind(*k)));
+                            for filter in filters {
+                                ui.selectable_value(
+                                    &mut self.proxy_results_filter,
+                                    filter,
+                                    results_filter_label(filter),
+                                );
+                            }
+                        });
                 });
                 ui.horizontal(|ui| {
-                    if ui.button("OK").clicked() {
-                        self.show_thread_window = false;
+                    let can_retry = failed > 0 && self.proxy_progress_receiver.is_none();
+                    if ui.add_enabled(can_retry, egui::Button::new("Retry Failed")).clicked() {
+                        action = Some(Action::RetryFailedProxyUrls);
+                    }
+                    ui.separator();
+                    ui.label("File name:");
+                    ui.text_edit_singleline(&mut self.proxy_results_file_name);
+                    if ui.button("Export CSV").clicked() {
+                        action = Some(Action::ExportProxyResults(self.proxy_results_file_name.clone()));
                     }
                 });
+                ui.separator();
+
+                let visible: Vec<&ProxyResult> = self
+                    .proxy_results
+                    .iter()
+                    .filter(|r| self.proxy_result_matches(r))
+                    .collect();
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::both()
+                    .id_source("proxy_results_scroll")
+                    .auto_shrink([false, false])
+                    .show_rows(ui, row_height, visible.len(), |ui, row_range| {
+                        egui::Grid::new("proxy_results_grid")
+                            .num_columns(3)
+                            .striped(true)
+                            .min_col_width(60.0)
+                            .show(ui, |ui| {
+                                for result in &visible[row_range] {
+                                    ui.label(&result.url);
+                                    match (&result.status, &result.error) {
+                                        (Some(status), _) => {
+                                            ui.label(status.to_string());
+                                        }
+                                        (None, Some((kind, message))) => {
+                                            ui.colored_label(ui.visuals().error_fg_color, kind.label())
+                                                .on_hover_text(message);
+                                        }
+                                        (None, None) => {
+                                            ui.label("");
+                                        }
+                                    }
+                                    ui.label(format!("{} ms", result.duration.as_millis()));
+                                    ui.end_row();
+                                }
+                            });
+                    });
             });
+
+        if !is_open {
+            self.show_proxy_results_window = false;
+        }
+        if let Some(action) = action {
+            self.execute_action(action);
+        }
     }
 
     fn show_file_picker_window(&mut self, ctx: &egui::Context) {
@@ -761,10 +4056,10 @@ impl SiteMapperApp {
                 }
                 ui.separator();
 
-                if ui.button("⬆ Up").clicked() {
-                    if let Some(parent) = self.file_picker_path.parent() {
-                        self.file_picker_path = parent.to_path_buf();
-                    }
+                if ui.button("⬆ Up").clicked()
+                    && let Some(parent) = self.file_picker_path.parent()
+                {
+                    self.file_picker_path = parent.to_path_buf();
                 }
 
                 egui::ScrollArea::vertical().show(ui, |ui| {
@@ -824,6 +4119,16 @@ impl SiteMapperApp {
         key: &str,
     ) -> Option<Action> {
         let mut children = file_processing::get_children(db, key);
+        if let Some(result) = &self.tree_filter_result {
+            let prefix = current_path.join("/");
+            children.retain(|name| {
+                if prefix.is_empty() {
+                    result.contains(name)
+                } else {
+                    result.contains(&format!("{}/{}", prefix, name))
+                }
+            });
+        }
         children.sort();
 
         for name in children {
@@ -834,33 +4139,48 @@ impl SiteMapperApp {
                 let path_clone = current_path.clone();
                 let new_key = path_clone.join("/");
                 let has_children = file_processing::get_node_value(db, &new_key)
-                    .map_or(false, |v| !v.children.is_empty());
+                    .is_some_and(|v| !v.children.is_empty());
 
                 let is_selected = self.selected_path.as_ref() == Some(current_path);
 
                 let response = if has_children {
                     let (icon, mut color) = ("📁", egui::Color32::from_rgb(255, 215, 100));
 
-                    if key == "__ROOT__" {
-                        if let Some(node) = file_processing::get_node_value(db, &new_key) {
-                            if let Some(scheme) = &node.scheme {
-                                if scheme == "http" {
-                                    color = egui::Color32::from_rgb(255, 180, 180); // Light red
-                                } else if scheme == "https" {
-                                    color = egui::Color32::from_rgb(180, 255, 180); // Light green
-                                }
-                            }
+                    if key == "__ROOT__"
+                        && let Some(node) = file_processing::get_node_value(db, &new_key)
+                        && let Some(scheme) = &node.scheme
+                    {
+                        if scheme == "http" {
+                            color = egui::Color32::from_rgb(255, 180, 180); // Light red
+                        } else if scheme == "https" {
+                            color = egui::Color32::from_rgb(180, 255, 180); // Light green
                         }
                     }
 
-                    let label = format!("{} {}", icon, name);
+                    let filter_count = self.tree_filter_result.as_ref().map(|result| result.count(&new_key));
+                    let label = match filter_count {
+                        Some(count) => format!("{} {} ({})", icon, name, count),
+                        None => format!("{} {}", icon, name),
+                    };
                     let mut rich_text = egui::RichText::new(label).size(14.0).color(color);
                     if is_selected {
                         rich_text = rich_text.background_color(ui.visuals().selection.bg_fill);
                     }
 
-                    let header = egui::CollapsingHeader::new(rich_text)
-                        .default_open(false)
+                    let is_revealing = self
+                        .reveal_path
+                        .as_ref()
+                        .is_some_and(|reveal| reveal.len() > current_path.len() && reveal.starts_with(current_path));
+                    let mut header = egui::CollapsingHeader::new(rich_text).default_open(false);
+                    if let Some(result) = &self.tree_filter_result {
+                        // A fresh id per result, so matches open up again
+                        // after the user collapsed them under an older one.
+                        header = header
+                            .id_source(("tree_filter", self.tree_filter_generation, &new_key))
+                            .default_open(result.matches <= tree_filter::AUTO_EXPAND_LIMIT);
+                    }
+                    let header = header
+                        .open(is_revealing.then_some(true))
                         .show(ui, |ui| {
                             self.show_db_tree(ui, current_path, db, &new_key)
                         });
@@ -872,27 +4192,65 @@ impl SiteMapperApp {
                 } else {
                     let extension = get_extension(&name);
                     let (icon, color) = match extension {
-                        Some("png" | "jpg" | "jpeg" | "gif" | "svg" | "webp") => {
+                        Some(ext) if file_processing::IMAGE_EXTENSIONS.contains(&ext) => {
                             ("🖼️", egui::Color32::from_rgb(200, 120, 255))
                         }
-                        Some("js" | "css" | "json" | "xml" | "html") => {
+                        Some(ext) if file_processing::CODE_EXTENSIONS.contains(&ext) => {
                             ("⚙️", egui::Color32::from_gray(180))
                         }
                         _ => ("📄", egui::Color32::from_rgb(150, 200, 255)),
                     };
-                    let label = format!("{} {}", icon, name);
+                    let node = file_processing::get_node_value(db, &new_key).unwrap_or_default();
+                    let mut label = format!("{} {}", icon, name);
+                    if node.sources.contains(&Source::Fuzzing) && !node.sources.contains(&Source::Import) {
+                        label.push_str(" 🔎");
+                    }
+                    let is_soft_404 = node.tags.contains(calibration::SOFT_404_TAG);
+                    if node.tags.iter().any(|tag| tag != calibration::SOFT_404_TAG) {
+                        label.push_str(" 🏷");
+                    }
+                    let color = if is_soft_404 { egui::Color32::from_gray(120) } else { color };
                     let mut rich_text = egui::RichText::new(label).size(14.0).color(color);
+                    if is_soft_404 {
+                        rich_text = rich_text.strikethrough();
+                    }
                     if is_selected {
                         rich_text = rich_text.background_color(ui.visuals().selection.bg_fill);
                     }
 
-                    ui.add(egui::SelectableLabel::new(is_selected, rich_text))
+                    let response = ui.add(egui::SelectableLabel::new(is_selected, rich_text));
+                    let mut hover = Vec::new();
+                    if !node.sources.is_empty() {
+                        hover.push(format!("Found by: {}", sources_label(&node.sources)));
+                    }
+                    if !node.tags.is_empty() {
+                        hover.push(format!("Tags: {}", tags_label(&node.tags)));
+                    }
+                    if node.tags.contains(secrets::SECRETS_TAG) {
+                        hover.push("Possible secrets found: see Findings".to_string());
+                    }
+                    if node.tags.contains(robots::ROBOTS_BLOCKED_TAG) {
+                        hover.push("Disallowed by robots.txt for the checked user agent".to_string());
+                    }
+                    if is_soft_404 {
+                        hover.push("Probable soft-404: matches the host's not-found page".to_string());
+                    }
+                    if hover.is_empty() {
+                        response
+                    } else {
+                        response.on_hover_text(hover.join("\n"))
+                    }
                 };
 
                 if response.hovered() {
                     response.clone().highlight();
                 }
 
+                if self.reveal_path.as_ref() == Some(&path_clone) {
+                    response.scroll_to_me(Some(egui::Align::Center));
+                    self.reveal_path = None;
+                }
+
                 if response.clicked() {
                     requested_action = Some(Action::Select(path_clone.clone()));
                 }
@@ -911,6 +4269,12 @@ impl SiteMapperApp {
                         requested_action = Some(Action::Copy(url.clone()));
                         ui.close_menu();
                     }
+                    for format in SnippetFormat::ALL {
+                        if ui.button(format.label()).clicked() {
+                            requested_action = Some(Action::CopyAs(format, Some(url.clone())));
+                            ui.close_menu();
+                        }
+                    }
                     if ui.button("Delete").clicked() {
                         requested_action = Some(Action::Delete(path_clone.clone()));
                         ui.close_menu();
@@ -919,6 +4283,38 @@ impl SiteMapperApp {
                         requested_action = Some(Action::SendToProxy(url.clone()));
                         ui.close_menu();
                     }
+                    if ui.button("Spider from here").clicked() {
+                        requested_action = Some(Action::ShowSpiderWindow(path_clone.clone()));
+                        ui.close_menu();
+                    }
+                    if ui.button("Check links").clicked() {
+                        requested_action = Some(Action::ShowLinkCheckWindow(path_clone.clone()));
+                        ui.close_menu();
+                    }
+                    if ui.button("Export as HAR...").clicked() {
+                        requested_action = Some(Action::ShowHarExport(path_clone.clone()));
+                        ui.close_menu();
+                    }
+                    if has_children && ui.button("Discover content").clicked() {
+                        requested_action = Some(Action::ShowDiscoveryWindow(path_clone.clone()));
+                        ui.close_menu();
+                    }
+                    let is_script = !has_children
+                        && get_extension(&name)
+                            .and_then(|ext| ext.split(['?', '#']).next())
+                            .is_some_and(|ext| matches!(ext.to_lowercase().as_str(), "js" | "mjs"));
+                    if is_script && ui.button("Extract endpoints").clicked() {
+                        requested_action = Some(Action::ExtractJsEndpoints(url.clone()));
+                        ui.close_menu();
+                    }
+                    if path_clone.len() == 1 && ui.button("Check robots.txt").clicked() {
+                        requested_action = Some(Action::CheckRobots(vec![url.clone()]));
+                        ui.close_menu();
+                    }
+                    if path_clone.len() == 1 && ui.button("Calibrate soft-404").clicked() {
+                        requested_action = Some(Action::CalibrateHost(url.clone()));
+                        ui.close_menu();
+                    }
                 });
 
                 requested_action
@@ -935,26 +4331,264 @@ impl SiteMapperApp {
     }
 }
 
-fn get_all_children(db: &Db, key: &str, all_children: &mut Vec<String>) {
+/// The full URLs of the endpoints at and below `key`, only those `filter`
+/// matched when one is given.
+fn get_all_children(db: &Db, key: &str, filter: Option<&FilterResult>, all_children: &mut Vec<String>) {
+    visit_endpoints(db, key, filter, &mut |url, _| all_children.push(url));
+}
+
+/// Calls `visit` with the URL and node of every endpoint under `key` that
+/// passes `filter`.
+fn visit_endpoints(
+    db: &Db,
+    key: &str,
+    filter: Option<&FilterResult>,
+    visit: &mut impl FnMut(String, &NodeValue),
+) {
+    if key != "__ROOT__" && filter.is_some_and(|filter| !filter.contains(key)) {
+        return;
+    }
     if let Some(node_value) = file_processing::get_node_value(db, key) {
-        if node_value.is_endpoint {
-            if let Some(scheme) = file_processing::get_node_value(db, key).and_then(|n| n.scheme) {
-                all_children.push(format!("{}://{}", scheme, key));
-            } else {
-                all_children.push(key.to_string());
-            }
+        if node_value.is_endpoint && filter.is_none_or(|filter| filter.is_match(key)) {
+            let url = match &node_value.scheme {
+                Some(scheme) => format!("{}://{}", scheme, key),
+                None => key.to_string(),
+            };
+            visit(url, &node_value);
         }
-        for child in node_value.children {
+        for child in &node_value.children {
             let new_key = if key == "__ROOT__" {
                 child.clone()
             } else {
                 format!("{}/{}", key, child)
             };
-            get_all_children(db, &new_key, all_children);
+            visit_endpoints(db, &new_key, filter, visit);
         }
     }
 }
 
+fn history_entry_label(response: &StoredResponse) -> String {
+    format!(
+        "{} | {}",
+        response_store::format_timestamp(response.timestamp),
+        response
+            .status
+            .map_or("error".to_string(), |s| s.to_string())
+    )
+}
+
+/// The body shown for one side of a diff, prettified so that reformatted
+/// JSON lines up, and the language to highlight it with.
+fn diff_body((response, body): &NetworkResult) -> (String, String) {
+    network::prettify_body(&response.content_type, &String::from_utf8_lossy(body))
+}
+
+fn diff_side_label(response: &StoredResponse) -> String {
+    format!("{} {} | {}", response.method, response.url, history_entry_label(response))
+}
+
+fn show_response_diff(
+    ui: &mut egui::Ui,
+    response_diff: &mut ResponseDiff,
+    noise_text: &mut String,
+    noise_error: Option<&str>,
+    action_sender: &std::sync::mpsc::Sender<Action>,
+) {
+    ui.horizontal(|ui| {
+        ui.heading("Diff");
+        ui.label(format!("{} changed lines", response_diff.changed_rows));
+        ui.checkbox(&mut response_diff.only_changes, "Only changes");
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui.button("Close").clicked() {
+                let _ = action_sender.send(Action::CloseDiff);
+            }
+            if ui.button("Swap").clicked() {
+                let _ = action_sender.send(Action::ShowDiff(Box::new((
+                    response_diff.right.clone(),
+                    response_diff.left.clone(),
+                ))));
+            }
+        });
+    });
+    ui.columns(2, |columns| {
+        columns[0].label(diff_side_label(&response_diff.left.0));
+        columns[1].label(diff_side_label(&response_diff.right.0));
+    });
+    ui.collapsing("Ignore noise", |ui| {
+        ui.label("One regex per line. Matches are ignored when comparing lines, e.g. CSRF tokens or timestamps.");
+        ui.add(
+            egui::TextEdit::multiline(noise_text)
+                .desired_rows(3)
+                .code_editor()
+                .desired_width(f32::INFINITY),
+        );
+        if let Some(err) = noise_error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+        if ui.button("Apply").clicked() {
+            let _ = action_sender.send(Action::ShowDiff(Box::new((
+                response_diff.left.clone(),
+                response_diff.right.clone(),
+            ))));
+        }
+    });
+    ui.separator();
+
+    // Section headings are listed as rows without an index.
+    let mut lines: Vec<(usize, Option<usize>)> = Vec::new();
+    for (section_index, section) in response_diff.sections.iter().enumerate() {
+        lines.push((section_index, None));
+        for (row_index, row) in section.rows.iter().enumerate() {
+            if !response_diff.only_changes || row.kind != RowKind::Same {
+                lines.push((section_index, Some(row_index)));
+            }
+        }
+    }
+
+    let row_height = ui.fonts(|f| f.row_height(&egui::FontId::monospace(14.0)));
+    egui::ScrollArea::vertical()
+        .id_source("diff_scroll")
+        .auto_shrink([false, false])
+        .show_rows(ui, row_height, lines.len(), |ui, range| {
+            for &(section_index, row_index) in &lines[range] {
+                let section = &response_diff.sections[section_index];
+                match row_index {
+                    None => {
+                        ui.strong(section.title);
+                    }
+                    Some(row_index) => show_diff_row(ui, section, &section.rows[row_index], row_height),
+                }
+            }
+        });
+}
+
+fn show_diff_row(ui: &mut egui::Ui, section: &DiffSection, row: &DiffRow, row_height: f32) {
+    let (left_color, right_color) = match row.kind {
+        RowKind::Same => (egui::Color32::TRANSPARENT, egui::Color32::TRANSPARENT),
+        RowKind::Changed => (DIFF_REMOVED_COLOR, DIFF_ADDED_COLOR),
+        RowKind::Removed => (DIFF_REMOVED_COLOR, DIFF_MISSING_COLOR),
+        RowKind::Added => (DIFF_MISSING_COLOR, DIFF_ADDED_COLOR),
+    };
+    ui.horizontal(|ui| {
+        let width = (ui.available_width() - ui.spacing().item_spacing.x) / 2.0;
+        show_diff_cell(ui, width, row_height, row.left, &section.left, left_color);
+        show_diff_cell(ui, width, row_height, row.right, &section.right, right_color);
+    });
+}
+
+const DIFF_REMOVED_COLOR: egui::Color32 = egui::Color32::from_rgb(80, 30, 30);
+const DIFF_ADDED_COLOR: egui::Color32 = egui::Color32::from_rgb(30, 70, 35);
+const DIFF_MISSING_COLOR: egui::Color32 = egui::Color32::from_rgb(45, 45, 45);
+const MATCH_HIGHLIGHT_COLOR: egui::Color32 = egui::Color32::from_rgb(110, 90, 20);
+
+fn show_diff_cell(
+    ui: &mut egui::Ui,
+    width: f32,
+    height: f32,
+    line: Option<usize>,
+    jobs: &[LayoutJob],
+    background: egui::Color32,
+) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(width, height), egui::Sense::hover());
+    ui.painter().rect_filled(rect, 0.0, background);
+    if let Some(line) = line {
+        let mut cell = ui.child_ui(rect, egui::Layout::left_to_right(egui::Align::Center));
+        cell.set_clip_rect(rect.intersect(ui.clip_rect()));
+        cell.label(egui::RichText::new(format!("{:>5} ", line + 1)).monospace().weak());
+        if let Some(job) = jobs.get(line) {
+            cell.add(egui::Label::new(job.clone()).truncate(true));
+        }
+    }
+}
+
+fn show_payload_set(ui: &mut egui::Ui, index: usize, set: &mut PayloadSet) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_source(("payload_kind", index))
+            .selected_text(set.kind.label())
+            .show_ui(ui, |ui| {
+                for kind in PayloadKind::ALL {
+                    ui.selectable_value(&mut set.kind, kind, kind.label());
+                }
+            });
+        egui::ComboBox::from_id_source(("payload_encoder", index))
+            .selected_text(set.encoder.label())
+            .show_ui(ui, |ui| {
+                for encoder in Encoder::ALL {
+                    ui.selectable_value(&mut set.encoder, encoder, encoder.label());
+                }
+            });
+    });
+    match set.kind {
+        PayloadKind::List => {
+            ui.add(
+                egui::TextEdit::multiline(&mut set.list)
+                    .hint_text("One payload per line")
+                    .desired_rows(3)
+                    .desired_width(f32::INFINITY),
+            );
+        }
+        PayloadKind::Wordlist => {
+            ui.horizontal(|ui| {
+                ui.label("File:");
+                ui.text_edit_singleline(&mut set.wordlist);
+            });
+        }
+        PayloadKind::Numbers => {
+            ui.horizontal(|ui| {
+                ui.label("From:");
+                ui.add(egui::DragValue::new(&mut set.from));
+                ui.label("To:");
+                ui.add(egui::DragValue::new(&mut set.to));
+                ui.label("Step:");
+                ui.add(egui::DragValue::new(&mut set.step).clamp_range(1..=i64::MAX));
+            });
+        }
+        PayloadKind::CasePermutations => {
+            ui.horizontal(|ui| {
+                ui.label("Word:");
+                ui.text_edit_singleline(&mut set.word);
+            });
+        }
+    }
+}
+
+fn severity_color(severity: Severity) -> egui::Color32 {
+    match severity {
+        Severity::Error => egui::Color32::from_rgb(255, 110, 110),
+        Severity::Warning => egui::Color32::from_rgb(255, 200, 90),
+        Severity::Notice => egui::Color32::from_rgb(150, 190, 255),
+    }
+}
+
+fn sources_label(sources: &HashSet<Source>) -> String {
+    let mut labels: Vec<&str> = sources.iter().map(|source| source.label()).collect();
+    labels.sort();
+    labels.join(", ")
+}
+
+fn tags_label(tags: &HashSet<String>) -> String {
+    let mut tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+    tags.sort();
+    tags.join(", ")
+}
+
+fn rate_limit_label(rate_limit: u32) -> String {
+    if rate_limit == 0 {
+        "no rate limit".to_string()
+    } else {
+        format!("{} requests/s", rate_limit)
+    }
+}
+
+fn results_filter_label(filter: ResultsFilter) -> &'static str {
+    match filter {
+        ResultsFilter::All => "All",
+        ResultsFilter::Succeeded => "Succeeded",
+        ResultsFilter::Failed => "Failed",
+        ResultsFilter::Kind(kind) => kind.label(),
+    }
+}
+
 fn get_extension(name: &str) -> Option<&str> {
     name.rsplit_once('.').map(|(_, ext)| ext)
 }
@@ -977,22 +4611,11 @@ fn delete_node_from_db(db: &Db, path: &[String]) -> Result<usize, Box<dyn std::e
     db.remove(&key)?;
 
     if path.len() > 1 {
-        let parent_path = &path[0..path.len() - 1];
-        let parent_key = parent_path.join("/");
-        if let Some(mut parent_node_value) = file_processing::get_node_value(db, &parent_key) {
-            if parent_node_value.children.remove(&path[path.len() - 1]) {
-                let encoded = serde_json::to_vec(&parent_node_value)?;
-                db.insert(parent_key.as_bytes(), encoded)?;
-            }
-        }
+        let parent_key = path[0..path.len() - 1].join("/");
+        file_processing::remove_child(db, &parent_key, &path[path.len() - 1])?;
     } else {
         // It's a root domain
-        if let Some(mut root_node_value) = file_processing::get_node_value(db, "__ROOT__") {
-            if root_node_value.children.remove(&key) {
-                let encoded = serde_json::to_vec(&root_node_value)?;
-                db.insert("__ROOT__", encoded)?;
-            }
-        }
+        file_processing::remove_child(db, "__ROOT__", &key)?;
     }
 
     Ok(deleted_count)
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use url::Url;
+
+use crate::file_processing;
+use crate::network::{self, NetworkConfig};
+use crate::response_store::{self, StoredResponse};
+
+const CALIBRATION_TREE: &str = "calibration";
+
+pub const SOFT_404_TAG: &str = "soft-404";
+
+/// Simhashes this many bits apart or fewer count as the same page.
+const MAX_SIMHASH_DISTANCE: u32 = 6;
+
+/// What a host answers for a path that cannot exist.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Fingerprint {
+    pub status: u16,
+    pub length_bucket: u32,
+    pub simhash: u64,
+}
+
+impl Fingerprint {
+    /// Fingerprints `body` as served for `url`. The request path is blanked
+    /// out first, since error pages often echo it back.
+    pub fn of(status: u16, url: &str, body: &[u8]) -> Self {
+        let mut text = String::from_utf8_lossy(body).into_owned();
+        if let Ok(url) = Url::parse(url) {
+            let path = url.path();
+            if path.len() > 1 {
+                text = text.replace(path, "");
+                text = text.replace(path.trim_start_matches('/'), "");
+            }
+        }
+        Self {
+            status,
+            length_bucket: length_bucket(text.len()),
+            simhash: simhash(&text),
+        }
+    }
+
+    pub fn matches(&self, other: &Fingerprint) -> bool {
+        self.status == other.status
+            && self.length_bucket.abs_diff(other.length_bucket) <= 1
+            && (self.simhash ^ other.simhash).count_ones() <= MAX_SIMHASH_DISTANCE
+    }
+}
+
+/// Logarithmic buckets, each about 20% wider than the last, so small
+/// variations in a large page land in the same or a neighbouring bucket.
+fn length_bucket(len: usize) -> u32 {
+    ((len as f64 + 1.0).log2() * 4.0) as u32
+}
+
+fn simhash(text: &str) -> u64 {
+    let mut weights = [0i32; 64];
+    for token in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+    {
+        let hash = fnv1a(token.to_lowercase().as_bytes());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |hash, (bit, _)| hash | (1 << bit))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn random_segment() -> String {
+    (0..16).map(|_| fastrand::alphanumeric()).collect::<String>().to_lowercase()
+}
+
+/// Requests a few random paths under `base` and stores how the host
+/// answered, then re-tags the endpoints already stored for that host.
+/// Returns the fingerprints found.
+pub fn calibrate(db: &sled::Db, config: &NetworkConfig, base: &Url) -> Result<Vec<Fingerprint>, String> {
+    let host = base.host_str().ok_or("URL has no host")?.to_string();
+    let base = base
+        .join(&format!("{}/", base.path().trim_end_matches('/')))
+        .map_err(|e| e.to_string())?;
+    let probes = [
+        random_segment(),
+        format!("{}.html", random_segment()),
+        format!("{}.php", random_segment()),
+        format!("{}/", random_segment()),
+    ];
+
+    let mut fingerprints: Vec<Fingerprint> = Vec::new();
+    for probe in probes {
+        let url = base.join(&probe).map_err(|e| e.to_string())?;
+        let (response, body) = network::fetch(config, url.as_str());
+        let Some(status) = response.status else {
+            continue;
+        };
+        let fingerprint = Fingerprint::of(status, url.as_str(), &body);
+        if !fingerprints.contains(&fingerprint) {
+            fingerprints.push(fingerprint);
+        }
+    }
+    if fingerprints.is_empty() {
+        return Err(format!("Could not reach {} to calibrate", host));
+    }
+
+    let tree = db.open_tree(CALIBRATION_TREE).map_err(|e| e.to_string())?;
+    let encoded = serde_json::to_vec(&fingerprints).map_err(|e| e.to_string())?;
+    tree.insert(host.as_bytes(), encoded).map_err(|e| e.to_string())?;
+
+    for response in response_store::latest_for_host(db, &host) {
+        classify_with(db, &fingerprints, &response);
+    }
+    Ok(fingerprints)
+}
+
+/// Calibrates in the background, reporting how many distinct not-found
+/// responses the host gave.
+pub fn spawn_calibration_thread(
+    db: Arc<sled::Db>,
+    config: NetworkConfig,
+    base: Url,
+) -> Receiver<Result<usize, String>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(calibrate(&db, &config, &base).map(|fingerprints| fingerprints.len()));
+    });
+    receiver
+}
+
+pub fn fingerprints(db: &sled::Db, host: &str) -> Vec<Fingerprint> {
+    db.open_tree(CALIBRATION_TREE)
+        .ok()
+        .and_then(|tree| tree.get(host.as_bytes()).ok().flatten())
+        .and_then(|value| serde_json::from_slice(&value).ok())
+        .unwrap_or_default()
+}
+
+/// Tags or untags the endpoint of `response` as a probable soft-404,
+/// if its host has been calibrated. Returns whether it matched.
+pub fn classify(db: &sled::Db, response: &StoredResponse, body: &[u8]) -> bool {
+    let Some(host) = Url::parse(&response.url)
+        .ok()
+        .and_then(|url| url.host_str().map(String::from))
+    else {
+        return false;
+    };
+    let fingerprints = fingerprints(db, &host);
+    if fingerprints.is_empty() {
+        return false;
+    }
+    let matched = is_soft_404(&fingerprints, response, body);
+    tag(db, response, matched);
+    matched
+}
+
+fn classify_with(db: &sled::Db, fingerprints: &[Fingerprint], response: &StoredResponse) {
+    let body = response_store::body(db, &response.body_hash).unwrap_or_default();
+    tag(db, response, is_soft_404(fingerprints, response, &body));
+}
+
+/// Whether `response` looks like the host's not-found page while claiming
+/// otherwise. Real 404s are never soft-404s.
+pub fn is_soft_404(fingerprints: &[Fingerprint], response: &StoredResponse, body: &[u8]) -> bool {
+    let Some(status) = response.status.filter(|status| *status != 404) else {
+        return false;
+    };
+    let fingerprint = Fingerprint::of(status, &response.url, body);
+    fingerprints.iter().any(|known| known.matches(&fingerprint))
+}
+
+fn tag(db: &sled::Db, response: &StoredResponse, present: bool) {
+    if let Some(key) = response_store::endpoint_key(&response.url) {
+        let _ = file_processing::set_tag(db, &key, SOFT_404_TAG, present);
+    }
+}
@@ -0,0 +1,321 @@
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Above this edit distance the diff gives up on finding a minimal script
+/// and falls back to `line_set_diff`. The backtrack keeps about D² cells, so
+/// this bounds it to roughly 32 MB.
+const MAX_EDIT_DISTANCE: usize = 2_000;
+
+const NOISE_PLACEHOLDER: &str = "\u{2026}";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum RowKind {
+    Same,
+    Changed,
+    Removed,
+    Added,
+}
+
+/// One row of a side-by-side diff, pointing at line indices on each side.
+#[derive(Clone)]
+pub struct DiffRow {
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+    pub kind: RowKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Parses one regex per line; blank lines are ignored.
+pub fn parse_noise_patterns(text: &str) -> Result<Vec<Regex>, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| Regex::new(line).map_err(|e| format!("Invalid pattern '{}': {}", line, e)))
+        .collect()
+}
+
+fn normalize(line: &str, noise: &[Regex]) -> String {
+    let mut normalized = line.to_string();
+    for pattern in noise {
+        normalized = pattern.replace_all(&normalized, NOISE_PLACEHOLDER).into_owned();
+    }
+    normalized
+}
+
+/// Aligns `left` and `right` line by line. Lines are compared after replacing
+/// every `noise` match, so values such as CSRF tokens do not count as changes.
+pub fn side_by_side(left: &[&str], right: &[&str], noise: &[Regex]) -> Vec<DiffRow> {
+    let left_norm: Vec<String> = left.iter().map(|l| normalize(l, noise)).collect();
+    let right_norm: Vec<String> = right.iter().map(|l| normalize(l, noise)).collect();
+    let edits = edit_script(&left_norm, &right_norm);
+
+    let mut rows = Vec::new();
+    let (mut l, mut r) = (0, 0);
+    let mut i = 0;
+    while i < edits.len() {
+        if edits[i] == Edit::Equal {
+            rows.push(DiffRow {
+                left: Some(l),
+                right: Some(r),
+                kind: RowKind::Same,
+            });
+            l += 1;
+            r += 1;
+            i += 1;
+            continue;
+        }
+
+        // Pair up a run of deletions with the insertions that follow it so
+        // modified lines sit next to each other.
+        let mut deleted = Vec::new();
+        let mut inserted = Vec::new();
+        while i < edits.len() && edits[i] != Edit::Equal {
+            match edits[i] {
+                Edit::Delete => {
+                    deleted.push(l);
+                    l += 1;
+                }
+                Edit::Insert => {
+                    inserted.push(r);
+                    r += 1;
+                }
+                Edit::Equal => unreachable!(),
+            }
+            i += 1;
+        }
+        for k in 0..deleted.len().max(inserted.len()) {
+            let (left, right) = (deleted.get(k).copied(), inserted.get(k).copied());
+            let kind = match (left, right) {
+                (Some(_), Some(_)) => RowKind::Changed,
+                (Some(_), None) => RowKind::Removed,
+                _ => RowKind::Added,
+            };
+            rows.push(DiffRow { left, right, kind });
+        }
+    }
+    rows
+}
+
+/// Myers' O(ND) diff over the lines between a common prefix and suffix.
+fn edit_script(a: &[String], b: &[String]) -> Vec<Edit> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+
+    let mut edits = vec![Edit::Equal; prefix];
+    edits.extend(myers(a_mid, b_mid));
+    edits.extend(std::iter::repeat_n(Edit::Equal, suffix));
+    edits
+}
+
+/// Myers' O(ND) search. Only the diagonals reachable at each step are kept
+/// for the backtrack, so memory grows with the square of the edit distance
+/// rather than with the input size; past `MAX_EDIT_DISTANCE` the search stops
+/// and `line_set_diff` takes over.
+fn myers(a: &[String], b: &[String]) -> Vec<Edit> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (a.len() + b.len()).min(MAX_EDIT_DISTANCE) as isize;
+    if n + m == 0 {
+        return Vec::new();
+    }
+    let offset = max + 1;
+    let mut v = vec![0isize; (2 * max + 3) as usize];
+    // `trace[d]` holds `v[-d..=d]` as it was after step `d`.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut found = false;
+    for d in 0..=max {
+        let mut k = -d;
+        while k <= d {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                found = true;
+                break;
+            }
+            k += 2;
+        }
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        if found {
+            break;
+        }
+    }
+
+    if !found {
+        return line_set_diff(a, b);
+    }
+
+    // Walk the trace backwards to recover the edit script.
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..trace.len()).rev() {
+        let previous = &trace[d - 1];
+        let d = d as isize;
+        let at = |k: isize| previous[(k + d - 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            edits.push(Edit::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if x == prev_x {
+            edits.push(Edit::Insert);
+        } else {
+            edits.push(Edit::Delete);
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    while x > 0 && y > 0 {
+        edits.push(Edit::Equal);
+        x -= 1;
+        y -= 1;
+    }
+    edits.reverse();
+    edits
+}
+
+/// A linear-time fallback for very different inputs: lines are kept in
+/// place when they match, and otherwise dropped from whichever side has no
+/// copy of them left further on.
+fn line_set_diff(a: &[String], b: &[String]) -> Vec<Edit> {
+    let mut left_remaining: HashMap<&str, usize> = HashMap::new();
+    let mut right_remaining: HashMap<&str, usize> = HashMap::new();
+    for line in a {
+        *left_remaining.entry(line).or_default() += 1;
+    }
+    for line in b {
+        *right_remaining.entry(line).or_default() += 1;
+    }
+
+    let mut edits = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let (left, right) = (a[i].as_str(), b[j].as_str());
+        if left == right {
+            edits.push(Edit::Equal);
+            *left_remaining.get_mut(left).unwrap() -= 1;
+            *right_remaining.get_mut(right).unwrap() -= 1;
+            i += 1;
+            j += 1;
+        } else if right_remaining.get(left).is_some_and(|count| *count > 0)
+            && left_remaining.get(right).is_none_or(|count| *count == 0)
+        {
+            edits.push(Edit::Insert);
+            *right_remaining.get_mut(right).unwrap() -= 1;
+            j += 1;
+        } else {
+            edits.push(Edit::Delete);
+            *left_remaining.get_mut(left).unwrap() -= 1;
+            i += 1;
+        }
+    }
+    edits.extend(std::iter::repeat_n(Edit::Delete, a.len() - i));
+    edits.extend(std::iter::repeat_n(Edit::Insert, b.len() - j));
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split(' ').map(String::from).collect()
+    }
+
+    /// Applies `edits` to `a`, taking inserted lines from `b` in order.
+    fn apply(edits: &[Edit], a: &[String], b: &[String]) -> (Vec<String>, Vec<String>) {
+        let (mut left, mut right) = (Vec::new(), Vec::new());
+        let (mut i, mut j) = (0, 0);
+        for edit in edits {
+            match edit {
+                Edit::Equal => {
+                    assert_eq!(a[i], b[j]);
+                    left.push(a[i].clone());
+                    right.push(b[j].clone());
+                    i += 1;
+                    j += 1;
+                }
+                Edit::Delete => {
+                    left.push(a[i].clone());
+                    i += 1;
+                }
+                Edit::Insert => {
+                    right.push(b[j].clone());
+                    j += 1;
+                }
+            }
+        }
+        (left, right)
+    }
+
+    #[test]
+    fn myers_finds_a_minimal_script() {
+        let (a, b) = (lines("a b c a b b a"), lines("c b a b a c"));
+        let edits = myers(&a, &b);
+        assert_eq!(apply(&edits, &a, &b), (a.clone(), b.clone()));
+        let changes = edits.iter().filter(|edit| **edit != Edit::Equal).count();
+        assert_eq!(changes, 5);
+    }
+
+    #[test]
+    fn myers_handles_empty_sides() {
+        assert!(myers(&[], &[]).is_empty());
+        assert_eq!(myers(&lines("a b"), &[]), vec![Edit::Delete, Edit::Delete]);
+        assert_eq!(myers(&[], &lines("a")), vec![Edit::Insert]);
+    }
+
+    #[test]
+    fn line_set_diff_covers_both_sides() {
+        let (a, b) = (lines("x a b y c"), lines("a z b c w"));
+        let edits = line_set_diff(&a, &b);
+        assert_eq!(apply(&edits, &a, &b), (a.clone(), b.clone()));
+        assert_eq!(edits.iter().filter(|edit| **edit == Edit::Equal).count(), 3);
+    }
+
+    #[test]
+    fn distant_inputs_fall_back_without_a_huge_trace() {
+        let a: Vec<String> = (0..3 * MAX_EDIT_DISTANCE).map(|i| format!("a{}", i)).collect();
+        let b: Vec<String> = (0..3 * MAX_EDIT_DISTANCE).map(|i| format!("b{}", i)).collect();
+        let edits = myers(&a, &b);
+        assert_eq!(edits.len(), a.len() + b.len());
+        assert_eq!(apply(&edits, &a, &b), (a, b));
+    }
+
+    #[test]
+    fn noise_is_ignored_when_aligning() {
+        let noise = parse_noise_patterns("token=\\w+").unwrap();
+        let rows = side_by_side(&["<p>", "token=abc", "x"], &["<p>", "token=def", "y"], &noise);
+        let kinds: Vec<RowKind> = rows.iter().map(|row| row.kind).collect();
+        assert!(kinds == vec![RowKind::Same, RowKind::Same, RowKind::Changed]);
+    }
+}
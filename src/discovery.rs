@@ -0,0 +1,272 @@
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use url::Url;
+
+use crate::calibration::{self, Fingerprint};
+use crate::file_processing::{self, Source};
+use crate::network::{self, ClientCache, NetworkConfig, RateLimiter};
+use crate::response_store;
+
+/// Decides which responses count as hits. Responses whose size or word
+/// count is listed are treated as soft-404s and dropped.
+#[derive(Clone, Default)]
+pub struct DiscoveryFilters {
+    pub match_statuses: Vec<u16>,
+    pub hide_sizes: Vec<usize>,
+    pub hide_words: Vec<usize>,
+}
+
+impl DiscoveryFilters {
+    /// Parses comma separated lists; statuses may also be ranges such as
+    /// `200-299`.
+    pub fn parse(statuses: &str, sizes: &str, words: &str) -> Result<Self, String> {
+        let mut match_statuses = Vec::new();
+        for item in split_list(statuses) {
+            match item.split_once('-') {
+                Some((from, to)) => {
+                    let from = parse_number::<u16>(from, "status")?;
+                    let to = parse_number::<u16>(to, "status")?;
+                    match_statuses.extend(from..=to);
+                }
+                None => match_statuses.push(parse_number(item, "status")?),
+            }
+        }
+        Ok(Self {
+            match_statuses,
+            hide_sizes: split_list(sizes)
+                .map(|item| parse_number(item, "size"))
+                .collect::<Result<_, _>>()?,
+            hide_words: split_list(words)
+                .map(|item| parse_number(item, "word count"))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn is_hit(&self, status: u16, size: usize, words: usize) -> bool {
+        (self.match_statuses.is_empty() || self.match_statuses.contains(&status))
+            && !self.hide_sizes.contains(&size)
+            && !self.hide_words.contains(&words)
+    }
+}
+
+fn split_list(text: &str) -> impl Iterator<Item = &str> {
+    text.split(',').map(str::trim).filter(|item| !item.is_empty())
+}
+
+fn parse_number<T: FromStr>(text: &str, what: &str) -> Result<T, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("Invalid {} '{}'", what, text.trim()))
+}
+
+#[derive(Clone)]
+pub struct DiscoveryOptions {
+    pub base_url: String,
+    pub wordlist: PathBuf,
+    pub extensions: Vec<String>,
+    pub filters: DiscoveryFilters,
+    pub threads: u32,
+    pub rate_limit: u32,
+    pub network: NetworkConfig,
+}
+
+#[derive(Clone)]
+pub struct DiscoveryHit {
+    pub url: String,
+    pub status: u16,
+    pub size: usize,
+    pub words: usize,
+    pub is_new: bool,
+}
+
+pub enum DiscoveryProgress {
+    Advanced {
+        completed: usize,
+        total: usize,
+        hit: Option<DiscoveryHit>,
+    },
+    /// Soft-404 fingerprints taken before the run, or why there are none.
+    Calibrated(Result<usize, String>),
+    Finished {
+        hits: usize,
+        soft_404s: usize,
+    },
+    Errored(String),
+}
+
+/// Every word as-is, then once per extension. Blank lines and `#` comments
+/// are skipped.
+pub fn candidate_paths(wordlist: &str, extensions: &[String]) -> Vec<String> {
+    let mut paths = Vec::new();
+    for word in wordlist.lines().map(str::trim) {
+        if word.is_empty() || word.starts_with('#') {
+            continue;
+        }
+        let word = word.trim_start_matches('/');
+        paths.push(word.to_string());
+        for extension in extensions {
+            paths.push(format!("{}.{}", word, extension.trim_start_matches('.')));
+        }
+    }
+    paths
+}
+
+/// Requests every wordlist path under `options.base_url` and adds the hits
+/// to the tree, marked as found by fuzzing.
+pub fn spawn_discovery_thread(
+    db: Arc<sled::Db>,
+    options: DiscoveryOptions,
+    stop: Arc<AtomicBool>,
+) -> Receiver<DiscoveryProgress> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let wordlist = match fs::read_to_string(&options.wordlist) {
+            Ok(wordlist) => wordlist,
+            Err(e) => {
+                let _ = sender.send(DiscoveryProgress::Errored(format!(
+                    "Failed to read wordlist: {}",
+                    e
+                )));
+                return;
+            }
+        };
+        let base_url = match Url::parse(&format!("{}/", options.base_url.trim_end_matches('/'))) {
+            Ok(url) => url,
+            Err(e) => {
+                let _ = sender.send(DiscoveryProgress::Errored(format!("Invalid target: {}", e)));
+                return;
+            }
+        };
+
+        let fingerprints = match calibration::calibrate(&db, &options.network, &base_url) {
+            Ok(fingerprints) => {
+                let _ = sender.send(DiscoveryProgress::Calibrated(Ok(fingerprints.len())));
+                fingerprints
+            }
+            Err(e) => {
+                let _ = sender.send(DiscoveryProgress::Calibrated(Err(e)));
+                Vec::new()
+            }
+        };
+        let fingerprints = Arc::new(fingerprints);
+
+        let paths = Arc::new(candidate_paths(&wordlist, &options.extensions));
+        let total = paths.len();
+        let next_index = Arc::new(AtomicUsize::new(0));
+        let completed = Arc::new(AtomicUsize::new(0));
+        let hits = Arc::new(AtomicUsize::new(0));
+        let soft_404s = Arc::new(AtomicUsize::new(0));
+        let limiter = Arc::new(RateLimiter::new(options.rate_limit));
+
+        let mut thread_handles = Vec::new();
+        for _ in 0..options.threads.max(1).min(total.max(1) as u32) {
+            let db = Arc::clone(&db);
+            let paths = Arc::clone(&paths);
+            let next_index = Arc::clone(&next_index);
+            let completed = Arc::clone(&completed);
+            let hits = Arc::clone(&hits);
+            let soft_404s = Arc::clone(&soft_404s);
+            let fingerprints = Arc::clone(&fingerprints);
+            let limiter = Arc::clone(&limiter);
+            let stop = Arc::clone(&stop);
+            let options = options.clone();
+            let base_url = base_url.clone();
+            let sender = sender.clone();
+
+            let handle = thread::spawn(move || {
+                let mut clients = ClientCache::new(false);
+                while !stop.load(Ordering::SeqCst) {
+                    let index = next_index.fetch_add(1, Ordering::SeqCst);
+                    let Some(path) = paths.get(index) else {
+                        break;
+                    };
+                    let Ok(url) = base_url.join(path) else {
+                        completed.fetch_add(1, Ordering::SeqCst);
+                        continue;
+                    };
+
+                    limiter.wait();
+                    let hit = match probe(&db, &options, &fingerprints, &mut clients, &url) {
+                        Probe::Hit(hit) => {
+                            hits.fetch_add(1, Ordering::SeqCst);
+                            Some(hit)
+                        }
+                        Probe::Soft404 => {
+                            soft_404s.fetch_add(1, Ordering::SeqCst);
+                            None
+                        }
+                        Probe::Miss => None,
+                    };
+                    let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+                    let _ = sender.send(DiscoveryProgress::Advanced {
+                        completed: done,
+                        total,
+                        hit,
+                    });
+                }
+            });
+            thread_handles.push(handle);
+        }
+
+        for handle in thread_handles {
+            let _ = handle.join();
+        }
+        let _ = db.flush();
+        let _ = sender.send(DiscoveryProgress::Finished {
+            hits: hits.load(Ordering::SeqCst),
+            soft_404s: soft_404s.load(Ordering::SeqCst),
+        });
+    });
+
+    receiver
+}
+
+enum Probe {
+    Hit(DiscoveryHit),
+    Soft404,
+    Miss,
+}
+
+/// Fetches one candidate without following redirects, so a redirect to a
+/// login page or a trailing-slash directory is reported as itself.
+fn probe(
+    db: &sled::Db,
+    options: &DiscoveryOptions,
+    fingerprints: &[Fingerprint],
+    clients: &mut ClientCache,
+    url: &Url,
+) -> Probe {
+    let config = &options.network;
+    let Ok((client, proxy)) = clients.client_for(config, url.as_str()) else {
+        return Probe::Miss;
+    };
+
+    let (response, body) = network::fetch_with(config, client, proxy.as_ref(), url.as_str());
+    let Some(status) = response.status else {
+        return Probe::Miss;
+    };
+    let words = String::from_utf8_lossy(&body).split_whitespace().count();
+    if !options.filters.is_hit(status, body.len(), words) {
+        return Probe::Miss;
+    }
+    if calibration::is_soft_404(fingerprints, &response, &body) {
+        return Probe::Soft404;
+    }
+
+    let is_new = file_processing::record_urls(db, std::slice::from_ref(url), Source::Fuzzing)
+        .is_ok_and(|added| added > 0);
+    let _ = response_store::record(db, response, &body);
+    Probe::Hit(DiscoveryHit {
+        url: url.to_string(),
+        status,
+        size: body.len(),
+        words,
+        is_new,
+    })
+}
@@ -0,0 +1,572 @@
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+use crate::calibration;
+use crate::file_processing::{self, NodeValue};
+use crate::file_saver::SaveProgress;
+use crate::openapi::OpenApi;
+use crate::response_store;
+use crate::sitemap::escape_xml;
+use crate::tree_filter::FilterResult;
+
+/// Which endpoints an export covers.
+#[derive(Clone, Default)]
+pub struct ExportScope {
+    /// Tree key of the selected node; the whole project when `None`.
+    pub root: Option<String>,
+    pub skip_soft_404: bool,
+    /// Only endpoints carrying this tag, when not empty.
+    pub tag: String,
+    /// Only the endpoints the sitemap filter bar matched, when it is active.
+    pub filter: Option<Arc<FilterResult>>,
+}
+
+impl ExportScope {
+    fn includes(&self, key: &str, node: &NodeValue) -> bool {
+        node.is_endpoint
+            && !(self.skip_soft_404 && node.tags.contains(calibration::SOFT_404_TAG))
+            && (self.tag.is_empty() || node.tags.contains(self.tag.trim()))
+            && self.filter.as_ref().is_none_or(|filter| filter.is_match(key))
+    }
+}
+
+/// One endpoint as every format sees it.
+pub struct EndpointRow {
+    pub url: String,
+    pub host: String,
+    pub path: String,
+    pub extension: String,
+    /// Query parameter names, in order.
+    pub parameters: Vec<String>,
+    /// Status of the newest stored response, if the endpoint was probed.
+    pub status: Option<u16>,
+    pub content_type: String,
+    pub sources: Vec<String>,
+    pub tags: Vec<String>,
+    /// Distinct method, status and content type combinations in the
+    /// endpoint's stored responses, newest first.
+    pub observed: Vec<Observation>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Observation {
+    pub method: String,
+    pub status: u16,
+    pub content_type: String,
+}
+
+impl EndpointRow {
+    fn new(db: &sled::Db, key: &str, node: &NodeValue) -> Self {
+        let scheme = node.scheme.as_deref().unwrap_or("https");
+        let (host, rest) = key.split_once('/').unwrap_or((key, ""));
+        let path_end = rest.find(['?', '#']).unwrap_or(rest.len());
+        let path = format!("/{}", &rest[..path_end]);
+        let query = rest[path_end..]
+            .strip_prefix('?')
+            .map_or("", |query| query.split('#').next().unwrap_or(""));
+        let extension = path
+            .rsplit('/')
+            .next()
+            .and_then(|name| name.rsplit_once('.'))
+            .map_or(String::new(), |(_, extension)| extension.to_lowercase());
+        let history = response_store::history(db, key);
+        let latest = history.first();
+        let mut observed: Vec<Observation> = Vec::new();
+        for response in &history {
+            let Some(status) = response.status else {
+                continue;
+            };
+            let observation = Observation {
+                method: response.method.clone(),
+                status,
+                content_type: response.content_type.split(';').next().unwrap_or("").trim().to_string(),
+            };
+            if !observed.contains(&observation) {
+                observed.push(observation);
+            }
+        }
+        let mut sources: Vec<String> = node.sources.iter().map(|source| source.label().to_string()).collect();
+        sources.sort();
+        let mut tags: Vec<String> = node.tags.iter().cloned().collect();
+        tags.sort();
+
+        Self {
+            url: if rest.is_empty() {
+                format!("{}://{}/", scheme, host)
+            } else {
+                format!("{}://{}", scheme, key)
+            },
+            host: host.to_string(),
+            path,
+            extension,
+            parameters: query
+                .split('&')
+                .filter_map(|pair| pair.split('=').next())
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect(),
+            status: latest.and_then(|response| response.status),
+            content_type: latest.map(|response| response.content_type.clone()).unwrap_or_default(),
+            sources,
+            tags,
+            observed,
+        }
+    }
+}
+
+/// A node of the exported hierarchy, mirroring the tree's `NodeValue`s.
+pub struct TreeNode {
+    pub name: String,
+    pub scheme: Option<String>,
+    /// Index into `Export::rows` when the node is an exported endpoint.
+    pub row: Option<usize>,
+    pub children: Vec<TreeNode>,
+}
+
+/// Everything an export writes: the endpoints in tree order and the
+/// branches that lead to them.
+pub struct Export {
+    pub rows: Vec<EndpointRow>,
+    pub roots: Vec<TreeNode>,
+}
+
+/// Walks the tree from the scope's root, keeping the branches that contain
+/// an endpoint in scope.
+pub fn collect(db: &sled::Db, scope: &ExportScope) -> Export {
+    let mut export = Export {
+        rows: Vec::new(),
+        roots: Vec::new(),
+    };
+    let starts: Vec<(String, String)> = match &scope.root {
+        Some(root) => vec![(root.clone(), root.clone())],
+        None => {
+            let mut hosts = file_processing::get_children(db, "__ROOT__");
+            hosts.sort();
+            hosts.into_iter().map(|host| (host.clone(), host)).collect()
+        }
+    };
+    for (name, key) in starts {
+        if let Some(node) = walk(db, scope, name, &key, &mut export.rows) {
+            export.roots.push(node);
+        }
+    }
+    export
+}
+
+fn walk(db: &sled::Db, scope: &ExportScope, name: String, key: &str, rows: &mut Vec<EndpointRow>) -> Option<TreeNode> {
+    if scope.filter.as_ref().is_some_and(|filter| !filter.contains(key)) {
+        return None;
+    }
+    let node = file_processing::get_node_value(db, key)?;
+    let row = scope.includes(key, &node).then(|| {
+        rows.push(EndpointRow::new(db, key, &node));
+        rows.len() - 1
+    });
+    let mut child_names: Vec<&String> = node.children.iter().collect();
+    child_names.sort();
+    let children: Vec<TreeNode> = child_names
+        .into_iter()
+        .filter_map(|child| walk(db, scope, child.clone(), &format!("{}/{}", key, child), rows))
+        .collect();
+    (row.is_some() || !children.is_empty()).then_some(TreeNode {
+        name,
+        scheme: node.scheme,
+        row,
+        children,
+    })
+}
+
+/// Totals shown by the report formats.
+struct Stats {
+    hosts: usize,
+    endpoints: usize,
+    with_parameters: usize,
+    /// Endpoints per status class: unprobed, then 1xx to 5xx.
+    status_classes: [usize; 6],
+    sources: BTreeMap<String, usize>,
+    extensions: Vec<(String, usize)>,
+}
+
+const STATUS_CLASS_LABELS: [&str; 6] = ["not probed", "1xx", "2xx", "3xx", "4xx", "5xx"];
+const MAX_LISTED_EXTENSIONS: usize = 10;
+
+impl Stats {
+    fn new(rows: &[EndpointRow]) -> Self {
+        let mut status_classes = [0; 6];
+        let mut sources = BTreeMap::new();
+        let mut extensions: BTreeMap<&str, usize> = BTreeMap::new();
+        for row in rows {
+            let class = row.status.map_or(0, |status| (status as usize / 100).clamp(1, 5));
+            status_classes[class] += 1;
+            for source in &row.sources {
+                *sources.entry(source.clone()).or_insert(0) += 1;
+            }
+            let extension = if row.extension.is_empty() { "(none)" } else { row.extension.as_str() };
+            *extensions.entry(extension).or_insert(0) += 1;
+        }
+        let mut extensions: Vec<(String, usize)> =
+            extensions.into_iter().map(|(extension, count)| (extension.to_string(), count)).collect();
+        extensions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        extensions.truncate(MAX_LISTED_EXTENSIONS);
+
+        Self {
+            hosts: rows.iter().map(|row| row.host.as_str()).collect::<HashSet<_>>().len(),
+            endpoints: rows.len(),
+            with_parameters: rows.iter().filter(|row| !row.parameters.is_empty()).count(),
+            status_classes,
+            sources,
+            extensions,
+        }
+    }
+
+    /// `(label, value)` lines, in display order.
+    fn lines(&self) -> Vec<(String, String)> {
+        let mut lines = vec![
+            ("Hosts".to_string(), self.hosts.to_string()),
+            ("Endpoints".to_string(), self.endpoints.to_string()),
+            ("With parameters".to_string(), self.with_parameters.to_string()),
+        ];
+        for (label, count) in STATUS_CLASS_LABELS.iter().zip(self.status_classes) {
+            if count > 0 {
+                lines.push((format!("Status {}", label), count.to_string()));
+            }
+        }
+        for (source, count) in &self.sources {
+            lines.push((format!("Found by {}", source), count.to_string()));
+        }
+        let extensions: Vec<String> = self
+            .extensions
+            .iter()
+            .map(|(extension, count)| format!("{} ({})", extension, count))
+            .collect();
+        lines.push(("Top extensions".to_string(), extensions.join(", ")));
+        lines
+    }
+}
+
+/// An output format for [`Export`]s.
+pub trait ExportFormat: Send + Sync {
+    fn label(&self) -> &'static str;
+    /// Appended to the file name when it has no extension of its own.
+    fn extension(&self) -> &'static str;
+    fn write(&self, export: &Export, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Lets the entries of [`FORMATS`] be boxed alongside formats that carry
+/// their own options.
+impl<T: ExportFormat + ?Sized> ExportFormat for &T {
+    fn label(&self) -> &'static str {
+        (**self).label()
+    }
+
+    fn extension(&self) -> &'static str {
+        (**self).extension()
+    }
+
+    fn write(&self, export: &Export, out: &mut dyn Write) -> io::Result<()> {
+        (**self).write(export, out)
+    }
+}
+
+/// Every format the export dialog offers.
+pub const FORMATS: &[&dyn ExportFormat] = &[&JsonTree, &Csv, &Jsonl, &Markdown, &HtmlReport, &OpenApi];
+
+fn endpoint_json(row: &EndpointRow) -> Value {
+    json!({
+        "url": row.url,
+        "host": row.host,
+        "path": row.path,
+        "extension": row.extension,
+        "parameters": row.parameters,
+        "status": row.status,
+        "content_type": row.content_type,
+        "sources": row.sources,
+        "tags": row.tags,
+    })
+}
+
+/// A nested object per tree node, with the endpoint's fields on the nodes
+/// that are endpoints.
+pub struct JsonTree;
+
+impl JsonTree {
+    fn node(export: &Export, node: &TreeNode) -> Value {
+        let mut object = Map::new();
+        object.insert("name".to_string(), json!(node.name));
+        if let Some(scheme) = &node.scheme {
+            object.insert("scheme".to_string(), json!(scheme));
+        }
+        if let Some(row) = node.row {
+            object.insert("endpoint".to_string(), endpoint_json(&export.rows[row]));
+        }
+        if !node.children.is_empty() {
+            let children: Vec<Value> = node.children.iter().map(|child| Self::node(export, child)).collect();
+            object.insert("children".to_string(), json!(children));
+        }
+        Value::Object(object)
+    }
+}
+
+impl ExportFormat for JsonTree {
+    fn label(&self) -> &'static str {
+        "JSON tree"
+    }
+
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn write(&self, export: &Export, out: &mut dyn Write) -> io::Result<()> {
+        let roots: Vec<Value> = export.roots.iter().map(|node| Self::node(export, node)).collect();
+        serde_json::to_writer_pretty(&mut *out, &json!({ "nodes": roots }))?;
+        writeln!(out)
+    }
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One row per endpoint; list columns are joined with `;`.
+pub struct Csv;
+
+impl ExportFormat for Csv {
+    fn label(&self) -> &'static str {
+        "CSV"
+    }
+
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn write(&self, export: &Export, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "host,path,extension,parameters,status,content_type,sources,tags,url")?;
+        for row in &export.rows {
+            let fields = [
+                csv_field(&row.host),
+                csv_field(&row.path),
+                csv_field(&row.extension),
+                csv_field(&row.parameters.join(";")),
+                row.status.map_or(String::new(), |status| status.to_string()),
+                csv_field(&row.content_type),
+                csv_field(&row.sources.join(";")),
+                csv_field(&row.tags.join(";")),
+                csv_field(&row.url),
+            ];
+            writeln!(out, "{}", fields.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// One JSON object per line and endpoint.
+pub struct Jsonl;
+
+impl ExportFormat for Jsonl {
+    fn label(&self) -> &'static str {
+        "JSON Lines"
+    }
+
+    fn extension(&self) -> &'static str {
+        "jsonl"
+    }
+
+    fn write(&self, export: &Export, out: &mut dyn Write) -> io::Result<()> {
+        for row in &export.rows {
+            serde_json::to_writer(&mut *out, &endpoint_json(row))?;
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+/// The totals, then a table of endpoints per host.
+pub struct Markdown;
+
+impl ExportFormat for Markdown {
+    fn label(&self) -> &'static str {
+        "Markdown"
+    }
+
+    fn extension(&self) -> &'static str {
+        "md"
+    }
+
+    fn write(&self, export: &Export, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "# Sitemap report\n")?;
+        for (label, value) in Stats::new(&export.rows).lines() {
+            writeln!(out, "- **{}**: {}", label, markdown_cell(&value))?;
+        }
+        let mut host = None;
+        for row in &export.rows {
+            if host != Some(row.host.as_str()) {
+                host = Some(row.host.as_str());
+                writeln!(out, "\n## {}\n", markdown_cell(&row.host))?;
+                writeln!(out, "| Path | Status | Content type | Parameters | Sources | Tags |")?;
+                writeln!(out, "| --- | --- | --- | --- | --- | --- |")?;
+            }
+            let path = match row.url.find(['?', '#']) {
+                Some(start) => format!("{}{}", row.path, &row.url[start..]),
+                None => row.path.clone(),
+            };
+            writeln!(
+                out,
+                "| `{}` | {} | {} | {} | {} | {} |",
+                markdown_cell(&path),
+                row.status.map_or("-".to_string(), |status| status.to_string()),
+                markdown_cell(&row.content_type),
+                markdown_cell(&row.parameters.join(", ")),
+                markdown_cell(&row.sources.join(", ")),
+                markdown_cell(&row.tags.join(", "))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+const HTML_STYLE: &str = "body{font-family:sans-serif;margin:2em;background:#1e1e1e;color:#ddd}\
+    table{border-collapse:collapse}td{padding:2px 12px 2px 0}a{color:#8cf}\
+    details{margin-left:1.2em}summary{cursor:pointer}.s2{color:#8e8}.s3{color:#ee8}.s4{color:#f96}\
+    .s5{color:#f66}.tag{font-size:80%;color:#aaa}";
+
+/// A single page with the totals and the tree as collapsible `<details>`.
+pub struct HtmlReport;
+
+impl HtmlReport {
+    fn node(export: &Export, node: &TreeNode, out: &mut dyn Write) -> io::Result<()> {
+        let label = match node.row.map(|row| &export.rows[row]) {
+            Some(row) => {
+                let status = row.status.map_or(String::new(), |status| {
+                    format!(" <span class=\"s{}\">{}</span>", status / 100, status)
+                });
+                let tags = if row.tags.is_empty() {
+                    String::new()
+                } else {
+                    format!(" <span class=\"tag\">[{}]</span>", escape_xml(&row.tags.join(", ")))
+                };
+                format!(
+                    "<a href=\"{}\">{}</a>{}{}",
+                    escape_xml(&row.url),
+                    escape_xml(&node.name),
+                    status,
+                    tags
+                )
+            }
+            None => escape_xml(&node.name),
+        };
+        if node.children.is_empty() {
+            return writeln!(out, "<div style=\"margin-left:1.2em\">{}</div>", label);
+        }
+        writeln!(out, "<details><summary>{}</summary>", label)?;
+        for child in &node.children {
+            Self::node(export, child, out)?;
+        }
+        writeln!(out, "</details>")
+    }
+}
+
+impl ExportFormat for HtmlReport {
+    fn label(&self) -> &'static str {
+        "HTML report"
+    }
+
+    fn extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn write(&self, export: &Export, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Sitemap report</title><style>{}</style></head><body>",
+            HTML_STYLE
+        )?;
+        writeln!(out, "<h1>Sitemap report</h1>\n<table>")?;
+        for (label, value) in Stats::new(&export.rows).lines() {
+            writeln!(out, "<tr><td>{}</td><td>{}</td></tr>", escape_xml(&label), escape_xml(&value))?;
+        }
+        writeln!(out, "</table>\n<h2>Tree</h2>")?;
+        for node in &export.roots {
+            Self::node(export, node, out)?;
+        }
+        writeln!(out, "</body></html>")
+    }
+}
+
+/// Writes the endpoints in `scope` to `path` in `format`.
+pub fn spawn_export_thread(
+    db: Arc<sled::Db>,
+    format: Box<dyn ExportFormat>,
+    scope: ExportScope,
+    path: PathBuf,
+) -> Receiver<SaveProgress> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let export = collect(&db, &scope);
+        if export.rows.is_empty() {
+            let _ = sender.send(SaveProgress::Errored("No endpoints match the export scope".to_string()));
+            return;
+        }
+        let result = File::create(&path).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            format.write(&export, &mut out)?;
+            out.flush()
+        });
+        let _ = sender.send(match result {
+            Ok(()) => SaveProgress::Finished(format!(
+                "Exported {} endpoints as {} to {}",
+                export.rows.len(),
+                format.label(),
+                path.display()
+            )),
+            Err(e) => SaveProgress::Errored(format!("Failed to write {}: {}", path.display(), e)),
+        });
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree_filter::{self, TreeFilter};
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn filtered_scopes_keep_only_matches() {
+        let db = Arc::new(file_processing::test_db(&[
+            "https://example.com/a",
+            "https://example.com/a/b.js",
+            "https://example.com/c.js",
+        ]));
+        let filter = TreeFilter {
+            extensions: "js".to_string(),
+            ..TreeFilter::default()
+        };
+        let result = tree_filter::spawn_filter_thread(Arc::clone(&db), filter, Arc::new(AtomicBool::new(false)))
+            .recv()
+            .unwrap()
+            .unwrap();
+
+        let scope = ExportScope {
+            filter: Some(Arc::new(result)),
+            ..ExportScope::default()
+        };
+        let mut exported: Vec<String> = collect(&db, &scope).rows.into_iter().map(|row| row.url).collect();
+        exported.sort();
+        assert_eq!(exported, ["https://example.com/a/b.js", "https://example.com/c.js"]);
+        assert_eq!(collect(&db, &ExportScope::default()).rows.len(), 3);
+    }
+}
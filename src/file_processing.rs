@@ -9,11 +9,45 @@ use std::thread;
 use std::time::{Duration, Instant};
 use url::Url;
 
+/// Extensions the sitemap tree, graphs and sitemaps treat as images.
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "svg", "webp"];
+/// Extensions the sitemap tree and graphs treat as code.
+pub const CODE_EXTENSIONS: &[&str] = &["js", "css", "json", "xml", "html"];
+
+/// How an endpoint got into the tree.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Import,
+    Intercept,
+    Spider,
+    Fuzzing,
+    Js,
+    Robots,
+}
+
+impl Source {
+    pub fn label(self) -> &'static str {
+        match self {
+            Source::Import => "imported",
+            Source::Intercept => "intercepted",
+            Source::Spider => "spidered",
+            Source::Fuzzing => "fuzzing",
+            Source::Js => "js-derived",
+            Source::Robots => "robots.txt",
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Default, Clone)]
 pub struct NodeValue {
     pub is_endpoint: bool,
     pub children: HashSet<String>,
     pub scheme: Option<String>,
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    pub sources: HashSet<Source>,
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    pub tags: HashSet<String>,
 }
 
 pub enum Progress {
@@ -33,11 +67,103 @@ pub fn get_children(db: &sled::Db, key: &str) -> Vec<String> {
     get_node_value(db, key).map_or(Vec::new(), |v| v.children.into_iter().collect())
 }
 
+/// Adds or removes `tag` on the node at `key`, leaving other fields as they
+/// are. Missing nodes are left alone.
+pub fn set_tag(db: &sled::Db, key: &str, tag: &str, present: bool) -> Result<(), sled::Error> {
+    db.fetch_and_update(key, |old| {
+        let old = old?;
+        let Ok(mut node) = serde_json::from_slice::<NodeValue>(old) else {
+            return Some(old.to_vec());
+        };
+        if present {
+            node.tags.insert(tag.to_string());
+        } else {
+            node.tags.remove(tag);
+        }
+        serde_json::to_vec(&node).ok()
+    })?;
+    Ok(())
+}
+
+/// Removes `child` from the children of the node at `key`, leaving other
+/// fields as they are.
+pub fn remove_child(db: &sled::Db, key: &str, child: &str) -> Result<(), sled::Error> {
+    db.fetch_and_update(key, |old| {
+        let old = old?;
+        let Ok(mut node) = serde_json::from_slice::<NodeValue>(old) else {
+            return Some(old.to_vec());
+        };
+        node.children.remove(child);
+        serde_json::to_vec(&node).ok()
+    })?;
+    Ok(())
+}
+
+/// Location of a file kept next to the executable, like the project database.
+pub fn data_path(name: &str) -> PathBuf {
+    if let Ok(mut exe_path) = std::env::current_exe() {
+        exe_path.pop();
+        exe_path.push(name);
+        exe_path
+    } else {
+        PathBuf::from(name)
+    }
+}
+
+/// Removes any previous project database and opens an empty one.
+pub fn open_fresh_db() -> Result<sled::Db, String> {
+    let db_path = data_path("maya.db");
+
+    if let Err(e) = std::fs::remove_dir_all(&db_path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        return Err(format!("Failed to remove old db: {}", e));
+    }
+
+    sled::open(db_path).map_err(|e| format!("Failed to open database: {}", e))
+}
 
+/// Reopens the project database left by a previous session, returning it
+/// with its endpoint count.
+pub fn open_existing_db() -> Result<(sled::Db, usize), String> {
+    let db_path = data_path("maya.db");
+    if !db_path.exists() {
+        return Err("No previous project found".to_string());
+    }
+    let db = sled::open(db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+    let endpoint_count = db
+        .iter()
+        .values()
+        .flatten()
+        .filter_map(|value| serde_json::from_slice::<NodeValue>(&value).ok())
+        .filter(|node| node.is_endpoint)
+        .count();
+    Ok((db, endpoint_count))
+}
 
+/// Reopens the previous project database, or creates an empty one when
+/// there is none. Unlike [`open_fresh_db`], nothing is ever deleted.
+pub fn open_or_create_db() -> Result<(sled::Db, usize), String> {
+    if data_path("maya.db").exists() {
+        open_existing_db()
+    } else {
+        let db = sled::open(data_path("maya.db")).map_err(|e| format!("Failed to open database: {}", e))?;
+        Ok((db, 0))
+    }
+}
 
+/// Inserts URLs into the tree as they are discovered, returning how many
+/// endpoints were not already present.
+pub fn record_urls(db: &sled::Db, urls: &[Url], source: Source) -> Result<usize, sled::Error> {
+    let mut url_count = 0;
+    let mut cache: HashMap<String, NodeValue> = HashMap::new();
+    for url in urls {
+        process_url(url, source, &mut url_count, &mut cache);
+    }
+    apply_batch(db, &cache)
+}
 
-fn process_url(url: &Url, url_count: &mut usize, cache: &mut HashMap<String, NodeValue>) {
+fn process_url(url: &Url, source: Source, url_count: &mut usize, cache: &mut HashMap<String, NodeValue>) {
     if let Some(host) = url.host_str() {
         *url_count += 1;
 
@@ -69,6 +195,7 @@ fn process_url(url: &Url, url_count: &mut usize, cache: &mut HashMap<String, Nod
             let endpoint_node = cache.entry(parent_key).or_default();
             endpoint_node.is_endpoint = true;
             endpoint_node.scheme = Some(url.scheme().to_string());
+            endpoint_node.sources.insert(source);
 
         } else {
             let mut leaf_part = String::new();
@@ -84,6 +211,7 @@ fn process_url(url: &Url, url_count: &mut usize, cache: &mut HashMap<String, Nod
             let endpoint_node = cache.entry(endpoint_key).or_default();
             endpoint_node.is_endpoint = true;
             endpoint_node.scheme = Some(url.scheme().to_string());
+            endpoint_node.sources.insert(source);
         }
     }
 }
@@ -92,29 +220,10 @@ pub fn spawn_file_processing_thread(path: PathBuf) -> Receiver<Progress> {
     let (sender, receiver) = mpsc::channel();
 
     thread::spawn(move || {
-        let db_path = if let Ok(mut exe_path) = std::env::current_exe() {
-            exe_path.pop();
-            exe_path.push("maya.db");
-            exe_path
-        } else {
-            PathBuf::from("maya.db")
-        };
-
-        if let Err(e) = std::fs::remove_dir_all(&db_path) {
-            if e.kind() != std::io::ErrorKind::NotFound {
-                sender
-                    .send(Progress::Errored(format!("Failed to remove old db: {}", e)))
-                    .unwrap();
-                return;
-            }
-        }
-
-        let db = match sled::open(db_path) {
+        let db = match open_fresh_db() {
             Ok(db) => db,
             Err(e) => {
-                sender
-                    .send(Progress::Errored(format!("Failed to open database: {}", e)))
-                    .unwrap();
+                sender.send(Progress::Errored(e)).unwrap();
                 return;
             }
         };
@@ -146,17 +255,17 @@ pub fn spawn_file_processing_thread(path: PathBuf) -> Receiver<Progress> {
             }
             bytes_read += bytes as f32;
 
-            let parts = buffer.split(|c| c == '<' || c == '>' || c == '"');
+            let parts = buffer.split(['<', '>', '"']);
             for part in parts {
                 let trimmed_part = part.trim();
                 if !trimmed_part.is_empty() {
                     let sanitized_part = trimmed_part.replace(' ', "%20");
                     if let Ok(url) = Url::parse(&sanitized_part) {
-                        process_url(&url, &mut url_count, &mut cache);
+                        process_url(&url, Source::Import, &mut url_count, &mut cache);
                     } else if sanitized_part.starts_with("//") {
                         let with_scheme = "https:".to_owned() + &sanitized_part;
                         if let Ok(url) = Url::parse(&with_scheme) {
-                            process_url(&url, &mut url_count, &mut cache);
+                            process_url(&url, Source::Import, &mut url_count, &mut cache);
                         }
                     }
                 }
@@ -195,16 +304,16 @@ pub fn spawn_file_processing_thread(path: PathBuf) -> Receiver<Progress> {
             }
         }
 
-        if !cache.is_empty() {
-            if let Err(e) = apply_batch(&db, &cache) {
-                sender
-                    .send(Progress::Errored(format!(
-                        "DB final batch apply error: {}",
-                        e
-                    )))
-                    .unwrap();
-                return;
-            }
+        if !cache.is_empty()
+            && let Err(e) = apply_batch(&db, &cache)
+        {
+            sender
+                .send(Progress::Errored(format!(
+                    "DB final batch apply error: {}",
+                    e
+                )))
+                .unwrap();
+            return;
         }
         
         db.flush().unwrap();
@@ -217,24 +326,82 @@ pub fn spawn_file_processing_thread(path: PathBuf) -> Receiver<Progress> {
     receiver
 }
 
+/// Merges each cached node into the stored one. Every key is updated with
+/// its own compare-and-swap, since spider, discovery, intercept and tagging
+/// threads write to the same parents concurrently and a plain read, merge
+/// and write would lose their children and tags.
 fn apply_batch(
     db: &sled::Db,
     cache: &HashMap<String, NodeValue>,
-) -> Result<(), sled::Error> {
-    let mut batch = sled::Batch::default();
+) -> Result<usize, sled::Error> {
+    let mut new_endpoints = 0;
     for (key, value) in cache.iter() {
-        let existing_value: NodeValue = db
-            .get(key)
-            .unwrap_or(None)
-            .and_then(|ivec| serde_json::from_slice(&ivec).ok())
-            .unwrap_or_default();
-
-        let mut new_value = value.clone();
-        new_value.is_endpoint = new_value.is_endpoint || existing_value.is_endpoint;
-        new_value.children.extend(existing_value.children);
-
-        let encoded = serde_json::to_vec(&new_value).unwrap();
-        batch.insert(key.as_bytes(), encoded);
+        let previous = db.fetch_and_update(key.as_bytes(), |old| {
+            let mut merged = value.clone();
+            if let Some(existing) = old.and_then(|old| serde_json::from_slice::<NodeValue>(old).ok()) {
+                merged.is_endpoint |= existing.is_endpoint;
+                merged.children.extend(existing.children);
+                merged.sources.extend(existing.sources);
+                merged.tags.extend(existing.tags);
+                if merged.scheme.is_none() {
+                    merged.scheme = existing.scheme;
+                }
+            }
+            serde_json::to_vec(&merged).ok()
+        })?;
+        let was_endpoint = previous
+            .and_then(|old| serde_json::from_slice::<NodeValue>(&old).ok())
+            .is_some_and(|existing| existing.is_endpoint);
+        if value.is_endpoint && !was_endpoint {
+            new_endpoints += 1;
+        }
+    }
+    Ok(new_endpoints)
+}
+
+/// Temporary project holding `urls`, for tests of modules that read the tree.
+#[cfg(test)]
+pub fn test_db(urls: &[&str]) -> sled::Db {
+    let db = sled::Config::new().temporary(true).open().unwrap();
+    let urls: Vec<Url> = urls.iter().map(|url| Url::parse(url).unwrap()).collect();
+    record_urls(&db, &urls, Source::Import).unwrap();
+    db
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_writers_keep_every_child() {
+        let db = Arc::new(sled::Config::new().temporary(true).open().unwrap());
+        let handles: Vec<_> = (0..8)
+            .map(|worker| {
+                let db = Arc::clone(&db);
+                thread::spawn(move || {
+                    for page in 0..50 {
+                        let url = Url::parse(&format!("https://example.com/dir/{}-{}", worker, page)).unwrap();
+                        record_urls(&db, &[url], Source::Spider).unwrap();
+                        set_tag(&db, "example.com/dir", "busy", page % 2 == 0).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(get_children(&db, "example.com/dir").len(), 400);
+        assert_eq!(get_children(&db, "__ROOT__"), ["example.com"]);
     }
-    db.apply_batch(batch)
-}
\ No newline at end of file
+
+    #[test]
+    fn counts_only_new_endpoints() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let urls = [Url::parse("https://example.com/a").unwrap(), Url::parse("https://example.com/a/b").unwrap()];
+        assert_eq!(record_urls(&db, &urls, Source::Import).unwrap(), 2);
+        assert_eq!(record_urls(&db, &urls[..1], Source::Spider).unwrap(), 0);
+        let node = get_node_value(&db, "example.com/a").unwrap();
+        assert!(node.is_endpoint && node.children.contains("b"));
+        assert_eq!(node.sources.len(), 2);
+    }
+}
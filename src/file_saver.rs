@@ -6,7 +6,8 @@ use std::sync::Arc;
 use std::thread;
 
 pub enum SaveProgress {
-    Finished,
+    /// A summary of what was written.
+    Finished(String),
     Errored(String),
 }
 
@@ -14,29 +15,32 @@ pub fn spawn_file_saving_thread(db: Arc<sled::Db>, path: PathBuf) -> Receiver<Sa
     let (sender, receiver) = mpsc::channel();
     thread::spawn(move || {
         let mut endpoints = Vec::new();
-        for item in db.iter() {
-            if let Ok((key, _value)) = item {
-                if let Ok(key_str) = std::str::from_utf8(&key) {
-                    endpoints.push(key_str.to_string());
-                }
+        for (key, _value) in db.iter().flatten() {
+            if let Ok(key_str) = std::str::from_utf8(&key) {
+                endpoints.push(key_str.to_string());
             }
         }
 
         match File::create(&path) {
             Ok(mut file) => {
                 endpoints.sort();
+                let count = endpoints.iter().filter(|url| *url != "__ROOT__").count();
                 for url in endpoints {
-                    if url != "__ROOT__" {
-                        if let Err(e) = writeln!(file, "{}", url) {
-                            let _ = sender.send(SaveProgress::Errored(format!(
-                                "Failed to write to file: {}",
-                                e
-                            )));
-                            return;
-                        }
+                    if url != "__ROOT__"
+                        && let Err(e) = writeln!(file, "{}", url)
+                    {
+                        let _ = sender.send(SaveProgress::Errored(format!(
+                            "Failed to write to file: {}",
+                            e
+                        )));
+                        return;
                     }
                 }
-                let _ = sender.send(SaveProgress::Finished);
+                let _ = sender.send(SaveProgress::Finished(format!(
+                    "Saved {} entries to {}",
+                    count,
+                    path.display()
+                )));
             }
             Err(e) => {
                 let _ =
@@ -0,0 +1,618 @@
+use std::fs;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+use crate::network::{self, ClientCache, NetworkConfig, NetworkResult, RateLimiter};
+
+/// Surrounds a payload position in a request template, as in `id=§1§`.
+pub const MARKER: char = '§';
+
+/// Response bodies are cut to this size when kept for the results table.
+const MAX_KEPT_BODY: usize = 64 * 1024;
+
+/// Case permutations double with every letter, so longer words are refused.
+const MAX_PERMUTED_LETTERS: usize = 12;
+
+/// The most payloads one number range may produce.
+const MAX_PAYLOADS: usize = 1_000_000;
+
+/// The most requests one attack may send; larger cluster bombs are refused
+/// up front rather than enumerated.
+const MAX_REQUESTS: usize = 10_000_000;
+
+/// Splits a template into the literal text around positions and the
+/// default value inside each position.
+fn split_template(template: &str) -> Result<(Vec<&str>, Vec<&str>), String> {
+    let parts: Vec<&str> = template.split(MARKER).collect();
+    if parts.len().is_multiple_of(2) {
+        return Err(format!("Unbalanced {} markers", MARKER));
+    }
+    let literals = parts.iter().step_by(2).copied().collect();
+    let defaults = parts.iter().skip(1).step_by(2).copied().collect();
+    Ok((literals, defaults))
+}
+
+pub fn position_defaults(template: &str) -> Result<Vec<String>, String> {
+    split_template(template).map(|(_, defaults)| defaults.into_iter().map(String::from).collect())
+}
+
+/// The template with every position replaced by the matching value.
+pub fn render(template: &str, values: &[String]) -> Result<String, String> {
+    let (literals, defaults) = split_template(template)?;
+    let mut rendered = String::new();
+    for (index, literal) in literals.iter().enumerate() {
+        rendered.push_str(literal);
+        if index < defaults.len() {
+            rendered.push_str(values.get(index).map_or(defaults[index], String::as_str));
+        }
+    }
+    Ok(rendered)
+}
+
+/// Replaces any existing positions with one around the value of every
+/// query parameter in the request line.
+pub fn mark_query_values(template: &str) -> String {
+    let template = template.replace(MARKER, "");
+    let (request_line, rest) = match template.split_once('\n') {
+        Some((line, rest)) => (line, Some(rest)),
+        None => (template.as_str(), None),
+    };
+    let Some((before, query)) = request_line.split_once('?') else {
+        return template.clone();
+    };
+    let (query, after) = query.split_once(' ').unwrap_or((query, ""));
+    let marked: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, value)) => format!("{}={}{}{}", name, MARKER, value, MARKER),
+            None => pair.to_string(),
+        })
+        .collect();
+
+    let mut result = format!("{}?{}", before, marked.join("&"));
+    if !after.is_empty() {
+        result.push(' ');
+        result.push_str(after);
+    }
+    if let Some(rest) = rest {
+        result.push('\n');
+        result.push_str(rest);
+    }
+    result
+}
+
+pub struct ParsedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// Parses a raw HTTP/1.1 request. The URL is built from the Host header and
+/// the request target unless the target is already absolute.
+pub fn parse_request(text: &str, scheme: &str) -> Result<ParsedRequest, String> {
+    let text = text.replace("\r\n", "\n");
+    let (head, body) = text.split_once("\n\n").unwrap_or((text.as_str(), ""));
+    let mut lines = head.lines();
+    let request_line = lines.next().ok_or("Empty request")?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().ok_or("Missing method")?.to_string();
+    let target = parts.next().ok_or("Missing request target")?;
+
+    let mut host = None;
+    let mut headers = Vec::new();
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, value) = (name.trim(), value.trim());
+        if name.eq_ignore_ascii_case("host") {
+            host = Some(value.to_string());
+        } else if !name.eq_ignore_ascii_case("content-length") {
+            headers.push((name.to_string(), value.to_string()));
+        }
+    }
+
+    let url = if target.starts_with("http://") || target.starts_with("https://") {
+        target.to_string()
+    } else {
+        format!("{}://{}{}", scheme, host.ok_or("Missing Host header")?, target)
+    };
+    Ok(ParsedRequest {
+        method,
+        url,
+        headers,
+        body: body.to_string(),
+    })
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum AttackMode {
+    /// One payload set, tried in each position in turn.
+    #[default]
+    Sniper,
+    /// One payload set, placed in every position at once.
+    BatteringRam,
+    /// One set per position, advanced together.
+    Pitchfork,
+    /// One set per position, every combination.
+    ClusterBomb,
+}
+
+impl AttackMode {
+    pub const ALL: [AttackMode; 4] = [
+        AttackMode::Sniper,
+        AttackMode::BatteringRam,
+        AttackMode::Pitchfork,
+        AttackMode::ClusterBomb,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AttackMode::Sniper => "Sniper",
+            AttackMode::BatteringRam => "Battering ram",
+            AttackMode::Pitchfork => "Pitchfork",
+            AttackMode::ClusterBomb => "Cluster bomb",
+        }
+    }
+
+    pub fn set_count(self, positions: usize) -> usize {
+        match self {
+            AttackMode::Sniper | AttackMode::BatteringRam => 1,
+            AttackMode::Pitchfork | AttackMode::ClusterBomb => positions,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum PayloadKind {
+    #[default]
+    List,
+    Wordlist,
+    Numbers,
+    CasePermutations,
+}
+
+impl PayloadKind {
+    pub const ALL: [PayloadKind; 4] = [
+        PayloadKind::List,
+        PayloadKind::Wordlist,
+        PayloadKind::Numbers,
+        PayloadKind::CasePermutations,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PayloadKind::List => "Simple list",
+            PayloadKind::Wordlist => "Wordlist file",
+            PayloadKind::Numbers => "Numbers",
+            PayloadKind::CasePermutations => "Case permutations",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Encoder {
+    #[default]
+    None,
+    Url,
+    DoubleUrl,
+    Base64,
+    Html,
+    Hex,
+}
+
+impl Encoder {
+    pub const ALL: [Encoder; 6] = [
+        Encoder::None,
+        Encoder::Url,
+        Encoder::DoubleUrl,
+        Encoder::Base64,
+        Encoder::Html,
+        Encoder::Hex,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Encoder::None => "No encoding",
+            Encoder::Url => "URL",
+            Encoder::DoubleUrl => "Double URL",
+            Encoder::Base64 => "Base64",
+            Encoder::Html => "HTML entities",
+            Encoder::Hex => "Hex",
+        }
+    }
+
+    pub fn encode(self, payload: &str) -> String {
+        match self {
+            Encoder::None => payload.to_string(),
+            Encoder::Url => percent_encode(payload),
+            Encoder::DoubleUrl => percent_encode(&percent_encode(payload)),
+            Encoder::Base64 => openssl::base64::encode_block(payload.as_bytes()),
+            Encoder::Html => payload
+                .chars()
+                .map(|c| match c {
+                    '&' => "&amp;".to_string(),
+                    '<' => "&lt;".to_string(),
+                    '>' => "&gt;".to_string(),
+                    '"' => "&quot;".to_string(),
+                    '\'' => "&#39;".to_string(),
+                    c => c.to_string(),
+                })
+                .collect(),
+            Encoder::Hex => payload.bytes().map(|b| format!("{:02x}", b)).collect(),
+        }
+    }
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// One payload set as edited in the fuzzer window; only the fields of the
+/// selected kind are used.
+#[derive(Clone)]
+pub struct PayloadSet {
+    pub kind: PayloadKind,
+    pub list: String,
+    pub wordlist: String,
+    pub from: i64,
+    pub to: i64,
+    pub step: i64,
+    pub word: String,
+    pub encoder: Encoder,
+}
+
+impl Default for PayloadSet {
+    fn default() -> Self {
+        Self {
+            kind: PayloadKind::default(),
+            list: String::new(),
+            wordlist: "wordlist.txt".to_string(),
+            from: 1,
+            to: 100,
+            step: 1,
+            word: String::new(),
+            encoder: Encoder::default(),
+        }
+    }
+}
+
+impl PayloadSet {
+    pub fn payloads(&self) -> Result<Vec<String>, String> {
+        let raw = match self.kind {
+            PayloadKind::List => self.list.lines().map(String::from).collect(),
+            PayloadKind::Wordlist => fs::read_to_string(&self.wordlist)
+                .map_err(|e| format!("Failed to read {}: {}", self.wordlist, e))?
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect(),
+            PayloadKind::Numbers => {
+                let step = self.step.max(1);
+                let count = (self.to as i128 - self.from as i128).unsigned_abs() / step as u128 + 1;
+                if count > MAX_PAYLOADS as u128 {
+                    return Err(format!(
+                        "The range {} to {} step {} gives {} numbers; the limit is {}",
+                        self.from, self.to, step, count, MAX_PAYLOADS
+                    ));
+                }
+                let mut numbers = Vec::with_capacity(count as usize);
+                let mut n = Some(self.from);
+                if self.from <= self.to {
+                    while let Some(value) = n.filter(|value| *value <= self.to) {
+                        numbers.push(value.to_string());
+                        n = value.checked_add(step);
+                    }
+                } else {
+                    while let Some(value) = n.filter(|value| *value >= self.to) {
+                        numbers.push(value.to_string());
+                        n = value.checked_sub(step);
+                    }
+                }
+                numbers
+            }
+            PayloadKind::CasePermutations => case_permutations(&self.word)?,
+        };
+        Ok(raw.iter().map(|payload| self.encoder.encode(payload)).collect())
+    }
+}
+
+fn case_permutations(word: &str) -> Result<Vec<String>, String> {
+    let chars: Vec<char> = word.chars().collect();
+    let letters: Vec<usize> = (0..chars.len()).filter(|i| chars[*i].is_alphabetic()).collect();
+    if letters.len() > MAX_PERMUTED_LETTERS {
+        return Err(format!(
+            "Case permutations are limited to {} letters",
+            MAX_PERMUTED_LETTERS
+        ));
+    }
+    Ok((0..1usize << letters.len())
+        .map(|mask| {
+            let mut permuted = chars.clone();
+            for (bit, index) in letters.iter().enumerate() {
+                let c = permuted[*index];
+                permuted[*index] = if mask & (1 << bit) != 0 {
+                    c.to_uppercase().next().unwrap_or(c)
+                } else {
+                    c.to_lowercase().next().unwrap_or(c)
+                };
+            }
+            permuted.into_iter().collect()
+        })
+        .collect())
+}
+
+/// Maps a request number to the values placed in each position.
+pub struct Attack {
+    pub mode: AttackMode,
+    pub defaults: Vec<String>,
+    pub sets: Vec<Vec<String>>,
+}
+
+impl Attack {
+    /// The number of requests the attack sends, or an error when that is
+    /// more than `MAX_REQUESTS`.
+    pub fn len(&self) -> Result<usize, String> {
+        let positions = self.defaults.len();
+        if positions == 0 || self.sets.is_empty() {
+            return Ok(0);
+        }
+        let total = match self.mode {
+            AttackMode::Sniper => positions.checked_mul(self.sets[0].len()),
+            AttackMode::BatteringRam => Some(self.sets[0].len()),
+            AttackMode::Pitchfork => Some(self.sets.iter().map(Vec::len).min().unwrap_or(0)),
+            AttackMode::ClusterBomb => self
+                .sets
+                .iter()
+                .try_fold(1usize, |total, set| total.checked_mul(set.len())),
+        };
+        match total {
+            Some(total) if total <= MAX_REQUESTS => Ok(total),
+            _ => Err(format!(
+                "This attack would send more than {} requests; use smaller payload sets",
+                MAX_REQUESTS
+            )),
+        }
+    }
+
+    /// The value for every position and the payloads that were used.
+    pub fn values(&self, index: usize) -> (Vec<String>, Vec<String>) {
+        let mut values = self.defaults.clone();
+        match self.mode {
+            AttackMode::Sniper => {
+                let set = &self.sets[0];
+                let payload = set[index % set.len()].clone();
+                values[index / set.len()] = payload.clone();
+                (values, vec![payload])
+            }
+            AttackMode::BatteringRam => {
+                let payload = self.sets[0][index].clone();
+                values.iter_mut().for_each(|value| *value = payload.clone());
+                (values, vec![payload])
+            }
+            AttackMode::Pitchfork => {
+                for (value, set) in values.iter_mut().zip(&self.sets) {
+                    *value = set[index].clone();
+                }
+                (values.clone(), values)
+            }
+            AttackMode::ClusterBomb => {
+                // The last position changes fastest.
+                let mut rest = index;
+                for (value, set) in values.iter_mut().zip(&self.sets).rev() {
+                    *value = set[rest % set.len()].clone();
+                    rest /= set.len();
+                }
+                (values.clone(), values)
+            }
+        }
+    }
+}
+
+pub struct FuzzOptions {
+    pub template: String,
+    pub scheme: String,
+    pub attack: Attack,
+    pub grep: Vec<String>,
+    pub threads: u32,
+    pub rate_limit: u32,
+    pub network: NetworkConfig,
+}
+
+#[derive(Clone)]
+pub struct FuzzResult {
+    pub index: usize,
+    pub payloads: Vec<String>,
+    pub status: Option<u16>,
+    pub length: usize,
+    pub elapsed_ms: u128,
+    /// The grep terms found in the response.
+    pub matches: Vec<String>,
+    /// The response, with the body cut to `MAX_KEPT_BODY`.
+    pub response: NetworkResult,
+}
+
+pub enum FuzzProgress {
+    Advanced {
+        completed: usize,
+        total: usize,
+        result: Box<FuzzResult>,
+    },
+    Finished,
+    Errored(String),
+}
+
+pub fn spawn_fuzz_thread(options: FuzzOptions, stop: Arc<AtomicBool>) -> Receiver<FuzzProgress> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let total = match options.attack.len() {
+            Ok(total) => total,
+            Err(e) => {
+                let _ = sender.send(FuzzProgress::Errored(e));
+                return;
+            }
+        };
+        if total == 0 {
+            let _ = sender.send(FuzzProgress::Errored(
+                "Nothing to send: add positions and payloads".to_string(),
+            ));
+            return;
+        }
+
+        let options = Arc::new(options);
+        let next_index = Arc::new(AtomicUsize::new(0));
+        let completed = Arc::new(AtomicUsize::new(0));
+        let limiter = Arc::new(RateLimiter::new(options.rate_limit));
+
+        let mut thread_handles = Vec::new();
+        for _ in 0..options.threads.max(1).min(total.min(u32::MAX as usize) as u32) {
+            let options = Arc::clone(&options);
+            let next_index = Arc::clone(&next_index);
+            let completed = Arc::clone(&completed);
+            let limiter = Arc::clone(&limiter);
+            let stop = Arc::clone(&stop);
+            let sender = sender.clone();
+
+            let handle = thread::spawn(move || {
+                let mut clients = ClientCache::new(false);
+                while !stop.load(Ordering::SeqCst) {
+                    let index = next_index.fetch_add(1, Ordering::SeqCst);
+                    if index >= total {
+                        break;
+                    }
+                    limiter.wait();
+                    let result = send_one(&options, &mut clients, index);
+                    let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+                    let _ = sender.send(FuzzProgress::Advanced {
+                        completed: done,
+                        total,
+                        result: Box::new(result),
+                    });
+                }
+            });
+            thread_handles.push(handle);
+        }
+
+        for handle in thread_handles {
+            let _ = handle.join();
+        }
+        let _ = sender.send(FuzzProgress::Finished);
+    });
+
+    receiver
+}
+
+fn send_one(options: &FuzzOptions, clients: &mut ClientCache, index: usize) -> FuzzResult {
+    let (values, payloads) = options.attack.values(index);
+    let failed = |error: String| FuzzResult {
+        index,
+        payloads: payloads.clone(),
+        status: None,
+        length: 0,
+        elapsed_ms: 0,
+        matches: Vec::new(),
+        response: (Default::default(), format!("Error: {}", error).into_bytes()),
+    };
+
+    let request = match render(&options.template, &values)
+        .and_then(|text| parse_request(&text, &options.scheme))
+    {
+        Ok(request) => request,
+        Err(e) => return failed(e),
+    };
+    let method = match reqwest::Method::from_bytes(request.method.as_bytes()) {
+        Ok(method) => method,
+        Err(e) => return failed(e.to_string()),
+    };
+    let (client, proxy) = match clients.client_for(&options.network, &request.url) {
+        Ok(client) => client,
+        Err(e) => return failed(e),
+    };
+
+    let mut builder = client.request(method, &request.url);
+    for (name, value) in &request.headers {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    if !request.body.is_empty() {
+        builder = builder.body(request.body.clone());
+    }
+    let (response, mut body) =
+        network::send_with(&options.network, client, proxy.as_ref(), builder, &request.url);
+
+    let length = body.len();
+    let haystack = format!("{}\n{}", response.header_block(), String::from_utf8_lossy(&body)).to_lowercase();
+    let matches = options
+        .grep
+        .iter()
+        .filter(|term| haystack.contains(&term.to_lowercase()))
+        .cloned()
+        .collect();
+    body.truncate(MAX_KEPT_BODY);
+    FuzzResult {
+        index,
+        payloads,
+        status: response.status,
+        length,
+        elapsed_ms: response.elapsed_ms,
+        matches,
+        response: (response, body),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(from: i64, to: i64, step: i64) -> Result<Vec<String>, String> {
+        PayloadSet {
+            kind: PayloadKind::Numbers,
+            from,
+            to,
+            step,
+            ..PayloadSet::default()
+        }
+        .payloads()
+    }
+
+    #[test]
+    fn number_ranges_count_both_ways() {
+        assert_eq!(numbers(1, 7, 3).unwrap(), ["1", "4", "7"]);
+        assert_eq!(numbers(3, 1, 1).unwrap(), ["3", "2", "1"]);
+    }
+
+    #[test]
+    fn number_ranges_stop_at_the_integer_limits() {
+        assert_eq!(numbers(i64::MAX - 1, i64::MAX, 1).unwrap().len(), 2);
+        assert_eq!(numbers(i64::MIN + 1, i64::MIN, 1).unwrap().len(), 2);
+        assert_eq!(numbers(i64::MAX - 2, i64::MAX, 2).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn oversized_number_ranges_are_refused() {
+        assert!(numbers(1, MAX_PAYLOADS as i64 + 1, 1).is_err());
+        assert!(numbers(i64::MIN, i64::MAX, 1).is_err());
+        assert_eq!(numbers(1, MAX_PAYLOADS as i64, 1).unwrap().len(), MAX_PAYLOADS);
+    }
+
+    #[test]
+    fn oversized_cluster_bombs_are_refused() {
+        let attack = |mode, size: usize| Attack {
+            mode,
+            defaults: vec![String::new(); 4],
+            sets: vec![vec![String::new(); size]; 4],
+        };
+        assert_eq!(attack(AttackMode::ClusterBomb, 10).len(), Ok(10_000));
+        assert!(attack(AttackMode::ClusterBomb, 100).len().is_err());
+        assert_eq!(attack(AttackMode::Pitchfork, 100).len(), Ok(100));
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::export::{Export, ExportFormat, TreeNode};
+use crate::file_processing::{CODE_EXTENSIONS, IMAGE_EXTENSIONS};
+
+/// Fill colours, matching the sitemap tree where it has one.
+const HTTP_COLOR: &str = "#ffb4b4";
+const HTTPS_COLOR: &str = "#b4ffb4";
+const FOLDER_COLOR: &str = "#ffd764";
+const IMAGE_COLOR: &str = "#c878ff";
+const CODE_COLOR: &str = "#b4b4b4";
+const FILE_COLOR: &str = "#96c8ff";
+const UNPROBED_COLOR: &str = "#e0e0e0";
+const STATUS_COLORS: [&str; 5] = ["#96c8ff", "#8ee88e", "#eeee88", "#ff9966", "#ff6666"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum GraphKind {
+    Dot,
+    Mermaid,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorBy {
+    Scheme,
+    Status,
+    FileType,
+}
+
+impl ColorBy {
+    pub const ALL: [ColorBy; 3] = [ColorBy::Scheme, ColorBy::Status, ColorBy::FileType];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorBy::Scheme => "Scheme",
+            ColorBy::Status => "Status code",
+            ColorBy::FileType => "File type",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct GraphOptions {
+    pub kind: GraphKind,
+    pub color_by: ColorBy,
+    /// Levels below this depth are folded into a count; 0 shows every level.
+    pub max_depth: usize,
+    /// Replaces the leaves of each node with one node per colour group.
+    pub aggregate_leaves: bool,
+}
+
+impl Default for GraphOptions {
+    fn default() -> Self {
+        Self {
+            kind: GraphKind::Dot,
+            color_by: ColorBy::Scheme,
+            max_depth: 0,
+            aggregate_leaves: true,
+        }
+    }
+}
+
+struct GraphNode {
+    label: String,
+    color: &'static str,
+}
+
+#[derive(Default)]
+struct Builder {
+    nodes: Vec<GraphNode>,
+    edges: Vec<(usize, usize)>,
+}
+
+fn endpoint_count(node: &TreeNode) -> usize {
+    node.children
+        .iter()
+        .map(|child| usize::from(child.row.is_some()) + endpoint_count(child))
+        .sum()
+}
+
+/// The colour of a node and, for leaves, the name of the group it is
+/// aggregated into.
+fn category(export: &Export, node: &TreeNode, color_by: ColorBy, is_host: bool) -> (&'static str, String) {
+    let row = node.row.map(|row| &export.rows[row]);
+    match color_by {
+        ColorBy::Scheme => match node.scheme.as_deref() {
+            Some("http") if is_host => (HTTP_COLOR, "http".to_string()),
+            Some("https") if is_host => (HTTPS_COLOR, "https".to_string()),
+            _ if !node.children.is_empty() => (FOLDER_COLOR, "folders".to_string()),
+            _ => (FILE_COLOR, "endpoints".to_string()),
+        },
+        ColorBy::Status => match row.and_then(|row| row.status) {
+            Some(status) => {
+                let class = (status as usize / 100).clamp(1, 5);
+                (STATUS_COLORS[class - 1], format!("{}xx", class))
+            }
+            None if row.is_none() => (FOLDER_COLOR, "folders".to_string()),
+            None => (UNPROBED_COLOR, "not probed".to_string()),
+        },
+        ColorBy::FileType => {
+            if !node.children.is_empty() || is_host {
+                return (FOLDER_COLOR, "folders".to_string());
+            }
+            let extension = row.map_or("", |row| row.extension.as_str());
+            let color = if IMAGE_EXTENSIONS.contains(&extension) {
+                IMAGE_COLOR
+            } else if CODE_EXTENSIONS.contains(&extension) {
+                CODE_COLOR
+            } else {
+                FILE_COLOR
+            };
+            let group = if extension.is_empty() {
+                "no extension".to_string()
+            } else {
+                format!(".{}", extension)
+            };
+            (color, group)
+        }
+    }
+}
+
+impl Builder {
+    fn push(&mut self, parent: Option<usize>, label: String, color: &'static str) -> usize {
+        self.nodes.push(GraphNode { label, color });
+        let id = self.nodes.len() - 1;
+        if let Some(parent) = parent {
+            self.edges.push((parent, id));
+        }
+        id
+    }
+
+    fn add(&mut self, export: &Export, node: &TreeNode, depth: usize, parent: Option<usize>, options: &GraphOptions) {
+        let (color, _) = category(export, node, options.color_by, depth == 0);
+        let mut label = node.name.clone();
+        let folded = options.max_depth > 0 && depth >= options.max_depth && !node.children.is_empty();
+        if folded {
+            let count = endpoint_count(node);
+            label.push_str(&format!("\n(+{} endpoint{})", count, if count == 1 { "" } else { "s" }));
+        }
+        let id = self.push(parent, label, color);
+        if folded {
+            return;
+        }
+
+        let (leaves, branches): (Vec<&TreeNode>, Vec<&TreeNode>) =
+            node.children.iter().partition(|child| child.children.is_empty());
+        if options.aggregate_leaves && leaves.len() > 1 {
+            let mut groups: BTreeMap<String, (&'static str, Vec<&TreeNode>)> = BTreeMap::new();
+            for leaf in leaves {
+                let (color, group) = category(export, leaf, options.color_by, false);
+                groups.entry(group).or_insert((color, Vec::new())).1.push(leaf);
+            }
+            for (group, (color, members)) in groups {
+                if let [leaf] = members.as_slice() {
+                    self.add(export, leaf, depth + 1, Some(id), options);
+                } else {
+                    self.push(Some(id), format!("{} × {}", members.len(), group), color);
+                }
+            }
+        } else {
+            for leaf in leaves {
+                self.add(export, leaf, depth + 1, Some(id), options);
+            }
+        }
+        for branch in branches {
+            self.add(export, branch, depth + 1, Some(id), options);
+        }
+    }
+}
+
+fn dot_label(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn mermaid_label(label: &str) -> String {
+    label.replace('"', "#quot;").replace('\n', "<br/>")
+}
+
+/// The exported branches as a Graphviz or Mermaid graph.
+pub struct Graph(pub GraphOptions);
+
+impl ExportFormat for Graph {
+    fn label(&self) -> &'static str {
+        match self.0.kind {
+            GraphKind::Dot => "Graphviz DOT",
+            GraphKind::Mermaid => "Mermaid",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self.0.kind {
+            GraphKind::Dot => "dot",
+            GraphKind::Mermaid => "mmd",
+        }
+    }
+
+    fn write(&self, export: &Export, out: &mut dyn Write) -> io::Result<()> {
+        let mut builder = Builder::default();
+        for root in &export.roots {
+            builder.add(export, root, 0, None, &self.0);
+        }
+
+        match self.0.kind {
+            GraphKind::Dot => {
+                writeln!(out, "digraph sitemap {{")?;
+                writeln!(out, "  rankdir=LR;")?;
+                writeln!(out, "  node [shape=box, style=\"rounded,filled\", fontname=\"Helvetica\"];")?;
+                for (id, node) in builder.nodes.iter().enumerate() {
+                    writeln!(out, "  n{} [label=\"{}\", fillcolor=\"{}\"];", id, dot_label(&node.label), node.color)?;
+                }
+                for (from, to) in &builder.edges {
+                    writeln!(out, "  n{} -> n{};", from, to)?;
+                }
+                writeln!(out, "}}")
+            }
+            GraphKind::Mermaid => {
+                writeln!(out, "graph LR")?;
+                for (id, node) in builder.nodes.iter().enumerate() {
+                    writeln!(out, "  n{}[\"{}\"]", id, mermaid_label(&node.label))?;
+                }
+                for (from, to) in &builder.edges {
+                    writeln!(out, "  n{} --> n{}", from, to)?;
+                }
+                for (id, node) in builder.nodes.iter().enumerate() {
+                    writeln!(out, "  style n{} fill:{}", id, node.color)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{self, ExportScope};
+    use crate::file_processing;
+
+    fn render(options: GraphOptions) -> String {
+        let db = file_processing::test_db(&[
+            "https://example.com/a.png",
+            "https://example.com/b.png",
+            "https://example.com/app.js",
+            "https://example.com/docs/guide/intro",
+            "https://example.com/docs/guide/setup",
+        ]);
+        let mut out = Vec::new();
+        Graph(options)
+            .write(&export::collect(&db, &ExportScope::default()), &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn dot_folds_deep_levels_and_aggregates_leaves() {
+        let dot = render(GraphOptions {
+            kind: GraphKind::Dot,
+            color_by: ColorBy::FileType,
+            max_depth: 1,
+            aggregate_leaves: true,
+        });
+        assert!(dot.starts_with("digraph sitemap {\n"));
+        assert!(dot.contains(&format!("[label=\"2 × .png\", fillcolor=\"{}\"];", IMAGE_COLOR)));
+        assert!(dot.contains(&format!("[label=\"app.js\", fillcolor=\"{}\"];", CODE_COLOR)));
+        assert!(dot.contains(&format!("[label=\"docs\\n(+2 endpoints)\", fillcolor=\"{}\"];", FOLDER_COLOR)));
+        assert!(!dot.contains("intro"));
+        assert_eq!(dot.matches(" -> ").count(), 3);
+    }
+
+    #[test]
+    fn mermaid_lists_every_node_without_folding() {
+        let mermaid = render(GraphOptions {
+            kind: GraphKind::Mermaid,
+            color_by: ColorBy::FileType,
+            max_depth: 0,
+            aggregate_leaves: false,
+        });
+        assert!(mermaid.starts_with("graph LR\n"));
+        for name in ["a.png", "b.png", "app.js", "docs", "guide", "intro", "setup"] {
+            assert!(mermaid.contains(&format!("[\"{}\"]", name)), "{} missing", name);
+        }
+        assert_eq!(mermaid.matches(" --> ").count(), 7);
+        assert!(mermaid.contains(&format!("fill:{}", IMAGE_COLOR)));
+    }
+}
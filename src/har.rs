@@ -0,0 +1,193 @@
+use openssl::base64;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use url::Url;
+
+use crate::export::{self, ExportScope};
+use crate::file_saver::SaveProgress;
+use crate::network::{self, ClientCache, NetworkConfig, RateLimiter};
+use crate::response_store::{self, StoredResponse};
+use crate::snippets::RequestSpec;
+
+const HAR_VERSION: &str = "1.2";
+
+fn name_values(pairs: &[(String, String)]) -> Vec<Value> {
+    pairs
+        .iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect()
+}
+
+/// `YYYY-MM-DDTHH:MM:SS.mmmZ`, as HAR timestamps require.
+fn iso_timestamp(millis: u64) -> String {
+    format!(
+        "{}.{:03}Z",
+        response_store::format_timestamp(millis).replace(' ', "T"),
+        millis % 1000
+    )
+}
+
+fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn entry(response: &StoredResponse, body: &[u8]) -> Value {
+    let request = RequestSpec::from_stored(response);
+    let query: Vec<(String, String)> = Url::parse(&response.url)
+        .map(|url| url.query_pairs().into_owned().collect())
+        .unwrap_or_default();
+    let mut request_json = json!({
+        "method": request.method,
+        "url": response.url,
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": name_values(&request.headers),
+        "queryString": name_values(&query),
+        "headersSize": -1,
+        "bodySize": request.body.as_ref().map_or(0, String::len),
+    });
+    if let Some(text) = &request.body {
+        let mime_type = header_value(&request.headers, "content-type").unwrap_or("");
+        request_json["postData"] = json!({ "mimeType": mime_type, "text": text });
+    }
+
+    // Failed requests keep their error out of the content; binary bodies go
+    // in base64 so the file stays valid UTF-8 JSON.
+    let (body, error) = match response.status {
+        Some(_) => (body, None),
+        None => (&[][..], Some(String::from_utf8_lossy(body))),
+    };
+    let mut content = json!({ "size": body.len(), "mimeType": response.content_type });
+    match std::str::from_utf8(body) {
+        Ok(text) => content["text"] = json!(text),
+        Err(_) => {
+            content["text"] = json!(base64::encode_block(body));
+            content["encoding"] = json!("base64");
+        }
+    }
+    let status = response.status.unwrap_or(0);
+    let mut response_json = json!({
+        "status": status,
+        "statusText": reqwest::StatusCode::from_u16(status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or(""),
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": name_values(&response.headers),
+        "content": content,
+        "redirectURL": header_value(&response.headers, "location").unwrap_or(""),
+        "headersSize": -1,
+        "bodySize": body.len(),
+    });
+    if let Some(error) = error {
+        response_json["_error"] = json!(error);
+    }
+
+    json!({
+        "startedDateTime": iso_timestamp(response.timestamp),
+        "time": response.elapsed_ms,
+        "request": request_json,
+        "response": response_json,
+        "cache": {},
+        "timings": { "send": 0, "wait": response.elapsed_ms, "receive": 0 },
+    })
+}
+
+/// Writes the newest stored request and response of every endpoint in
+/// `scope` to a HAR file. Endpoints without a stored response are fetched
+/// first, at most `rate_limit` per second, when `fetch_missing` is set and
+/// left out otherwise. Setting `stop` ends the fetching; the file is still
+/// written with what was stored by then.
+pub fn spawn_har_thread(
+    db: Arc<sled::Db>,
+    scope: ExportScope,
+    config: NetworkConfig,
+    fetch_missing: bool,
+    rate_limit: u32,
+    stop: Arc<AtomicBool>,
+    path: PathBuf,
+) -> Receiver<SaveProgress> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let rows = export::collect(&db, &scope).rows;
+        if rows.is_empty() {
+            let _ = sender.send(SaveProgress::Errored("No endpoints match the export scope".to_string()));
+            return;
+        }
+
+        let mut clients = ClientCache::new(true);
+        let limiter = RateLimiter::new(rate_limit);
+        let mut entries = Vec::new();
+        let mut fetched = 0;
+        let mut skipped = 0;
+        for row in &rows {
+            let stored = response_store::endpoint_key(&row.url)
+                .and_then(|key| response_store::latest(&db, &key))
+                .map(|response| {
+                    let body = response_store::body(&db, &response.body_hash).unwrap_or_default();
+                    (response, body)
+                });
+            let (response, body) = match stored {
+                Some(stored) => stored,
+                None if fetch_missing && !stop.load(Ordering::SeqCst) => {
+                    limiter.wait();
+                    let (response, body) = match clients.client_for(&config, &row.url) {
+                        Ok((client, proxy)) => network::fetch_with(&config, client, proxy.as_ref(), &row.url),
+                        Err(_) => network::fetch(&config, &row.url),
+                    };
+                    fetched += 1;
+                    match response_store::record(&db, response.clone(), &body) {
+                        Ok(stored) => (stored, body),
+                        Err(_) => (response, body),
+                    }
+                }
+                None => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            entries.push(entry(&response, &body));
+        }
+
+        let har = json!({
+            "log": {
+                "version": HAR_VERSION,
+                "creator": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                "pages": [],
+                "entries": entries,
+            }
+        });
+        let result = File::create(&path).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            serde_json::to_writer_pretty(&mut out, &har)?;
+            out.flush()
+        });
+        let _ = sender.send(match result {
+            Ok(()) => {
+                let mut summary = format!("Exported {} requests as HAR to {}", entries.len(), path.display());
+                if fetched > 0 {
+                    summary.push_str(&format!(" ({} fetched)", fetched));
+                }
+                if skipped > 0 {
+                    summary.push_str(&format!(" ({} never requested, skipped)", skipped));
+                }
+                if fetch_missing && stop.load(Ordering::SeqCst) {
+                    summary.push_str(" (fetching stopped)");
+                }
+                SaveProgress::Finished(summary)
+            }
+            Err(e) => SaveProgress::Errored(format!("Failed to write {}: {}", path.display(), e)),
+        });
+    });
+    receiver
+}
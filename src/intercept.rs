@@ -0,0 +1,982 @@
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::ssl::{SslAcceptor, SslMethod};
+use openssl::x509::extension::{
+    AuthorityKeyIdentifier, BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName,
+    SubjectKeyIdentifier,
+};
+use openssl::x509::{X509NameBuilder, X509};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::file_processing::{self, Source};
+use crate::network::NetworkConfig;
+use crate::proxy::ProxyConfig;
+use crate::response_store::{self, StoredResponse};
+
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Headers that describe a single hop and must not be forwarded.
+const HOP_BY_HOP_HEADERS: [&str; 9] = [
+    "connection",
+    "proxy-connection",
+    "keep-alive",
+    "proxy-authorization",
+    "proxy-authenticate",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+pub enum InterceptEvent {
+    Listening(String),
+    /// An exchange already written to the project, with the number of
+    /// endpoints it added to the tree.
+    Captured(StoredResponse, usize),
+    Errored(String),
+    Stopped,
+}
+
+pub struct InterceptOptions {
+    pub bind_address: String,
+    pub intercept_tls: bool,
+    pub network: NetworkConfig,
+    pub db: Arc<sled::Db>,
+}
+
+/// Records captured exchanges into the project on the connection's own
+/// thread, then reports them.
+#[derive(Clone)]
+struct Recorder {
+    db: Arc<sled::Db>,
+    sender: Sender<InterceptEvent>,
+}
+
+impl Recorder {
+    fn capture(&self, response: StoredResponse, body: &[u8]) {
+        let mut new_endpoints = 0;
+        if response.status.is_some()
+            && let Ok(url) = url::Url::parse(&response.url)
+        {
+            match file_processing::record_urls(&self.db, &[url], Source::Intercept) {
+                Ok(added) => new_endpoints = added,
+                Err(e) => {
+                    let _ = self
+                        .sender
+                        .send(InterceptEvent::Errored(format!("Failed to record proxied URL: {}", e)));
+                }
+            }
+        }
+        let response = match response_store::record(&self.db, response.clone(), body) {
+            Ok(stored) => stored,
+            Err(e) => {
+                let _ = self
+                    .sender
+                    .send(InterceptEvent::Errored(format!("Failed to store response: {}", e)));
+                response
+            }
+        };
+        let _ = self.sender.send(InterceptEvent::Captured(response, new_endpoints));
+    }
+}
+
+/// Location of the interception CA certificate users import into their browser.
+pub fn ca_cert_path() -> PathBuf {
+    file_processing::data_path("maya_ca.pem")
+}
+
+fn ca_key_path() -> PathBuf {
+    file_processing::data_path("maya_ca.key")
+}
+
+/// Runs a local HTTP proxy that forwards traffic upstream through the shared
+/// network configuration, records every exchange into `options.db` and
+/// reports it. Setting `stop` shuts the listener down.
+pub fn spawn_intercept_thread(
+    options: InterceptOptions,
+    stop: Arc<AtomicBool>,
+) -> Receiver<InterceptEvent> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let authority = if options.intercept_tls {
+            match CertificateAuthority::load_or_create() {
+                Ok(ca) => Some(Arc::new(ca)),
+                Err(e) => {
+                    let _ = sender.send(InterceptEvent::Errored(format!(
+                        "Failed to prepare CA certificate: {}",
+                        e
+                    )));
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
+        let listener = match TcpListener::bind(&options.bind_address) {
+            Ok(listener) => listener,
+            Err(e) => {
+                let _ = sender.send(InterceptEvent::Errored(format!(
+                    "Failed to listen on {}: {}",
+                    options.bind_address, e
+                )));
+                return;
+            }
+        };
+        if let Err(e) = listener.set_nonblocking(true) {
+            let _ = sender.send(InterceptEvent::Errored(e.to_string()));
+            return;
+        }
+        let local_address = listener
+            .local_addr()
+            .map(|a| a.to_string())
+            .unwrap_or(options.bind_address.clone());
+        let _ = sender.send(InterceptEvent::Listening(local_address));
+
+        let network = Arc::new(options.network);
+        let recorder = Recorder {
+            db: options.db,
+            sender: sender.clone(),
+        };
+        while !stop.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let network = Arc::clone(&network);
+                    let authority = authority.clone();
+                    let recorder = recorder.clone();
+                    thread::spawn(move || {
+                        let _ = stream.set_nonblocking(false);
+                        if let Err(e) = handle_client(stream, &network, authority.as_deref(), &recorder)
+                            && e.kind() != io::ErrorKind::UnexpectedEof
+                        {
+                            let _ = recorder.sender.send(InterceptEvent::Errored(e.to_string()));
+                        }
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_POLL_INTERVAL);
+                }
+                Err(e) => {
+                    let _ = sender.send(InterceptEvent::Errored(e.to_string()));
+                    thread::sleep(ACCEPT_POLL_INTERVAL);
+                }
+            }
+        }
+        let _ = sender.send(InterceptEvent::Stopped);
+    });
+    receiver
+}
+
+struct ParsedRequest {
+    method: String,
+    target: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl ParsedRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn wants_close(&self) -> bool {
+        self.header("connection")
+            .or_else(|| self.header("proxy-connection"))
+            .is_some_and(|v| v.eq_ignore_ascii_case("close"))
+    }
+}
+
+/// A stream with a read-ahead buffer, so HTTP heads can be parsed without
+/// losing bytes that belong to the body or the next request.
+struct Connection<S> {
+    stream: S,
+    buffer: Vec<u8>,
+}
+
+impl<S: Read + Write> Connection<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream,
+            buffer: Vec::new(),
+        }
+    }
+
+    fn fill(&mut self) -> io::Result<usize> {
+        let mut chunk = [0u8; 8192];
+        let read = self.stream.read(&mut chunk)?;
+        self.buffer.extend_from_slice(&chunk[..read]);
+        Ok(read)
+    }
+
+    fn read_exact_buffered(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        while self.buffer.len() < len {
+            if self.fill()? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+            }
+        }
+        Ok(self.buffer.drain(..len).collect())
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        loop {
+            if let Some(pos) = self.buffer.windows(2).position(|w| w == b"\r\n") {
+                let line: Vec<u8> = self.buffer.drain(..pos + 2).collect();
+                return Ok(String::from_utf8_lossy(&line[..pos]).into_owned());
+            }
+            if self.buffer.len() > MAX_HEAD_SIZE || self.fill()? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+            }
+        }
+    }
+
+    /// Reads the next request, or `None` when the client closed the connection.
+    fn read_request(&mut self) -> io::Result<Option<ParsedRequest>> {
+        let head_end = loop {
+            if let Some(pos) = self.buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos;
+            }
+            if self.buffer.len() > MAX_HEAD_SIZE {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "request head too large"));
+            }
+            if self.fill()? == 0 {
+                return Ok(None);
+            }
+        };
+        let head: Vec<u8> = self.buffer.drain(..head_end + 4).collect();
+        let head = String::from_utf8_lossy(&head[..head_end]).into_owned();
+        let mut lines = head.split("\r\n");
+
+        let request_line = lines.next().unwrap_or("");
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed request line"));
+        };
+
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect();
+
+        let mut request = ParsedRequest {
+            method: method.to_string(),
+            target: target.to_string(),
+            headers,
+            body: Vec::new(),
+        };
+
+        if request
+            .header("transfer-encoding")
+            .is_some_and(|v| v.to_ascii_lowercase().contains("chunked"))
+        {
+            request.body = self.read_chunked_body()?;
+        } else if let Some(length) = request.header("content-length") {
+            let length = length
+                .parse::<usize>()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid content-length"))?;
+            request.body = self.read_exact_buffered(length)?;
+        }
+        Ok(Some(request))
+    }
+
+    fn read_chunked_body(&mut self) -> io::Result<Vec<u8>> {
+        let mut body = Vec::new();
+        loop {
+            let size_line = self.read_line()?;
+            let size_hex = size_line.split(';').next().unwrap_or("").trim();
+            let size = usize::from_str_radix(size_hex, 16)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid chunk size"))?;
+            if size == 0 {
+                // Skip trailers up to the terminating empty line.
+                while !self.read_line()?.is_empty() {}
+                return Ok(body);
+            }
+            body.extend(self.read_exact_buffered(size)?);
+            self.read_line()?;
+        }
+    }
+}
+
+fn handle_client(
+    stream: TcpStream,
+    network: &NetworkConfig,
+    authority: Option<&CertificateAuthority>,
+    recorder: &Recorder,
+) -> io::Result<()> {
+    let mut connection = Connection::new(stream);
+    let mut clients = HashMap::new();
+    loop {
+        let Some(request) = connection.read_request()? else {
+            return Ok(());
+        };
+
+        if request.method.eq_ignore_ascii_case("CONNECT") {
+            let target = request.target.clone();
+            return match authority {
+                Some(authority) => {
+                    connection
+                        .stream
+                        .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")?;
+                    let host = target.rsplit_once(':').map_or(target.as_str(), |(h, _)| h);
+                    let host = host.trim_start_matches('[').trim_end_matches(']');
+                    let acceptor = authority
+                        .acceptor_for(host)
+                        .map_err(|e| io::Error::other(e.to_string()))?;
+                    let tls_stream = acceptor
+                        .accept(connection.stream)
+                        .map_err(|e| io::Error::other(format!("TLS handshake with client failed: {}", e)))?;
+                    serve(Connection::new(tls_stream), Some(&target), network, recorder)
+                }
+                None => match connect_upstream(&target, network) {
+                    Ok(upstream) => {
+                        connection
+                            .stream
+                            .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")?;
+                        tunnel(connection, upstream)
+                    }
+                    Err(e) => write_error(&mut connection.stream, 502, "Bad Gateway", &e.to_string()),
+                },
+            };
+        }
+
+        let close = request.wants_close();
+        forward(&mut connection, request, None, network, &mut clients, recorder)?;
+        if close {
+            return Ok(());
+        }
+    }
+}
+
+/// Serves requests arriving on an intercepted TLS connection to `authority`.
+fn serve<S: Read + Write>(
+    mut connection: Connection<S>,
+    authority: Option<&str>,
+    network: &NetworkConfig,
+    recorder: &Recorder,
+) -> io::Result<()> {
+    let mut clients = HashMap::new();
+    while let Some(request) = connection.read_request()? {
+        let close = request.wants_close();
+        forward(&mut connection, request, authority, network, &mut clients, recorder)?;
+        if close {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Relays an opaque CONNECT tunnel when TLS interception is disabled.
+fn tunnel(connection: Connection<TcpStream>, mut upstream: TcpStream) -> io::Result<()> {
+    upstream.write_all(&connection.buffer)?;
+    let mut client = connection.stream;
+    let mut client_reader = client.try_clone()?;
+    let mut upstream_writer = upstream.try_clone()?;
+    let upload = thread::spawn(move || {
+        let _ = io::copy(&mut client_reader, &mut upstream_writer);
+        let _ = upstream_writer.shutdown(std::net::Shutdown::Write);
+    });
+    let _ = io::copy(&mut upstream, &mut client);
+    let _ = client.shutdown(std::net::Shutdown::Write);
+    let _ = upload.join();
+    Ok(())
+}
+
+/// Opens a raw connection to the CONNECT `target`, going through the
+/// upstream proxy when `route_through_proxy` is set.
+fn connect_upstream(target: &str, network: &NetworkConfig) -> io::Result<TcpStream> {
+    if !network.route_through_proxy {
+        return TcpStream::connect(target);
+    }
+    let proxy = network
+        .proxy_for(&format!("https://{}", target))
+        .map_err(io::Error::other)?;
+    let mut stream = TcpStream::connect((proxy.host.as_str(), proxy.port))?;
+    let timeout = Some(Duration::from_secs(network.connect_timeout_secs.max(1)));
+    stream.set_read_timeout(timeout)?;
+    match proxy.protocol.as_str() {
+        "http" => http_connect(&mut stream, &proxy, target)?,
+        "socks5" => socks5_connect(&mut stream, &proxy, target, false)?,
+        "socks5h" => socks5_connect(&mut stream, &proxy, target, true)?,
+        other => {
+            return Err(io::Error::other(format!(
+                "Cannot tunnel through a {} proxy; enable HTTPS interception instead",
+                other
+            )));
+        }
+    }
+    stream.set_read_timeout(None)?;
+    Ok(stream)
+}
+
+/// Asks an HTTP proxy to open a tunnel to `target`.
+fn http_connect(stream: &mut TcpStream, proxy: &ProxyConfig, target: &str) -> io::Result<()> {
+    let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", target, target);
+    if !proxy.username.is_empty() {
+        let credentials = format!("{}:{}", proxy.username, proxy.password);
+        request.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            openssl::base64::encode_block(credentials.as_bytes())
+        ));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes())?;
+
+    // Read byte by byte so nothing past the header block is consumed.
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Proxy closed the connection"));
+        }
+        head.push(byte[0]);
+        if head.len() > MAX_HEAD_SIZE {
+            return Err(io::Error::other("Proxy response headers too large"));
+        }
+    }
+    let head = String::from_utf8_lossy(&head);
+    let status_line = head.lines().next().unwrap_or_default();
+    match status_line.split_whitespace().nth(1) {
+        Some("200") => Ok(()),
+        _ => Err(io::Error::other(format!("Proxy refused CONNECT: {}", status_line))),
+    }
+}
+
+/// Runs the SOCKS5 handshake for `target`. With `remote_dns` the proxy
+/// resolves the host name, otherwise it is resolved locally first.
+fn socks5_connect(
+    stream: &mut TcpStream,
+    proxy: &ProxyConfig,
+    target: &str,
+    remote_dns: bool,
+) -> io::Result<()> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid CONNECT target: {}", target));
+    let (host, port) = target.rsplit_once(':').ok_or_else(invalid)?;
+    let port: u16 = port.parse().map_err(|_| invalid())?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    let with_auth = !proxy.username.is_empty();
+    if with_auth {
+        stream.write_all(&[5, 2, 0, 2])?;
+    } else {
+        stream.write_all(&[5, 1, 0])?;
+    }
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply)?;
+    match reply {
+        [5, 0] => {}
+        [5, 2] if with_auth => {
+            let mut auth = vec![1, proxy.username.len() as u8];
+            auth.extend_from_slice(proxy.username.as_bytes());
+            auth.push(proxy.password.len() as u8);
+            auth.extend_from_slice(proxy.password.as_bytes());
+            stream.write_all(&auth)?;
+            stream.read_exact(&mut reply)?;
+            if reply[1] != 0 {
+                return Err(io::Error::other("SOCKS5 proxy rejected the credentials"));
+            }
+        }
+        _ => return Err(io::Error::other("SOCKS5 proxy offered no usable authentication method")),
+    }
+
+    let mut request = vec![5, 1, 0];
+    let address = match host.parse::<IpAddr>() {
+        Ok(ip) => Some(ip),
+        Err(_) if remote_dns => None,
+        Err(_) => Some(
+            std::net::ToSocketAddrs::to_socket_addrs(&(host, port))?
+                .next()
+                .ok_or_else(|| io::Error::other(format!("Could not resolve {}", host)))?
+                .ip(),
+        ),
+    };
+    match address {
+        Some(IpAddr::V4(ip)) => {
+            request.push(1);
+            request.extend_from_slice(&ip.octets());
+        }
+        Some(IpAddr::V6(ip)) => {
+            request.push(4);
+            request.extend_from_slice(&ip.octets());
+        }
+        None => {
+            request.push(3);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request)?;
+
+    let mut head = [0u8; 4];
+    stream.read_exact(&mut head)?;
+    if head[1] != 0 {
+        return Err(io::Error::other(format!("SOCKS5 proxy refused the connection (code {})", head[1])));
+    }
+    let bound_len = match head[3] {
+        1 => 4,
+        4 => 16,
+        3 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len)?;
+            len[0] as usize
+        }
+        _ => return Err(io::Error::other("Malformed SOCKS5 reply")),
+    };
+    let mut bound = vec![0u8; bound_len + 2];
+    stream.read_exact(&mut bound)?;
+    Ok(())
+}
+
+fn request_url(request: &ParsedRequest, tls_authority: Option<&str>) -> Option<String> {
+    if request.target.starts_with("http://") || request.target.starts_with("https://") {
+        return Some(request.target.clone());
+    }
+    match tls_authority {
+        Some(authority) => {
+            let authority = authority.strip_suffix(":443").unwrap_or(authority);
+            Some(format!("https://{}{}", authority, request.target))
+        }
+        None => request
+            .header("host")
+            .map(|host| format!("http://{}{}", host, request.target)),
+    }
+}
+
+fn forward<S: Read + Write>(
+    connection: &mut Connection<S>,
+    request: ParsedRequest,
+    tls_authority: Option<&str>,
+    network: &NetworkConfig,
+    clients: &mut HashMap<Option<ProxyConfig>, reqwest::blocking::Client>,
+    recorder: &Recorder,
+) -> io::Result<()> {
+    let Some(url) = request_url(&request, tls_authority) else {
+        return write_error(&mut connection.stream, 400, "Bad Request", "Missing Host header");
+    };
+
+    let mut raw_request = format!("{} {} HTTP/1.1\n", request.method, request.target);
+    for (name, value) in &request.headers {
+        raw_request.push_str(&format!("{}: {}\n", name, value));
+    }
+    if !request.body.is_empty() {
+        raw_request.push('\n');
+        raw_request.push_str(&String::from_utf8_lossy(&request.body));
+    }
+
+    let start_time = Instant::now();
+    let result = send_upstream(&url, &request, network, clients);
+    let elapsed_ms = start_time.elapsed().as_millis();
+
+    let (status, reason, headers, body) = match result {
+        Ok(response) => response,
+        Err(e) => {
+            let response = StoredResponse {
+                method: request.method.clone(),
+                url: url.clone(),
+                request: raw_request,
+                elapsed_ms,
+                ..Default::default()
+            };
+            recorder.capture(response, format!("Error: {}", e).as_bytes());
+            return write_error(&mut connection.stream, 502, "Bad Gateway", &e);
+        }
+    };
+
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, reason);
+    let mut content_type = String::new();
+    for (name, value) in &headers {
+        let lower = name.to_ascii_lowercase();
+        if lower == "content-type" {
+            content_type = value.to_lowercase();
+        }
+        if HOP_BY_HOP_HEADERS.contains(&lower.as_str()) || lower == "content-length" {
+            continue;
+        }
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    if request.wants_close() {
+        head.push_str("Connection: close\r\n");
+    }
+    head.push_str("\r\n");
+    connection.stream.write_all(head.as_bytes())?;
+    connection.stream.write_all(&body)?;
+    connection.stream.flush()?;
+
+    let response = StoredResponse {
+        method: request.method,
+        url,
+        request: raw_request,
+        status: Some(status),
+        headers,
+        content_type,
+        elapsed_ms,
+        ..Default::default()
+    };
+    recorder.capture(response, &body);
+    Ok(())
+}
+
+type UpstreamResponse = (u16, String, Vec<(String, String)>, Vec<u8>);
+
+fn send_upstream(
+    url: &str,
+    request: &ParsedRequest,
+    network: &NetworkConfig,
+    clients: &mut HashMap<Option<ProxyConfig>, reqwest::blocking::Client>,
+) -> Result<UpstreamResponse, String> {
+    let proxy = if network.route_through_proxy {
+        Some(network.proxy_for(url)?)
+    } else {
+        None
+    };
+    if !clients.contains_key(&proxy) {
+        let client = network
+            .client_builder(proxy.as_ref())?
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| e.to_string())?;
+        clients.insert(proxy.clone(), client);
+    }
+    let client = &clients[&proxy];
+
+    let method = reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|e| e.to_string())?;
+    let mut builder = client.request(method, url);
+    for (name, value) in &request.headers {
+        let lower = name.to_ascii_lowercase();
+        // Asking for an identity encoding keeps recorded bodies readable.
+        if HOP_BY_HOP_HEADERS.contains(&lower.as_str())
+            || matches!(lower.as_str(), "host" | "content-length" | "accept-encoding")
+        {
+            continue;
+        }
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    if !request.body.is_empty() {
+        builder = builder.body(request.body.clone());
+    }
+
+    let response = builder.send();
+    if let Some(proxy) = &proxy {
+        let ok = match &response {
+            Ok(_) => true,
+            Err(e) => !(e.is_connect() || e.is_timeout()),
+        };
+        network.proxy_pool.report(proxy, ok);
+    }
+    let response = response.map_err(|e| e.to_string())?;
+
+    let status = response.status();
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect();
+    let body = response.bytes().map_err(|e| e.to_string())?.to_vec();
+    Ok((
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("").to_string(),
+        headers,
+        body,
+    ))
+}
+
+fn write_error<W: Write>(stream: &mut W, status: u16, reason: &str, message: &str) -> io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        message.len(),
+        message
+    );
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
+
+/// Writes the CA key readable by the owner only: anyone who can read it can
+/// mint certificates that browsers trusting the CA will accept.
+fn write_private_key(path: &Path, pem: &[u8]) -> io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // `mode` only applies to new files; tighten a leftover one too.
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)?.write_all(pem)
+}
+
+/// Refuses a CA key that other users on the machine can read.
+fn check_key_permissions(path: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(path).map_err(|e| e.to_string())?.permissions().mode();
+        if mode & 0o077 != 0 {
+            return Err(format!(
+                "The CA key {} is readable by other users (mode {:o}); run `chmod 600` on it or delete it to generate a new CA",
+                path.display(),
+                mode & 0o777
+            ));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// The locally generated CA used to mint per-host certificates for
+/// intercepted TLS connections.
+struct CertificateAuthority {
+    cert: X509,
+    key: PKey<Private>,
+    leaf_key: PKey<Private>,
+    acceptors: Mutex<HashMap<String, Arc<SslAcceptor>>>,
+}
+
+impl CertificateAuthority {
+    fn load_or_create() -> Result<Self, String> {
+        let (cert, key) = match (std::fs::read(ca_cert_path()), std::fs::read(ca_key_path())) {
+            (Ok(cert_pem), Ok(key_pem)) => {
+                check_key_permissions(&ca_key_path())?;
+                (
+                    X509::from_pem(&cert_pem).map_err(|e| e.to_string())?,
+                    PKey::private_key_from_pem(&key_pem).map_err(|e| e.to_string())?,
+                )
+            }
+            _ => {
+                let (cert, key) = generate_ca().map_err(|e| e.to_string())?;
+                std::fs::write(ca_cert_path(), cert.to_pem().map_err(|e| e.to_string())?)
+                    .map_err(|e| e.to_string())?;
+                write_private_key(
+                    &ca_key_path(),
+                    &key.private_key_to_pem_pkcs8().map_err(|e| e.to_string())?,
+                )
+                .map_err(|e| e.to_string())?;
+                (cert, key)
+            }
+        };
+        let leaf_key = PKey::from_rsa(Rsa::generate(2048).map_err(|e| e.to_string())?)
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            cert,
+            key,
+            leaf_key,
+            acceptors: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn acceptor_for(&self, host: &str) -> Result<Arc<SslAcceptor>, ErrorStack> {
+        if let Some(acceptor) = self.acceptors.lock().unwrap().get(host) {
+            return Ok(Arc::clone(acceptor));
+        }
+        let leaf = self.sign_leaf(host)?;
+        let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())?;
+        builder.set_private_key(&self.leaf_key)?;
+        builder.set_certificate(&leaf)?;
+        builder.add_extra_chain_cert(self.cert.clone())?;
+        builder.check_private_key()?;
+        let acceptor = Arc::new(builder.build());
+        self.acceptors
+            .lock()
+            .unwrap()
+            .insert(host.to_string(), Arc::clone(&acceptor));
+        Ok(acceptor)
+    }
+
+    fn sign_leaf(&self, host: &str) -> Result<X509, ErrorStack> {
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_text("CN", host)?;
+        let name = name.build();
+
+        let mut builder = X509::builder()?;
+        builder.set_version(2)?;
+        let serial = random_serial()?;
+        builder.set_serial_number(&serial)?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(self.cert.subject_name())?;
+        builder.set_pubkey(&self.leaf_key)?;
+        // Backdate a day to tolerate clock skew between machines.
+        let not_before = Asn1Time::from_unix(unix_now() - 86_400)?;
+        let not_after = Asn1Time::days_from_now(365)?;
+        builder.set_not_before(&not_before)?;
+        builder.set_not_after(&not_after)?;
+
+        let mut san = SubjectAlternativeName::new();
+        if host.parse::<IpAddr>().is_ok() {
+            san.ip(host);
+        } else {
+            san.dns(host);
+        }
+        let context = builder.x509v3_context(Some(&self.cert), None);
+        let san = san.build(&context)?;
+        let authority_key = AuthorityKeyIdentifier::new().keyid(false).build(&context)?;
+        builder.append_extension(san)?;
+        builder.append_extension(authority_key)?;
+        builder.append_extension(BasicConstraints::new().build()?)?;
+        builder.append_extension(
+            KeyUsage::new()
+                .critical()
+                .digital_signature()
+                .key_encipherment()
+                .build()?,
+        )?;
+        builder.append_extension(ExtendedKeyUsage::new().server_auth().build()?)?;
+        builder.sign(&self.key, MessageDigest::sha256())?;
+        Ok(builder.build())
+    }
+}
+
+fn generate_ca() -> Result<(X509, PKey<Private>), ErrorStack> {
+    let key = PKey::from_rsa(Rsa::generate(2048)?)?;
+
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_text("CN", "maya_vi Intercept CA")?;
+    name.append_entry_by_text("O", "maya_vi")?;
+    let name = name.build();
+
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    let serial = random_serial()?;
+    builder.set_serial_number(&serial)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(&key)?;
+    let not_before = Asn1Time::from_unix(unix_now() - 86_400)?;
+    let not_after = Asn1Time::days_from_now(3650)?;
+    builder.set_not_before(&not_before)?;
+    builder.set_not_after(&not_after)?;
+    builder.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+    builder.append_extension(
+        KeyUsage::new()
+            .critical()
+            .key_cert_sign()
+            .crl_sign()
+            .build()?,
+    )?;
+    let key_identifier = SubjectKeyIdentifier::new().build(&builder.x509v3_context(None, None))?;
+    builder.append_extension(key_identifier)?;
+    builder.sign(&key, MessageDigest::sha256())?;
+    Ok((builder.build(), key))
+}
+
+fn random_serial() -> Result<openssl::asn1::Asn1Integer, ErrorStack> {
+    let mut serial = BigNum::new()?;
+    serial.rand(127, MsbOption::MAYBE_ZERO, false)?;
+    serial.to_asn1_integer()
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    #[cfg(unix)]
+    #[test]
+    fn ca_key_is_private_and_loose_keys_are_refused() {
+        let path = std::env::temp_dir().join(format!("maya_ca_test_{}.key", std::process::id()));
+        std::fs::write(&path, b"old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(check_key_permissions(&path).is_err());
+
+        write_private_key(&path, b"key").unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(check_key_permissions(&path).is_ok());
+        assert_eq!(std::fs::read(&path).unwrap(), b"key");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn tunnels_through_http_proxy_with_credentials() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let fake_proxy = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut head = Vec::new();
+            let mut byte = [0u8; 1];
+            while !head.ends_with(b"\r\n\r\n") {
+                stream.read_exact(&mut byte).unwrap();
+                head.push(byte[0]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\ntunnel-data")
+                .unwrap();
+            String::from_utf8(head).unwrap()
+        });
+
+        let network = NetworkConfig {
+            proxy: ProxyConfig {
+                protocol: "http".to_string(),
+                host: "127.0.0.1".to_string(),
+                port,
+                username: "user".to_string(),
+                password: "pass".to_string(),
+            },
+            route_through_proxy: true,
+            ..NetworkConfig::default()
+        };
+        let mut upstream = connect_upstream("example.com:443", &network).unwrap();
+        let mut data = String::new();
+        upstream.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "tunnel-data");
+
+        let head = fake_proxy.join().unwrap();
+        assert!(head.starts_with("CONNECT example.com:443 HTTP/1.1\r\n"));
+        assert!(head.contains("Proxy-Authorization: Basic dXNlcjpwYXNz\r\n"));
+    }
+
+    #[test]
+    fn reports_refused_connect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0u8; 1024]);
+            let _ = stream.write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n");
+        });
+
+        let network = NetworkConfig {
+            proxy: ProxyConfig {
+                port,
+                ..ProxyConfig::default()
+            },
+            route_through_proxy: true,
+            ..NetworkConfig::default()
+        };
+        let err = connect_upstream("example.com:443", &network).unwrap_err();
+        assert!(err.to_string().contains("407"));
+    }
+}
@@ -0,0 +1,292 @@
+use openssl::base64;
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use url::Url;
+
+use crate::file_processing::{self, Source};
+use crate::network::{self, NetworkConfig};
+use crate::response_store;
+
+lazy_static::lazy_static! {
+    /// `fetch(...)`, `axios(...)`, `axios.get(...)`, `$.get(...)` and
+    /// `xhr.open("GET", ...)` call sites.
+    static ref CALL_SITE: Regex = Regex::new(
+        r#"(?:\bfetch|\baxios(?:\.(?:get|post|put|patch|delete|head|options|request))?|\$\.(?:get|post|getJSON|ajax)|\.open\(\s*["'][A-Za-z]+["']\s*,)\s*\(?\s*["'`]([^"'`\s]+)["'`]"#
+    )
+    .unwrap();
+    /// `url: "..."` options and router `path: "..."` or `path="..."`
+    /// definitions.
+    static ref KEYED_PATH: Regex = Regex::new(
+        r#"\b(?:url|path|endpoint|baseURL|baseUrl|href|to)\s*[:=]\s*\{?\s*["'`]([^"'`\s]+)["'`]"#
+    )
+    .unwrap();
+    /// Any literal that looks like a URL or an absolute or dotted path.
+    static ref PATH_LITERAL: Regex = Regex::new(
+        r#"["'`]((?:https?:)?//[^"'`\s<>]+|/[A-Za-z0-9_\-.~/?=&%+:@!$,;{}]+|\.{1,2}/[^"'`\s<>]+)["'`]"#
+    )
+    .unwrap();
+    static ref SOURCE_MAP_COMMENT: Regex =
+        Regex::new(r"(?m)^\s*//[#@]\s*sourceMappingURL\s*=\s*(\S+)\s*$").unwrap();
+    static ref MIME_TYPE: Regex =
+        Regex::new(r"^(?:application|text|image|audio|video|font|multipart|model)/[A-Za-z0-9.+\-*]+$").unwrap();
+}
+
+pub enum JsProgress {
+    Extracted {
+        url: String,
+        found: Vec<String>,
+        new_endpoints: usize,
+        source_map: Option<String>,
+    },
+    Finished(usize),
+    Errored(String),
+}
+
+/// Raw paths and URLs referenced by a script, in order of first appearance.
+/// Template expressions are cut off, keeping the static prefix.
+pub fn extract_paths(source: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut paths = Vec::new();
+    let captures = CALL_SITE
+        .captures_iter(source)
+        .chain(KEYED_PATH.captures_iter(source))
+        .chain(PATH_LITERAL.captures_iter(source));
+    for capture in captures {
+        let raw = capture[1].split("${").next().unwrap_or("");
+        if is_plausible_path(raw) && seen.insert(raw.to_string()) {
+            paths.push(raw.to_string());
+        }
+    }
+    paths
+}
+
+fn is_plausible_path(raw: &str) -> bool {
+    raw.len() > 1
+        && raw != "//"
+        && !raw.starts_with("//*")
+        && !raw.starts_with('#')
+        && !raw.contains(['<', '>', '(', ')', '\\', '*'])
+        && !MIME_TYPE.is_match(raw)
+        && raw.chars().any(|c| c.is_ascii_alphabetic())
+        && !["javascript:", "mailto:", "tel:", "data:"]
+            .iter()
+            .any(|scheme| raw.to_lowercase().starts_with(scheme))
+}
+
+/// Resolves `raw` against `script`. `./` and `../` references are relative
+/// to the script itself, while bare paths such as `api/users` are taken from
+/// the site root, since bundles call the API relative to the page rather
+/// than to the script. Full URLs are kept only on one of `start_hosts` or a
+/// subdomain of one.
+pub fn resolve(script: &Url, raw: &str, start_hosts: &HashSet<String>) -> Option<Url> {
+    let mut url = if raw.starts_with("//") || raw.starts_with("http://") || raw.starts_with("https://") {
+        let url = script.join(raw).ok()?;
+        if !in_scope(&url, start_hosts) {
+            return None;
+        }
+        url
+    } else if raw.starts_with('/') || raw.starts_with("./") || raw.starts_with("../") {
+        script.join(raw).ok()?
+    } else {
+        script.join("/").ok()?.join(raw).ok()?
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    url.set_fragment(None);
+    Some(url)
+}
+
+/// Whether `url` is on one of `start_hosts` or a subdomain of one. Sibling
+/// hosts are left out, since without the public suffix list `a.co.uk` and
+/// `b.co.uk` cannot be told apart from two hosts of one site.
+fn in_scope(url: &Url, start_hosts: &HashSet<String>) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    start_hosts.iter().any(|start| {
+        host == start
+            || host
+                .strip_suffix(start.as_str())
+                .is_some_and(|prefix| prefix.ends_with('.'))
+    })
+}
+
+/// Where the script's source map lives: the `SourceMap` header, then the
+/// trailing `sourceMappingURL` comment.
+fn source_map_reference(headers: &[(String, String)], source: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("sourcemap") || name.eq_ignore_ascii_case("x-sourcemap"))
+        .map(|(_, value)| value.trim().to_string())
+        .or_else(|| {
+            SOURCE_MAP_COMMENT
+                .captures_iter(source)
+                .last()
+                .map(|capture| capture[1].to_string())
+        })
+}
+
+/// The original sources embedded in a source map, or why there are none.
+fn source_map_contents(map: &[u8]) -> Result<Vec<String>, String> {
+    let map: serde_json::Value =
+        serde_json::from_slice(map).map_err(|e| format!("Invalid source map: {}", e))?;
+    let contents = map
+        .get("sourcesContent")
+        .and_then(|contents| contents.as_array())
+        .ok_or("Source map has no sourcesContent")?;
+    Ok(contents
+        .iter()
+        .filter_map(|content| content.as_str().map(String::from))
+        .collect())
+}
+
+/// Follows a source map reference, returning the map's URL for the tree
+/// (when it is not inline) and the sources it embeds. Maps outside
+/// `start_hosts` are neither fetched nor recorded.
+fn fetch_source_map(
+    config: &NetworkConfig,
+    db: &sled::Db,
+    script: &Url,
+    reference: &str,
+    start_hosts: &HashSet<String>,
+) -> Result<(Option<Url>, Vec<String>), String> {
+    if let Some(data) = reference.strip_prefix("data:") {
+        let (_, encoded) = data
+            .split_once(";base64,")
+            .ok_or("Unsupported inline source map encoding")?;
+        let map = base64::decode_block(encoded).map_err(|e| format!("Invalid inline source map: {}", e))?;
+        return Ok((None, source_map_contents(&map)?));
+    }
+
+    let map_url = script.join(reference).map_err(|e| e.to_string())?;
+    if !in_scope(&map_url, start_hosts) {
+        return Err(format!("Source map out of scope: {}", map_url));
+    }
+    let (response, body) = network::fetch(config, map_url.as_str());
+    match response.status {
+        Some(200) => {
+            let _ = response_store::record(db, response, &body);
+            Ok((Some(map_url), source_map_contents(&body)?))
+        }
+        Some(status) => Err(format!("Source map returned {}", status)),
+        None => Err("Source map could not be fetched".to_string()),
+    }
+}
+
+/// Fetches each script, extracts the endpoints it and its source map
+/// reference, and adds the ones on the scripts' hosts and their subdomains
+/// to the tree as JS-derived.
+pub fn spawn_js_extract_thread(
+    db: Arc<sled::Db>,
+    config: NetworkConfig,
+    scripts: Vec<String>,
+) -> Receiver<JsProgress> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let start_hosts: HashSet<String> = scripts
+            .iter()
+            .filter_map(|script| Url::parse(script).ok())
+            .filter_map(|url| url.host_str().map(String::from))
+            .collect();
+        let mut total_new = 0;
+        for script in scripts {
+            let Ok(script_url) = Url::parse(&script) else {
+                let _ = sender.send(JsProgress::Errored(format!("Invalid URL: {}", script)));
+                continue;
+            };
+            let (response, body) = network::fetch(&config, script_url.as_str());
+            if response.status.is_none() {
+                let _ = sender.send(JsProgress::Errored(format!("Failed to fetch {}", script)));
+                continue;
+            }
+            let headers = response.headers.clone();
+            let _ = response_store::record(&db, response, &body);
+
+            let source = String::from_utf8_lossy(&body);
+            let mut raw_paths = extract_paths(&source);
+            let mut urls = Vec::new();
+            let mut source_map = None;
+            if let Some(reference) = source_map_reference(&headers, &source) {
+                match fetch_source_map(&config, &db, &script_url, &reference, &start_hosts) {
+                    Ok((map_url, contents)) => {
+                        source_map = Some(map_url.as_ref().map_or("inline".to_string(), Url::to_string));
+                        urls.extend(map_url);
+                        for content in contents {
+                            raw_paths.extend(extract_paths(&content));
+                        }
+                    }
+                    Err(e) => source_map = Some(e),
+                }
+            }
+
+            let mut seen = HashSet::new();
+            urls.extend(
+                raw_paths
+                    .iter()
+                    .filter_map(|raw| resolve(&script_url, raw, &start_hosts))
+                    .filter(|url| seen.insert(url.to_string())),
+            );
+            let new_endpoints = file_processing::record_urls(&db, &urls, Source::Js).unwrap_or(0);
+            total_new += new_endpoints;
+            let _ = sender.send(JsProgress::Extracted {
+                url: script,
+                found: urls.iter().map(Url::to_string).collect(),
+                new_endpoints,
+                source_map,
+            });
+        }
+        let _ = db.flush();
+        let _ = sender.send(JsProgress::Finished(total_new));
+    });
+
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolved(raw: &str) -> Option<String> {
+        let script = Url::parse("https://www.example.com/static/js/app.js").unwrap();
+        let start_hosts = HashSet::from(["www.example.com".to_string()]);
+        resolve(&script, raw, &start_hosts).map(|url| url.to_string())
+    }
+
+    #[test]
+    fn relative_references_follow_the_script() {
+        assert_eq!(resolved("./chunk.js").as_deref(), Some("https://www.example.com/static/js/chunk.js"));
+        assert_eq!(resolved("../css/site.css").as_deref(), Some("https://www.example.com/static/css/site.css"));
+        assert_eq!(resolved("/api/users#top").as_deref(), Some("https://www.example.com/api/users"));
+        assert_eq!(resolved("api/users").as_deref(), Some("https://www.example.com/api/users"));
+    }
+
+    #[test]
+    fn full_urls_stay_on_start_hosts_and_their_subdomains() {
+        assert!(resolved("https://www.example.com/a").is_some());
+        assert!(resolved("//cdn.www.example.com/a").is_some());
+        assert!(resolved("https://api.example.com/a").is_none());
+        assert!(resolved("https://evilwww.example.com/a").is_none());
+        assert!(resolved("https://other.co.uk/a").is_none());
+    }
+
+    #[test]
+    fn source_maps_off_the_start_hosts_are_not_fetched() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let script = Url::parse("https://www.example.com/static/js/app.js").unwrap();
+        let start_hosts = HashSet::from(["www.example.com".to_string()]);
+        let result = fetch_source_map(
+            &NetworkConfig::default(),
+            &db,
+            &script,
+            "https://tracker.invalid/app.js.map",
+            &start_hosts,
+        );
+        assert_eq!(result.unwrap_err(), "Source map out of scope: https://tracker.invalid/app.js.map");
+        assert!(response_store::latest_all(&db).is_empty());
+    }
+}
@@ -0,0 +1,328 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use url::Url;
+
+use crate::export::csv_field;
+use crate::links::{self, ContentKind};
+use crate::network::{self, ClientCache, NetworkConfig, RateLimiter};
+use crate::response_store;
+
+/// `target \n source` keys, so every referrer of a target is one prefix
+/// scan away.
+const EDGES_TREE: &str = "link_edges";
+const STATUS_TREE: &str = "link_status";
+
+const MAX_ERROR_CHARS: usize = 120;
+
+/// Endpoints with these extensions are not fetched as pages; they are still
+/// checked when a page links to them.
+const NON_PAGE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "ico", "bmp", "svg", "avif", "css", "js", "mjs", "map", "json",
+    "xml", "txt", "pdf", "woff", "woff2", "ttf", "eot", "otf", "mp3", "mp4", "webm", "avi", "mov", "zip",
+    "gz", "tar", "rar", "7z", "exe", "dmg", "iso",
+];
+
+#[derive(Clone)]
+pub struct LinkCheckOptions {
+    pub start_urls: Vec<String>,
+    /// Also check links that leave the hosts of the start URLs.
+    pub external: bool,
+    pub threads: u32,
+    pub rate_limit: u32,
+    pub network: NetworkConfig,
+}
+
+pub enum LinkCheckProgress {
+    Advanced { completed: usize, total: usize },
+    Finished { pages: usize, links: usize, broken: usize },
+    Errored(String),
+}
+
+/// The outcome of the last check of one URL.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LinkStatus {
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub timestamp: u64,
+}
+
+impl LinkStatus {
+    /// Error statuses and requests that got no response at all.
+    pub fn is_broken(&self) -> bool {
+        self.status.is_none_or(|status| status >= 400)
+    }
+
+    pub fn label(&self) -> String {
+        match (self.status, &self.error) {
+            (Some(status), _) => status.to_string(),
+            (None, Some(error)) => error.clone(),
+            (None, None) => "no response".to_string(),
+        }
+    }
+}
+
+/// A target that failed, with every page that links to it.
+pub struct BrokenLink {
+    pub target: String,
+    pub status: LinkStatus,
+    pub referrers: Vec<String>,
+}
+
+fn is_page(url: &Url) -> bool {
+    let extension = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_lowercase());
+    extension.is_none_or(|ext| !NON_PAGE_EXTENSIONS.contains(&ext.as_str()))
+}
+
+fn edge_key(target: &str, source: &str) -> String {
+    format!("{}\n{}", target, source)
+}
+
+/// A short reason for a request that got no response, taken from the
+/// innermost message of the error `network::fetch` put in the body.
+fn failure_reason(body: &[u8]) -> String {
+    let text = String::from_utf8_lossy(body);
+    if text.contains("TimedOut") || text.contains("timed out") {
+        return "timed out".to_string();
+    }
+    let message = text
+        .lines()
+        .map(str::trim)
+        .filter_map(|line| line.strip_prefix("message: ").or_else(|| line.strip_prefix("error: ")))
+        .next_back()
+        .map(|message| message.trim_end_matches(',').trim_matches('"'));
+    let reason = message.unwrap_or_else(|| text.lines().next().unwrap_or("request failed"));
+    reason.chars().take(MAX_ERROR_CHARS).collect()
+}
+
+/// State shared by the workers of one link check.
+struct CheckRun<'a> {
+    db: &'a sled::Db,
+    options: &'a LinkCheckOptions,
+    /// Hosts of the start pages; only their responses go into the project.
+    hosts: HashSet<String>,
+    limiter: RateLimiter,
+    stop: &'a AtomicBool,
+    completed: AtomicUsize,
+    sender: &'a Sender<LinkCheckProgress>,
+}
+
+impl CheckRun<'_> {
+    fn is_on_site(&self, url: &Url) -> bool {
+        url.host_str().is_some_and(|host| self.hosts.contains(host))
+    }
+
+    /// Fetches `url`, following redirects, and stores its status. The
+    /// response itself is only stored for on-site URLs, so external targets
+    /// do not leak into the project's response history.
+    fn check(&self, clients: &mut ClientCache, url: &Url) -> (LinkStatus, response_store::StoredResponse, Vec<u8>) {
+        let config = &self.options.network;
+        let (response, body) = match clients.client_for(config, url.as_str()) {
+            Ok((client, proxy)) => network::fetch_with(config, client, proxy.as_ref(), url.as_str()),
+            Err(_) => network::fetch(config, url.as_str()),
+        };
+        let status = LinkStatus {
+            status: response.status,
+            error: response.status.is_none().then(|| failure_reason(&body)),
+            timestamp: response_store::now_millis(),
+        };
+        if let Ok(tree) = self.db.open_tree(STATUS_TREE)
+            && let Ok(encoded) = serde_json::to_vec(&status)
+        {
+            let _ = tree.insert(url.as_str().as_bytes(), encoded);
+        }
+        if self.is_on_site(url) {
+            let _ = response_store::record(self.db, response.clone(), &body);
+        }
+        (status, response, body)
+    }
+
+    /// Runs `work` over `urls` on up to `threads` workers sharing one rate
+    /// limit, reporting progress after each URL.
+    fn run_parallel(&self, urls: &[Url], total: usize, work: impl Fn(&mut ClientCache, &Url) + Sync) {
+        let next_index = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..self.options.threads.max(1).min(urls.len().max(1) as u32) {
+                scope.spawn(|| {
+                    let mut clients = ClientCache::new(true);
+                    while !self.stop.load(Ordering::SeqCst) {
+                        let Some(url) = urls.get(next_index.fetch_add(1, Ordering::SeqCst)) else {
+                            break;
+                        };
+                        self.limiter.wait();
+                        work(&mut clients, url);
+                        let done = self.completed.fetch_add(1, Ordering::SeqCst) + 1;
+                        let _ = self.sender.send(LinkCheckProgress::Advanced { completed: done, total });
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// Removes the stored edges whose source is one of `sources`, so a page's
+/// links are replaced rather than accumulated.
+fn clear_edges(tree: &sled::Tree, sources: &HashSet<String>) -> Result<(), sled::Error> {
+    for key in tree.iter().keys() {
+        let key = key?;
+        let is_stale = std::str::from_utf8(&key)
+            .ok()
+            .and_then(|key| key.split_once('\n'))
+            .is_some_and(|(_, source)| sources.contains(source));
+        if is_stale {
+            tree.remove(key)?;
+        }
+    }
+    Ok(())
+}
+
+/// Fetches every page in `options.start_urls`, records the links of the
+/// HTML ones as source→target edges and checks each target once.
+pub fn spawn_link_check_thread(
+    db: Arc<sled::Db>,
+    options: LinkCheckOptions,
+    stop: Arc<AtomicBool>,
+) -> Receiver<LinkCheckProgress> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let pages: Vec<Url> = options
+            .start_urls
+            .iter()
+            .filter_map(|url| Url::parse(url).ok())
+            .filter(is_page)
+            .collect();
+        if pages.is_empty() {
+            let _ = sender.send(LinkCheckProgress::Errored("No pages to check".to_string()));
+            return;
+        }
+        let edges = match db.open_tree(EDGES_TREE) {
+            Ok(tree) => tree,
+            Err(e) => {
+                let _ = sender.send(LinkCheckProgress::Errored(e.to_string()));
+                return;
+            }
+        };
+        let sources: HashSet<String> = pages.iter().map(Url::to_string).collect();
+        if let Err(e) = clear_edges(&edges, &sources) {
+            let _ = sender.send(LinkCheckProgress::Errored(format!("Failed to clear old links: {}", e)));
+            return;
+        }
+
+        let run = CheckRun {
+            db: &db,
+            options: &options,
+            hosts: pages.iter().filter_map(|url| url.host_str().map(String::from)).collect(),
+            limiter: RateLimiter::new(options.rate_limit),
+            stop: &stop,
+            completed: AtomicUsize::new(0),
+            sender: &sender,
+        };
+        let targets = Mutex::new(BTreeMap::<String, Url>::new());
+        let link_count = AtomicUsize::new(0);
+
+        run.run_parallel(&pages, pages.len(), |clients, page| {
+            let (status, response, body) = run.check(clients, page);
+            if status.status != Some(200) || links::content_kind(&response.content_type, page) != ContentKind::Html {
+                return;
+            }
+            let found = links::extract_links(page, &response.content_type, &String::from_utf8_lossy(&body));
+            let mut targets = targets.lock().unwrap();
+            for target in found {
+                if !options.external && !run.is_on_site(&target) {
+                    continue;
+                }
+                let _ = edges.insert(edge_key(target.as_str(), page.as_str()).as_bytes(), &[]);
+                link_count.fetch_add(1, Ordering::SeqCst);
+                targets.entry(target.to_string()).or_insert(target);
+            }
+        });
+
+        // Pages fetched above already have a fresh status.
+        let targets: Vec<Url> = targets
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .filter(|(target, _)| !sources.contains(target))
+            .map(|(_, url)| url)
+            .collect();
+        let total = pages.len() + targets.len();
+        run.run_parallel(&targets, total, |clients, target| {
+            run.check(clients, target);
+        });
+
+        let _ = db.flush();
+        if stop.load(Ordering::SeqCst) {
+            let _ = sender.send(LinkCheckProgress::Errored("Link check stopped".to_string()));
+            return;
+        }
+        let _ = sender.send(LinkCheckProgress::Finished {
+            pages: pages.len(),
+            links: link_count.load(Ordering::SeqCst),
+            broken: broken(&db).len(),
+        });
+    });
+
+    receiver
+}
+
+/// Every linked target whose last check failed, with its referrers, sorted
+/// by target.
+pub fn broken(db: &sled::Db) -> Vec<BrokenLink> {
+    let (Ok(edges), Ok(statuses)) = (db.open_tree(EDGES_TREE), db.open_tree(STATUS_TREE)) else {
+        return Vec::new();
+    };
+    let mut referrers: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for key in edges.iter().keys().flatten() {
+        if let Some((target, source)) = std::str::from_utf8(&key).ok().and_then(|key| key.split_once('\n')) {
+            referrers.entry(target.to_string()).or_default().push(source.to_string());
+        }
+    }
+    referrers
+        .into_iter()
+        .filter_map(|(target, referrers)| {
+            let status: LinkStatus = statuses
+                .get(target.as_bytes())
+                .ok()
+                .flatten()
+                .and_then(|value| serde_json::from_slice(&value).ok())?;
+            status.is_broken().then_some(BrokenLink {
+                target,
+                status,
+                referrers,
+            })
+        })
+        .collect()
+}
+
+/// Writes one `target,status,error,referrer` row per broken link and
+/// referring page, returning the number of rows.
+pub fn write_csv(path: &Path, links: &[BrokenLink]) -> Result<usize, String> {
+    let mut csv = String::from("target,status,error,referrer\n");
+    let mut rows = 0;
+    for link in links {
+        let status = link.status.status.map_or(String::new(), |status| status.to_string());
+        let error = link.status.error.as_deref().unwrap_or("");
+        for referrer in &link.referrers {
+            csv.push_str(&format!(
+                "{},{},{},{}\n",
+                csv_field(&link.target),
+                status,
+                csv_field(error),
+                csv_field(referrer)
+            ));
+            rows += 1;
+        }
+    }
+    fs::write(path, csv).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(rows)
+}
@@ -0,0 +1,171 @@
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+lazy_static::lazy_static! {
+    static ref LINK_TAG: Regex =
+        Regex::new(r"(?is)<(?:a|area|form|script|link|img|iframe|frame|source)\b([^>]*)>").unwrap();
+    static ref LINK_ATTRIBUTE: Regex = Regex::new(
+        r#"(?is)(?:^|\s)(href|src|action|srcset|data-src)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#
+    )
+    .unwrap();
+    static ref BASE_TAG: Regex =
+        Regex::new(r#"(?is)<base\b[^>]*\shref\s*=\s*["']?([^"'\s>]+)"#).unwrap();
+    static ref INLINE_SCRIPT: Regex = Regex::new(r"(?is)<script\b[^>]*>(.*?)</script>").unwrap();
+    static ref CSS_URL: Regex =
+        Regex::new(r#"(?i)url\(\s*["']?([^"')\s]+)["']?\s*\)|@import\s+["']([^"']+)["']"#).unwrap();
+    static ref ATTRIBUTE: Regex =
+        Regex::new(r#"(?s)([A-Za-z][A-Za-z0-9_:\-]*)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap();
+    static ref JS_STRING: Regex = Regex::new(
+        r#"["'`]((?:https?:)?//[^"'`\s<>]+|/[A-Za-z0-9_\-.~/?=&%+:@!$,;]+|\.{1,2}/[^"'`\s<>]+)["'`]"#
+    )
+    .unwrap();
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ContentKind {
+    Html,
+    JavaScript,
+    Css,
+    Other,
+}
+
+/// Decides how to read a body, preferring the content type and falling back
+/// to the URL's extension.
+pub fn content_kind(content_type: &str, url: &Url) -> ContentKind {
+    let content_type = content_type.to_lowercase();
+    let path = url.path().to_lowercase();
+    if content_type.contains("html") {
+        ContentKind::Html
+    } else if content_type.contains("javascript") || path.ends_with(".js") || path.ends_with(".mjs") {
+        ContentKind::JavaScript
+    } else if content_type.contains("text/css") || path.ends_with(".css") {
+        ContentKind::Css
+    } else {
+        ContentKind::Other
+    }
+}
+
+/// All http(s) links found in `body`, resolved against `base`, without
+/// fragments and in order of first appearance.
+pub fn extract_links(base: &Url, content_type: &str, body: &str) -> Vec<Url> {
+    let mut raw_links = Vec::new();
+    let mut base = base.clone();
+
+    match content_kind(content_type, &base) {
+        ContentKind::Html => {
+            if let Some(href) = BASE_TAG.captures(body).and_then(|c| c.get(1))
+                && let Ok(joined) = base.join(&decode_entities(href.as_str()))
+            {
+                base = joined;
+            }
+            html_links(body, &mut raw_links);
+            for script in INLINE_SCRIPT.captures_iter(body) {
+                js_links(&script[1], &mut raw_links);
+            }
+            css_links(body, &mut raw_links);
+        }
+        ContentKind::JavaScript => js_links(body, &mut raw_links),
+        ContentKind::Css => css_links(body, &mut raw_links),
+        ContentKind::Other => {}
+    }
+
+    let mut seen = HashSet::new();
+    raw_links
+        .into_iter()
+        .filter_map(|raw| resolve(&base, &raw))
+        .filter(|url| seen.insert(url.to_string()))
+        .collect()
+}
+
+fn html_links(body: &str, links: &mut Vec<String>) {
+    for tag in LINK_TAG.captures_iter(body) {
+        for attribute in LINK_ATTRIBUTE.captures_iter(&tag[1]) {
+            let value = attribute
+                .get(2)
+                .or_else(|| attribute.get(3))
+                .or_else(|| attribute.get(4))
+                .map_or("", |m| m.as_str());
+            if attribute[1].eq_ignore_ascii_case("srcset") {
+                for candidate in value.split(',') {
+                    if let Some(url) = candidate.split_whitespace().next() {
+                        links.push(decode_entities(url));
+                    }
+                }
+            } else {
+                links.push(decode_entities(value));
+            }
+        }
+    }
+}
+
+fn js_links(body: &str, links: &mut Vec<String>) {
+    for literal in JS_STRING.captures_iter(body) {
+        let value = &literal[1];
+        // Skip comment-like and bare separator strings.
+        if value.len() > 1 && !value.starts_with("//*") && value != "//" {
+            links.push(value.to_string());
+        }
+    }
+}
+
+fn css_links(body: &str, links: &mut Vec<String>) {
+    for reference in CSS_URL.captures_iter(body) {
+        if let Some(value) = reference.get(1).or_else(|| reference.get(2)) {
+            links.push(value.as_str().to_string());
+        }
+    }
+}
+
+fn resolve(base: &Url, raw: &str) -> Option<Url> {
+    let raw = raw.trim();
+    if raw.is_empty() || raw.starts_with('#') {
+        return None;
+    }
+    let lower = raw.to_lowercase();
+    if ["javascript:", "mailto:", "tel:", "data:"]
+        .iter()
+        .any(|scheme| lower.starts_with(scheme))
+    {
+        return None;
+    }
+    let mut url = base.join(raw).ok()?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return None;
+    }
+    url.set_fragment(None);
+    Some(url)
+}
+
+/// The attributes of an HTML tag, given the text after its name, with
+/// lowercase names and decoded values. Attributes without a value map to
+/// an empty string.
+pub fn tag_attributes(tag: &str) -> HashMap<String, String> {
+    let mut attributes: HashMap<String, String> = ATTRIBUTE
+        .captures_iter(tag)
+        .map(|capture| {
+            let value = capture
+                .get(2)
+                .or_else(|| capture.get(3))
+                .or_else(|| capture.get(4))
+                .map_or("", |m| m.as_str());
+            (capture[1].to_lowercase(), decode_entities(value))
+        })
+        .collect();
+    // Attributes without a value, such as a bare `alt`.
+    let without_values = ATTRIBUTE.replace_all(tag, " ");
+    for name in without_values.split_whitespace().map(|word| word.trim_matches('/')) {
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || "_:-".contains(c)) {
+            attributes.entry(name.to_lowercase()).or_default();
+        }
+    }
+    attributes
+}
+
+pub fn decode_entities(value: &str) -> String {
+    value
+        .replace("&amp;", "&")
+        .replace("&#38;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+}
@@ -1,8 +1,30 @@
 mod app;
+mod calibration;
+mod diff;
+mod discovery;
+mod export;
 mod file_processing;
 mod file_saver;
+mod fuzzer;
+mod graph;
+mod har;
+mod intercept;
+mod js_endpoints;
+mod link_checker;
+mod links;
+mod match_rules;
 mod network;
+mod openapi;
+mod response_store;
+mod robots;
+mod secrets;
+mod seo;
+mod sitemap;
+mod snippets;
+mod spider;
 mod syntax_highlighter;
+mod tool_lists;
+mod tree_filter;
 
 
 mod proxy;
@@ -0,0 +1,248 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+use crate::file_processing;
+use crate::response_store::{self, StoredResponse};
+use crate::{calibration, robots, secrets};
+
+const RULES_FILE: &str = "match_rules.json";
+
+/// Tags set by other features. A rule using one would be cleared or
+/// confused with them when those features re-tag the tree.
+const RESERVED_TAGS: &[&str] = &[calibration::SOFT_404_TAG, robots::ROBOTS_BLOCKED_TAG, secrets::SECRETS_TAG];
+
+lazy_static::lazy_static! {
+    /// The enabled rules, applied to every response as it is stored.
+    static ref ACTIVE_RULES: RwLock<Vec<CompiledRule>> = RwLock::new(Vec::new());
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleKind {
+    /// Case-insensitive substring of the body.
+    #[default]
+    Text,
+    Regex,
+    /// Regex over the values of one header; an empty pattern only checks
+    /// that the header is present.
+    Header,
+}
+
+impl RuleKind {
+    pub const ALL: [RuleKind; 3] = [RuleKind::Text, RuleKind::Regex, RuleKind::Header];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RuleKind::Text => "Text",
+            RuleKind::Regex => "Regex",
+            RuleKind::Header => "Header",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MatchRule {
+    pub enabled: bool,
+    pub kind: RuleKind,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub header: String,
+    pub pattern: String,
+    /// Added to the endpoint while its latest response matches.
+    pub tag: String,
+}
+
+impl Default for MatchRule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            kind: RuleKind::Text,
+            header: String::new(),
+            pattern: String::new(),
+            tag: String::new(),
+        }
+    }
+}
+
+pub struct CompiledRule {
+    kind: RuleKind,
+    header: String,
+    regex: Regex,
+    tag: String,
+}
+
+impl CompiledRule {
+    fn matches(&self, response: &StoredResponse, body: &str) -> bool {
+        match self.kind {
+            RuleKind::Text | RuleKind::Regex => self.regex.is_match(body),
+            RuleKind::Header => response
+                .headers
+                .iter()
+                .filter(|(name, _)| name.eq_ignore_ascii_case(&self.header))
+                .any(|(_, value)| self.regex.is_match(value)),
+        }
+    }
+}
+
+/// Compiles the enabled rules, naming the first one that is invalid.
+pub fn compile(rules: &[MatchRule]) -> Result<Vec<CompiledRule>, String> {
+    let mut compiled = Vec::new();
+    for (index, rule) in rules.iter().enumerate().filter(|(_, rule)| rule.enabled) {
+        let name = format!("Rule {}", index + 1);
+        let tag = rule.tag.trim();
+        if tag.is_empty() {
+            return Err(format!("{} has no tag", name));
+        }
+        if RESERVED_TAGS.contains(&tag) {
+            return Err(format!("{}: the tag \"{}\" is reserved", name, tag));
+        }
+        let pattern = match rule.kind {
+            RuleKind::Text if rule.pattern.is_empty() => {
+                return Err(format!("{} has no pattern", name));
+            }
+            RuleKind::Text => format!("(?i){}", regex::escape(&rule.pattern)),
+            RuleKind::Regex if rule.pattern.is_empty() => {
+                return Err(format!("{} has no pattern", name));
+            }
+            RuleKind::Regex => rule.pattern.clone(),
+            RuleKind::Header if rule.header.trim().is_empty() => {
+                return Err(format!("{} has no header name", name));
+            }
+            RuleKind::Header => rule.pattern.clone(),
+        };
+        let regex = Regex::new(&pattern).map_err(|e| format!("{}: {}", name, e))?;
+        compiled.push(CompiledRule {
+            kind: rule.kind,
+            header: rule.header.trim().to_string(),
+            regex,
+            tag: tag.to_string(),
+        });
+    }
+    Ok(compiled)
+}
+
+/// Reads the saved rules and makes the enabled ones active.
+pub fn load() -> Result<Vec<MatchRule>, String> {
+    let rules: Vec<MatchRule> = match fs::read(file_processing::data_path(RULES_FILE)) {
+        Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| format!("Invalid {}: {}", RULES_FILE, e))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(format!("Failed to read {}: {}", RULES_FILE, e)),
+    };
+    *ACTIVE_RULES.write().unwrap() = compile(&rules)?;
+    Ok(rules)
+}
+
+/// Saves `rules` and makes them active. Returns the tags no rule uses any
+/// more, so they can be removed from the tree.
+pub fn save(rules: &[MatchRule]) -> Result<Vec<String>, String> {
+    let compiled = compile(rules)?;
+    let encoded = serde_json::to_vec_pretty(rules).map_err(|e| e.to_string())?;
+    fs::write(file_processing::data_path(RULES_FILE), encoded)
+        .map_err(|e| format!("Failed to write {}: {}", RULES_FILE, e))?;
+
+    let mut active = ACTIVE_RULES.write().unwrap();
+    let mut stale: Vec<String> = active
+        .iter()
+        .map(|rule| rule.tag.clone())
+        .filter(|tag| !compiled.iter().any(|rule| &rule.tag == tag))
+        .collect();
+    stale.sort();
+    stale.dedup();
+    *active = compiled;
+    Ok(stale)
+}
+
+/// Byte ranges of `text` matched by the active body rules, sorted and
+/// merged, for highlighting.
+pub fn highlight_ranges(text: &str) -> Vec<Range<usize>> {
+    let active = ACTIVE_RULES.read().unwrap();
+    let mut ranges: Vec<Range<usize>> = active
+        .iter()
+        .filter(|rule| rule.kind != RuleKind::Header)
+        .flat_map(|rule| rule.regex.find_iter(text).map(|m| m.range()))
+        .filter(|range| !range.is_empty())
+        .collect();
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Tags the endpoint of `response` with every active rule it matches and
+/// untags the ones it no longer matches. Returns whether any matched.
+pub fn apply(db: &sled::Db, response: &StoredResponse, body: &[u8]) -> bool {
+    let active = ACTIVE_RULES.read().unwrap();
+    apply_with(db, &active, response, body)
+}
+
+fn apply_with(db: &sled::Db, rules: &[CompiledRule], response: &StoredResponse, body: &[u8]) -> bool {
+    if rules.is_empty() {
+        return false;
+    }
+    let Some(key) = response_store::endpoint_key(&response.url) else {
+        return false;
+    };
+    let body = String::from_utf8_lossy(body);
+
+    // Several rules may share a tag; it stays while any of them matches.
+    let mut tags: HashMap<&str, bool> = HashMap::new();
+    for rule in rules {
+        let matched = rule.matches(response, &body);
+        *tags.entry(rule.tag.as_str()).or_default() |= matched;
+    }
+    for (tag, matched) in &tags {
+        let _ = file_processing::set_tag(db, &key, tag, *matched);
+    }
+    tags.values().any(|matched| *matched)
+}
+
+/// Re-applies the active rules to the newest response of every endpoint
+/// and removes `stale_tags`, reporting how many endpoints matched.
+pub fn spawn_rescan_thread(db: Arc<sled::Db>, stale_tags: Vec<String>) -> Receiver<Result<usize, String>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut matched = 0;
+        for response in response_store::latest_all(&db) {
+            let body = response_store::body(&db, &response.body_hash).unwrap_or_default();
+            if apply(&db, &response, &body) {
+                matched += 1;
+            }
+            if let Some(key) = response_store::endpoint_key(&response.url) {
+                for tag in &stale_tags {
+                    let _ = file_processing::set_tag(&db, &key, tag, false);
+                }
+            }
+        }
+        let _ = sender.send(db.flush().map(|_| matched).map_err(|e| e.to_string()));
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserved_tags_are_refused() {
+        let rule = |tag: &str| MatchRule {
+            pattern: "admin".to_string(),
+            tag: tag.to_string(),
+            ..MatchRule::default()
+        };
+        assert!(compile(&[rule("admin-panel")]).is_ok());
+        for tag in RESERVED_TAGS {
+            assert!(compile(&[rule(&format!(" {} ", tag))]).is_err());
+        }
+    }
+}
@@ -1,65 +1,417 @@
+use std::collections::HashMap;
 use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-pub type NetworkResult = (String, String, String, String, String, u64, u128);
+use crate::proxy::{ProxyConfig, ProxyPool};
+use crate::response_store::{self, StoredResponse};
 
-pub fn spawn_request_thread(url: String) -> Receiver<NetworkResult> {
+pub type NetworkResult = (StoredResponse, Vec<u8>);
+
+/// Settings shared by every code path that talks to the network, so the
+/// repeater and bulk proxy sends behave the same way.
+#[derive(Clone)]
+pub struct NetworkConfig {
+    pub proxy: ProxyConfig,
+    /// When non-empty, requests rotate through the pool instead of `proxy`.
+    pub proxy_pool: Arc<ProxyPool>,
+    pub health_check_url: String,
+    /// Off until a proxy is configured or a pool is loaded, which switch it
+    /// on; `proxy` holds a placeholder address before then.
+    pub route_through_proxy: bool,
+    pub verify_tls: bool,
+    pub connect_timeout_secs: u64,
+    pub timeout_secs: u64,
+    pub default_headers: Vec<(String, String)>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            proxy: ProxyConfig::default(),
+            proxy_pool: Arc::new(ProxyPool::default()),
+            health_check_url: String::new(),
+            route_through_proxy: false,
+            verify_tls: true,
+            connect_timeout_secs: 10,
+            timeout_secs: 30,
+            default_headers: vec![
+                ("User-Agent".to_string(), "Sitemapper/1.0".to_string()),
+                ("Accept".to_string(), "*/*".to_string()),
+            ],
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// The `User-Agent` sent with every request, or `*` when none is set.
+    pub fn user_agent(&self) -> &str {
+        self.default_headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("user-agent"))
+            .map_or("*", |(_, value)| value.as_str())
+    }
+
+    /// The proxy to use for `url`: the pool's pick when a pool is loaded,
+    /// the single configured proxy otherwise.
+    pub fn proxy_for(&self, url: &str) -> Result<ProxyConfig, String> {
+        if self.proxy_pool.is_empty() {
+            return Ok(self.proxy.clone());
+        }
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(String::from))
+            .unwrap_or_default();
+        self.proxy_pool
+            .pick(&host)
+            .ok_or_else(|| "No live proxies left in the pool".to_string())
+    }
+
+    /// Client for regular requests to `url`; only proxied when
+    /// `route_through_proxy` is set. Returns the proxy used, if any.
+    pub fn client(
+        &self,
+        url: &str,
+    ) -> Result<(reqwest::blocking::Client, Option<ProxyConfig>), String> {
+        if self.route_through_proxy {
+            let proxy = self.proxy_for(url)?;
+            Ok((self.build_client(Some(&proxy))?, Some(proxy)))
+        } else {
+            Ok((self.build_client(None)?, None))
+        }
+    }
+
+    pub fn build_client(
+        &self,
+        proxy: Option<&ProxyConfig>,
+    ) -> Result<reqwest::blocking::Client, String> {
+        self.client_builder(proxy)?.build().map_err(|e| e.to_string())
+    }
+
+    /// Builder with the TLS, timeout and proxy settings applied, for callers
+    /// that need to tweak the client further.
+    pub fn client_builder(
+        &self,
+        proxy: Option<&ProxyConfig>,
+    ) -> Result<reqwest::blocking::ClientBuilder, String> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .danger_accept_invalid_certs(!self.verify_tls)
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs.max(1)));
+        if self.timeout_secs > 0 {
+            builder = builder.timeout(Duration::from_secs(self.timeout_secs));
+        }
+        if let Some(proxy) = proxy {
+            builder = builder.proxy(proxy.to_reqwest()?);
+        } else {
+            builder = builder.no_proxy();
+        }
+        Ok(builder)
+    }
+
+    pub fn get(
+        &self,
+        client: &reqwest::blocking::Client,
+        url: &str,
+    ) -> reqwest::blocking::RequestBuilder {
+        let mut request = client.get(url);
+        for (name, value) in &self.default_headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        request
+    }
+}
+
+/// Settings the response panel can override for a single request; the rest
+/// is read from the live `NetworkConfig` when the request is sent.
+#[derive(Clone, Debug)]
+pub struct RequestOverride {
+    pub route_through_proxy: bool,
+    pub verify_tls: bool,
+    pub timeout_secs: u64,
+}
+
+impl RequestOverride {
+    pub fn from_config(config: &NetworkConfig) -> Self {
+        Self {
+            route_through_proxy: config.route_through_proxy,
+            verify_tls: config.verify_tls,
+            timeout_secs: config.timeout_secs,
+        }
+    }
+
+    pub fn apply(&self, config: &NetworkConfig, default_headers: Vec<(String, String)>) -> NetworkConfig {
+        NetworkConfig {
+            route_through_proxy: self.route_through_proxy,
+            verify_tls: self.verify_tls,
+            timeout_secs: self.timeout_secs,
+            default_headers,
+            ..config.clone()
+        }
+    }
+}
+
+/// Spaces requests out evenly across worker threads. A limit of zero
+/// requests per second disables it.
+pub struct RateLimiter {
+    interval: Option<Duration>,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: u32) -> Self {
+        Self {
+            interval: (requests_per_second > 0)
+                .then(|| Duration::from_secs_f64(1.0 / requests_per_second as f64)),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Blocks until the caller may send its next request.
+    pub fn wait(&self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + interval;
+            slot
+        };
+        let now = Instant::now();
+        if slot > now {
+            thread::sleep(slot - now);
+        }
+    }
+}
+
+/// Parses `Name: value` lines, skipping blank or malformed ones.
+pub fn parse_header_lines(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+pub fn format_header_lines(headers: &[(String, String)]) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns the body to show in "Pretty" mode and the language used to
+/// highlight it, based on the response content type.
+pub fn prettify_body(content_type: &str, body: &str) -> (String, String) {
+    let content_type = content_type.to_lowercase();
+    if content_type.contains("application/json") {
+        match serde_json::from_str::<serde_json::Value>(body) {
+            Ok(json_value) => {
+                let pretty_json = serde_json::to_string_pretty(&json_value).unwrap_or_else(|_| body.to_string());
+                (pretty_json, "json".to_string())
+            }
+            Err(_) => (body.to_string(), "json".to_string()),
+        }
+    } else if content_type.contains("text/html") {
+        (body.to_string(), "html".to_string())
+    } else if content_type.contains("text/xml") || content_type.contains("application/xml") {
+        (body.to_string(), "xml".to_string())
+    } else if content_type.contains("javascript") {
+        (body.to_string(), "javascript".to_string())
+    } else {
+        (body.to_string(), "text".to_string())
+    }
+}
+
+/// Clients keyed by the proxy they go through, for workers that send many
+/// requests and should not rebuild a client for each one.
+pub struct ClientCache {
+    follow_redirects: bool,
+    clients: HashMap<Option<ProxyConfig>, reqwest::blocking::Client>,
+}
+
+impl ClientCache {
+    pub fn new(follow_redirects: bool) -> Self {
+        Self {
+            follow_redirects,
+            clients: HashMap::new(),
+        }
+    }
+
+    /// The client to use for `url` and the proxy behind it, rotating through
+    /// the pool when one is loaded.
+    pub fn client_for(
+        &mut self,
+        config: &NetworkConfig,
+        url: &str,
+    ) -> Result<(&reqwest::blocking::Client, Option<ProxyConfig>), String> {
+        let proxy = if config.route_through_proxy {
+            Some(config.proxy_for(url)?)
+        } else {
+            None
+        };
+        if !self.clients.contains_key(&proxy) {
+            let mut builder = config.client_builder(proxy.as_ref())?;
+            if !self.follow_redirects {
+                builder = builder.redirect(reqwest::redirect::Policy::none());
+            }
+            let client = builder.build().map_err(|e| e.to_string())?;
+            self.clients.insert(proxy.clone(), client);
+        }
+        Ok((&self.clients[&proxy], proxy))
+    }
+}
+
+fn format_request(request: &reqwest::blocking::Request) -> String {
+    let url = request.url();
+    let mut path = url.path().to_string();
+    if let Some(query) = url.query() {
+        path.push('?');
+        path.push_str(query);
+    }
+    let mut host = url.host_str().unwrap_or("-").to_string();
+    if let Some(port) = url.port() {
+        host.push_str(&format!(":{}", port));
+    }
+    let mut request_str = format!("{} {} HTTP/1.1\nHost: {}\n", request.method(), path, host);
+    for (name, value) in request.headers() {
+        request_str.push_str(&format!("{}: {}\n", name, value.to_str().unwrap_or("")));
+    }
+    request_str
+}
+
+/// Fetches `url` with the shared settings. Failures are reported as a
+/// response without a status whose body holds the error.
+pub fn fetch(config: &NetworkConfig, url: &str) -> (StoredResponse, Vec<u8>) {
+    match config.client(url) {
+        Ok((client, proxy)) => fetch_with(config, &client, proxy.as_ref(), url),
+        Err(e) => failed_response(url, Instant::now(), e),
+    }
+}
+
+/// Like [`fetch`], but with a client the caller built and may reuse.
+/// `proxy` is the proxy behind `client`, if any, so the pool can be told
+/// whether it worked.
+pub fn fetch_with(
+    config: &NetworkConfig,
+    client: &reqwest::blocking::Client,
+    proxy: Option<&ProxyConfig>,
+    url: &str,
+) -> (StoredResponse, Vec<u8>) {
+    send_with(config, client, proxy, config.get(client, url), url)
+}
+
+/// Sends a request the caller built on `client`, for anything other than a
+/// plain GET. `url` is only used to label failures.
+pub fn send_with(
+    config: &NetworkConfig,
+    client: &reqwest::blocking::Client,
+    proxy: Option<&ProxyConfig>,
+    request: reqwest::blocking::RequestBuilder,
+    url: &str,
+) -> (StoredResponse, Vec<u8>) {
+    let start_time = Instant::now();
+    let send = || -> Result<(String, reqwest::Method, reqwest::blocking::Response), String> {
+        let request = request.build().map_err(|e| format!("{:#?}", e))?;
+        let request_str = format_request(&request);
+        let method = request.method().clone();
+        let response = client.execute(request);
+        if let Some(proxy) = proxy {
+            let ok = match &response {
+                Ok(_) => true,
+                Err(e) => !(e.is_connect() || e.is_timeout()),
+            };
+            config.proxy_pool.report(proxy, ok);
+        }
+        let response = response.map_err(|e| format!("{:#?}", e))?;
+        Ok((request_str, method, response))
+    };
+    match send() {
+        Ok((request_str, method, response)) => {
+            let status = response.status();
+            let headers: Vec<(String, String)> = response
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect();
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|val| val.to_str().ok())
+                .unwrap_or("")
+                .to_lowercase();
+
+            let body = match response.bytes() {
+                Ok(bytes) => bytes.to_vec(),
+                Err(e) => format!("Failed to read response body: {}", e).into_bytes(),
+            };
+            let response = StoredResponse {
+                method: method.to_string(),
+                url: url.to_string(),
+                request: request_str,
+                status: Some(status.as_u16()),
+                headers,
+                content_type,
+                elapsed_ms: start_time.elapsed().as_millis(),
+                ..Default::default()
+            };
+            (response, body)
+        }
+        Err(e) => failed_response(url, start_time, e),
+    }
+}
+
+fn failed_response(url: &str, start_time: Instant, error: String) -> (StoredResponse, Vec<u8>) {
+    let response = StoredResponse {
+        method: "GET".to_string(),
+        url: url.to_string(),
+        request: format!("Failed to make request to: {}", url),
+        elapsed_ms: start_time.elapsed().as_millis(),
+        ..Default::default()
+    };
+    (response, format!("Error: {}", error).into_bytes())
+}
+
+/// Fetches `url` in the background, storing the response in `db` when a
+/// project is open.
+pub fn spawn_request_thread(
+    url: String,
+    config: NetworkConfig,
+    db: Option<Arc<sled::Db>>,
+) -> Receiver<NetworkResult> {
     let (sender, receiver) = mpsc::channel();
 
     thread::spawn(move || {
-        let start_time = Instant::now();
-        let result = match reqwest::blocking::get(&url) {
-            Ok(response) => {
-                let request_str = format!("GET {} HTTP/1.1\nHost: {}\nUser-Agent: Sitemapper/1.0\nAccept: */*\n",
-                    url.splitn(4, '/').nth(3).unwrap_or(""),
-                    url.splitn(4, '/').nth(2).unwrap_or("-"));
-
-                let status = response.status();
-                let headers = response.headers().clone();
-                let content_length = response.content_length().unwrap_or(0);
-                let content_type = headers
-                    .get(reqwest::header::CONTENT_TYPE)
-                    .and_then(|val| val.to_str().ok())
-                    .unwrap_or("")
-                    .to_lowercase();
-
-                let body = response.text().unwrap_or_else(|e| format!("Failed to read response body: {}", e));
-                let elapsed = start_time.elapsed().as_millis();
-
-                let (pretty_body, language) = if content_type.contains("application/json") {
-                    match serde_json::from_str::<serde_json::Value>(&body) {
-                        Ok(json_value) => {
-                            let pretty_json = serde_json::to_string_pretty(&json_value).unwrap_or_else(|_| body.clone());
-                            (pretty_json, "json".to_string())
-                        }
-                        Err(_) => (body.clone(), "json".to_string()),
-                    }
-                } else if content_type.contains("text/html") {
-                    (body.clone(), "html".to_string())
-                } else if content_type.contains("text/xml") || content_type.contains("application/xml") {
-                    (body.clone(), "xml".to_string())
-                } else if content_type.contains("javascript") {
-                    (body.clone(), "javascript".to_string())
-                } else {
-                    (body.clone(), "text".to_string())
-                };
-
-                let response_headers_str = format!("HTTP/1.1 {}\n{:#?}", status, headers);
-                (request_str, response_headers_str, body, pretty_body, language, content_length, elapsed)
+        let (mut response, body) = fetch(&config, &url);
+        if let Some(db) = &db {
+            match response_store::record(db, response.clone(), &body) {
+                Ok(stored) => response = stored,
+                Err(e) => response.request.push_str(&format!("\n(not stored: {})", e)),
             }
-            Err(e) => (
-                format!("Failed to make request to: {}", url),
-                String::new(),
-                format!("Error: {:#?}", e),
-                String::new(),
-                "text".to_string(),
-                0,
-                start_time.elapsed().as_millis(),
-            ),
-        };
-        let _ = sender.send(result);
+        }
+        let _ = sender.send((response, body));
     });
 
     receiver
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_text_keeps_explicit_ports() {
+        let client = reqwest::blocking::Client::new();
+        let request = client.get("http://example.com:8081/a?b=1").build().unwrap();
+        assert!(format_request(&request).starts_with("GET /a?b=1 HTTP/1.1\nHost: example.com:8081\n"));
+        let request = client.get("https://example.com/").build().unwrap();
+        assert!(format_request(&request).contains("Host: example.com\n"));
+    }
+}
@@ -0,0 +1,341 @@
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::{self, Write};
+use url::Url;
+
+use crate::export::{EndpointRow, Export, ExportFormat};
+
+lazy_static::lazy_static! {
+    static ref UUID: Regex =
+        Regex::new(r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$").unwrap();
+    static ref HEX_ID: Regex = Regex::new(r"^[0-9a-fA-F]{16,}$").unwrap();
+    static ref TOKEN: Regex = Regex::new(r"^[A-Za-z0-9_\-]{20,}$").unwrap();
+}
+
+const OPENAPI_VERSION: &str = "3.0.3";
+
+/// The schema of a path segment that looks like an identifier rather than
+/// a fixed name, or `None` for fixed names.
+fn variable_schema(segment: &str) -> Option<Value> {
+    if !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()) {
+        Some(json!({ "type": "integer" }))
+    } else if UUID.is_match(segment) {
+        Some(json!({ "type": "string", "format": "uuid" }))
+    } else if (HEX_ID.is_match(segment) || TOKEN.is_match(segment))
+        && segment.chars().any(|c| c.is_ascii_digit())
+        && segment.chars().any(|c| c.is_ascii_alphabetic())
+    {
+        Some(json!({ "type": "string" }))
+    } else {
+        None
+    }
+}
+
+/// `userId` after `users`, `categoryId` after `categories`, `id` at the
+/// start of a path.
+fn parameter_name(previous: Option<&str>) -> String {
+    let word: String = previous
+        .unwrap_or("")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+    let singular = if let Some(stem) = word.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if word.ends_with('s') && !word.ends_with("ss") && word.len() > 3 {
+        word[..word.len() - 1].to_string()
+    } else {
+        word
+    };
+    if singular.is_empty() || singular.starts_with(|c: char| c.is_ascii_digit()) {
+        "id".to_string()
+    } else {
+        format!("{}Id", singular)
+    }
+}
+
+/// The templated path of `url` and its path parameters in order.
+fn template(url: &Url) -> (String, Vec<(String, Value)>) {
+    let mut path = String::new();
+    let mut parameters: Vec<(String, Value)> = Vec::new();
+    let mut previous = None;
+    for segment in url.path_segments().into_iter().flatten().filter(|segment| !segment.is_empty()) {
+        path.push('/');
+        match variable_schema(segment) {
+            Some(schema) => {
+                let base = parameter_name(previous);
+                let mut name = base.clone();
+                let mut suffix = 2;
+                while parameters.iter().any(|(existing, _)| *existing == name) {
+                    name = format!("{}{}", base, suffix);
+                    suffix += 1;
+                }
+                path.push_str(&format!("{{{}}}", name));
+                parameters.push((name, schema));
+                previous = None;
+            }
+            None => {
+                path.push_str(segment);
+                previous = Some(segment);
+            }
+        }
+    }
+    if path.is_empty() {
+        path.push('/');
+    }
+    (path, parameters)
+}
+
+#[derive(Default)]
+struct QueryParameter {
+    all_integer: bool,
+    example: String,
+}
+
+#[derive(Default)]
+struct Operation {
+    query: BTreeMap<String, QueryParameter>,
+    /// Content types seen per status; `None` keys the placeholder response
+    /// of endpoints that were never probed.
+    responses: BTreeMap<Option<u16>, BTreeSet<String>>,
+}
+
+#[derive(Default)]
+struct PathItem {
+    servers: BTreeSet<String>,
+    parameters: Vec<(String, Value)>,
+    operations: BTreeMap<String, Operation>,
+}
+
+impl PathItem {
+    fn add(&mut self, row: &EndpointRow, url: &Url) {
+        let mut observed: Vec<(String, Option<u16>, String)> = row
+            .observed
+            .iter()
+            .map(|observation| {
+                (
+                    observation.method.to_lowercase(),
+                    Some(observation.status),
+                    observation.content_type.clone(),
+                )
+            })
+            .collect();
+        if observed.is_empty() {
+            observed.push(("get".to_string(), None, String::new()));
+        }
+        for (method, status, content_type) in observed {
+            let operation = self.operations.entry(method).or_default();
+            let content_types = operation.responses.entry(status).or_default();
+            if !content_type.is_empty() {
+                content_types.insert(content_type);
+            }
+            for (name, value) in url.query_pairs() {
+                let parameter = operation.query.entry(name.into_owned()).or_insert_with(|| QueryParameter {
+                    all_integer: true,
+                    example: value.to_string(),
+                });
+                parameter.all_integer &= !value.is_empty() && value.chars().all(|c| c.is_ascii_digit());
+            }
+        }
+    }
+}
+
+fn status_description(status: u16) -> &'static str {
+    reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("Observed response")
+}
+
+/// `get_users_userId` for `get /users/{userId}`, with a numeric suffix when
+/// another path already produced the same id (`/a/b` and `/a_b`).
+fn operation_id(method: &str, path: &str, used: &mut HashSet<String>) -> String {
+    let words: Vec<&str> = path
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    let base = if words.is_empty() {
+        format!("{}_root", method)
+    } else {
+        format!("{}_{}", method, words.join("_"))
+    };
+    let mut id = base.clone();
+    let mut suffix = 2;
+    while !used.insert(id.clone()) {
+        id = format!("{}_{}", base, suffix);
+        suffix += 1;
+    }
+    id
+}
+
+fn operation_json(operation_id: &str, path_parameters: &[(String, Value)], operation: &Operation) -> Value {
+    let mut parameters: Vec<Value> = path_parameters
+        .iter()
+        .map(|(name, schema)| json!({ "name": name, "in": "path", "required": true, "schema": schema }))
+        .collect();
+    for (name, parameter) in &operation.query {
+        let schema = if parameter.all_integer {
+            json!({ "type": "integer" })
+        } else {
+            json!({ "type": "string" })
+        };
+        parameters.push(json!({
+            "name": name,
+            "in": "query",
+            "required": false,
+            "schema": schema,
+            "example": parameter.example,
+        }));
+    }
+
+    let mut responses = Map::new();
+    for (status, content_types) in &operation.responses {
+        let (code, description) = match status {
+            Some(status) => (status.to_string(), status_description(*status)),
+            None => ("default".to_string(), "Not observed"),
+        };
+        let mut response = Map::new();
+        response.insert("description".to_string(), json!(description));
+        if !content_types.is_empty() {
+            let content: Map<String, Value> = content_types
+                .iter()
+                .map(|content_type| (content_type.clone(), json!({})))
+                .collect();
+            response.insert("content".to_string(), Value::Object(content));
+        }
+        responses.insert(code, Value::Object(response));
+    }
+
+    let mut object = Map::new();
+    object.insert("operationId".to_string(), json!(operation_id));
+    if !parameters.is_empty() {
+        object.insert("parameters".to_string(), json!(parameters));
+    }
+    object.insert("responses".to_string(), Value::Object(responses));
+    Value::Object(object)
+}
+
+/// An OpenAPI 3 skeleton of the exported endpoints: each host becomes a
+/// server, identifier-like path segments become path parameters, and the
+/// methods, query parameters, statuses and content types seen in stored
+/// responses fill in the operations.
+pub struct OpenApi;
+
+impl OpenApi {
+    fn document(export: &Export) -> Value {
+        let mut paths: BTreeMap<String, PathItem> = BTreeMap::new();
+        let mut servers = BTreeSet::new();
+        for row in &export.rows {
+            let Ok(url) = Url::parse(&row.url) else {
+                continue;
+            };
+            let server = url.origin().ascii_serialization();
+            let (path, parameters) = template(&url);
+            let item = paths.entry(path).or_default();
+            if item.parameters.is_empty() {
+                item.parameters = parameters;
+            }
+            item.servers.insert(server.clone());
+            item.add(row, &url);
+            servers.insert(server);
+        }
+
+        let mut path_objects = Map::new();
+        let mut operation_ids = HashSet::new();
+        for (path, item) in &paths {
+            let mut object = Map::new();
+            // Paths are relative to the servers, so when hosts are mixed
+            // each path names the ones it was seen on.
+            if servers.len() > 1 {
+                let item_servers: Vec<Value> = item.servers.iter().map(|url| json!({ "url": url })).collect();
+                object.insert("servers".to_string(), json!(item_servers));
+            }
+            for (method, operation) in &item.operations {
+                let id = operation_id(method, path, &mut operation_ids);
+                object.insert(method.clone(), operation_json(&id, &item.parameters, operation));
+            }
+            path_objects.insert(path.clone(), Value::Object(object));
+        }
+
+        let servers: Vec<Value> = servers.iter().map(|url| json!({ "url": url })).collect();
+        json!({
+            "openapi": OPENAPI_VERSION,
+            "info": {
+                "title": "Observed endpoints",
+                "version": "1.0.0",
+                "description": format!("Generated from {} endpoints in the sitemap.", export.rows.len()),
+            },
+            "servers": servers,
+            "paths": path_objects,
+        })
+    }
+}
+
+impl ExportFormat for OpenApi {
+    fn label(&self) -> &'static str {
+        "OpenAPI 3"
+    }
+
+    fn extension(&self) -> &'static str {
+        "openapi.json"
+    }
+
+    fn write(&self, export: &Export, out: &mut dyn Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, &Self::document(export))?;
+        writeln!(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{self, ExportScope};
+    use crate::file_processing;
+
+    fn templated(url: &str) -> (String, Vec<(String, Value)>) {
+        template(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn identifier_segments_become_typed_parameters() {
+        let (path, parameters) = templated(
+            "https://example.com/users/42/orders/123e4567-e89b-12d3-a456-426614174000/files/a1b2c3d4e5f6a7b8c9d0e1f2",
+        );
+        assert_eq!(path, "/users/{userId}/orders/{orderId}/files/{fileId}");
+        assert_eq!(
+            parameters,
+            [
+                ("userId".to_string(), json!({ "type": "integer" })),
+                ("orderId".to_string(), json!({ "type": "string", "format": "uuid" })),
+                ("fileId".to_string(), json!({ "type": "string" })),
+            ]
+        );
+
+        let (path, _) = templated("https://example.com/v1/status/readme");
+        assert_eq!(path, "/v1/status/readme");
+    }
+
+    #[test]
+    fn parameter_names_follow_the_previous_segment() {
+        assert_eq!(parameter_name(Some("categories")), "categoryId");
+        assert_eq!(parameter_name(Some("users")), "userId");
+        assert_eq!(parameter_name(Some("address")), "addressId");
+        assert_eq!(parameter_name(None), "id");
+
+        let (path, _) = templated("https://example.com/7/9");
+        assert_eq!(path, "/{id}/{id2}");
+    }
+
+    #[test]
+    fn colliding_operation_ids_get_a_suffix() {
+        let db = file_processing::test_db(&["https://example.com/a/b", "https://example.com/a_b"]);
+        let document = OpenApi::document(&export::collect(&db, &ExportScope::default()));
+        let mut ids: Vec<&str> = ["/a/b", "/a_b"]
+            .iter()
+            .map(|path| document["paths"][*path]["get"]["operationId"].as_str().unwrap())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, ["get_a_b", "get_a_b_2"]);
+    }
+}
@@ -0,0 +1,294 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+use crate::calibration;
+use crate::match_rules;
+use crate::secrets;
+
+const RESPONSES_TREE: &str = "responses";
+const BODIES_TREE: &str = "bodies";
+
+/// A response as kept in the project database. Bodies are stored separately,
+/// keyed by their SHA-256, so identical bodies are only written once.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct StoredResponse {
+    pub method: String,
+    pub url: String,
+    pub request: String,
+    pub status: Option<u16>,
+    pub headers: Vec<(String, String)>,
+    pub content_type: String,
+    pub body_hash: String,
+    pub body_len: u64,
+    pub elapsed_ms: u128,
+    pub timestamp: u64,
+}
+
+impl StoredResponse {
+    /// Status line and headers as shown in the response viewer.
+    pub fn header_block(&self) -> String {
+        let mut block = match self.status {
+            Some(status) => format!(
+                "HTTP/1.1 {} {}\n",
+                status,
+                reqwest::StatusCode::from_u16(status)
+                    .ok()
+                    .and_then(|s| s.canonical_reason())
+                    .unwrap_or("")
+            ),
+            None => String::new(),
+        };
+        for (name, value) in &self.headers {
+            block.push_str(&format!("{}: {}\n", name, value));
+        }
+        block
+    }
+}
+
+/// The sitemap tree key of the endpoint a URL belongs to, matching the keys
+/// produced when URLs are imported.
+pub fn endpoint_key(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map_or(Vec::new(), |s| s.filter(|s| !s.is_empty()).collect());
+
+    let mut leaf = String::new();
+    if let Some(query) = url.query() {
+        leaf.push('?');
+        leaf.push_str(query);
+    }
+    if let Some(fragment) = url.fragment() {
+        leaf.push('#');
+        leaf.push_str(fragment);
+    }
+
+    let mut key = host.to_string();
+    for segment in &segments {
+        key.push('/');
+        key.push_str(segment);
+    }
+    if !leaf.is_empty() {
+        if segments.is_empty() {
+            key.push('/');
+        }
+        key.push_str(&leaf);
+    }
+    Some(key)
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn body_hash(body: &[u8]) -> String {
+    openssl::sha::sha256(body)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn history_prefix(key: &str) -> Vec<u8> {
+    let mut prefix = key.as_bytes().to_vec();
+    prefix.push(0);
+    prefix
+}
+
+/// Stores `response` with `body` under its endpoint, filling in the body
+/// hash, length and timestamp, and re-checks the endpoint against the host's
+/// soft-404 calibration and the match rules, and scans the body for secrets.
+/// Returns the record as stored.
+pub fn record(
+    db: &sled::Db,
+    mut response: StoredResponse,
+    body: &[u8],
+) -> Result<StoredResponse, String> {
+    let key = endpoint_key(&response.url)
+        .ok_or_else(|| format!("Cannot store response for {}", response.url))?;
+    let responses = db.open_tree(RESPONSES_TREE).map_err(|e| e.to_string())?;
+    let bodies = db.open_tree(BODIES_TREE).map_err(|e| e.to_string())?;
+
+    response.body_hash = body_hash(body);
+    response.body_len = body.len() as u64;
+    if response.timestamp == 0 {
+        response.timestamp = now_millis();
+    }
+
+    if !bodies
+        .contains_key(response.body_hash.as_bytes())
+        .map_err(|e| e.to_string())?
+    {
+        bodies
+            .insert(response.body_hash.as_bytes(), body)
+            .map_err(|e| e.to_string())?;
+    }
+
+    // The sequence number keeps responses recorded in the same millisecond
+    // apart while keeping the keys in recording order.
+    let sequence = db.generate_id().map_err(|e| e.to_string())?;
+    let mut record_key = history_prefix(&key);
+    record_key.extend_from_slice(&response.timestamp.to_be_bytes());
+    record_key.extend_from_slice(&sequence.to_be_bytes());
+    let encoded = serde_json::to_vec(&response).map_err(|e| e.to_string())?;
+    responses
+        .insert(record_key, encoded)
+        .map_err(|e| e.to_string())?;
+    calibration::classify(db, &response, body);
+    match_rules::apply(db, &response, body);
+    let _ = secrets::scan(db, &response, body);
+    Ok(response)
+}
+
+/// All stored responses for the endpoint `key`, newest first.
+pub fn history(db: &sled::Db, key: &str) -> Vec<StoredResponse> {
+    let Ok(responses) = db.open_tree(RESPONSES_TREE) else {
+        return Vec::new();
+    };
+    let mut history: Vec<StoredResponse> = responses
+        .scan_prefix(history_prefix(key))
+        .values()
+        .flatten()
+        .filter_map(|value| serde_json::from_slice(&value).ok())
+        .collect();
+    history.reverse();
+    history
+}
+
+/// The newest stored response of every endpoint on `host`.
+pub fn latest_for_host(db: &sled::Db, host: &str) -> Vec<StoredResponse> {
+    let Ok(responses) = db.open_tree(RESPONSES_TREE) else {
+        return Vec::new();
+    };
+    newest_per_endpoint(responses.scan_prefix(host.as_bytes()), |endpoint| {
+        endpoint == host.as_bytes() || endpoint.starts_with(format!("{}/", host).as_bytes())
+    })
+}
+
+/// The newest stored response of every endpoint.
+pub fn latest_all(db: &sled::Db) -> Vec<StoredResponse> {
+    let Ok(responses) = db.open_tree(RESPONSES_TREE) else {
+        return Vec::new();
+    };
+    newest_per_endpoint(responses.iter(), |_| true)
+}
+
+fn newest_per_endpoint(
+    records: impl Iterator<Item = sled::Result<(sled::IVec, sled::IVec)>>,
+    keep: impl Fn(&[u8]) -> bool,
+) -> Vec<StoredResponse> {
+    let mut latest: Vec<StoredResponse> = Vec::new();
+    let mut last_key: Option<Vec<u8>> = None;
+    for (key, value) in records.flatten() {
+        let Some(separator) = key.iter().position(|b| *b == 0) else {
+            continue;
+        };
+        let endpoint = &key[..separator];
+        if !keep(endpoint) {
+            continue;
+        }
+        let Ok(response) = serde_json::from_slice(&value) else {
+            continue;
+        };
+        // Keys sort oldest first within an endpoint, so a repeat replaces.
+        if last_key.as_deref() == Some(endpoint) {
+            latest.pop();
+        }
+        last_key = Some(endpoint.to_vec());
+        latest.push(response);
+    }
+    latest
+}
+
+pub fn latest(db: &sled::Db, key: &str) -> Option<StoredResponse> {
+    let responses = db.open_tree(RESPONSES_TREE).ok()?;
+    responses
+        .scan_prefix(history_prefix(key))
+        .values()
+        .next_back()?
+        .ok()
+        .and_then(|value| serde_json::from_slice(&value).ok())
+}
+
+pub fn body(db: &sled::Db, hash: &str) -> Option<Vec<u8>> {
+    let bodies = db.open_tree(BODIES_TREE).ok()?;
+    bodies.get(hash.as_bytes()).ok().flatten().map(|b| b.to_vec())
+}
+
+/// Formats a millisecond Unix timestamp as `YYYY-MM-DD HH:MM:SS` (UTC).
+pub fn format_timestamp(millis: u64) -> String {
+    let secs = millis / 1000;
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let time_of_day = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        (time_of_day % 3600) / 60,
+        time_of_day % 60
+    )
+}
+
+/// The UTC date of `millis` in W3C format, as used by sitemaps.
+pub fn format_date(millis: u64) -> String {
+    let (year, month, day) = civil_from_days((millis / 1000 / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) civil date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn responses_in_the_same_millisecond_are_all_kept() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        for status in [200, 500, 404] {
+            let response = StoredResponse {
+                method: "GET".to_string(),
+                url: "https://example.com/a?x=1".to_string(),
+                status: Some(status),
+                timestamp: 1_700_000_000_000,
+                ..Default::default()
+            };
+            record(&db, response, b"body").unwrap();
+        }
+        let statuses: Vec<Option<u16>> = history(&db, "example.com/a?x=1").iter().map(|r| r.status).collect();
+        assert_eq!(statuses, vec![Some(404), Some(500), Some(200)]);
+        assert_eq!(latest(&db, "example.com/a?x=1").unwrap().status, Some(404));
+        assert_eq!(latest_all(&db).len(), 1);
+    }
+
+    #[test]
+    fn endpoint_keys_match_the_tree() {
+        assert_eq!(endpoint_key("https://example.com:8443/a/b/?q=1#f").as_deref(), Some("example.com/a/b?q=1#f"));
+        assert_eq!(endpoint_key("http://example.com/?q").as_deref(), Some("example.com/?q"));
+        assert_eq!(endpoint_key("http://example.com").as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn timestamps_format_as_utc() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951_782_400_000), "2000-02-29 00:00:00");
+        assert_eq!(format_date(1_700_000_000_000), "2023-11-14");
+    }
+}
@@ -0,0 +1,441 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use url::Url;
+
+use crate::file_processing::{self, NodeValue, Source};
+use crate::network::{self, NetworkConfig};
+use crate::response_store;
+
+const ROBOTS_TREE: &str = "robots";
+
+pub const ROBOTS_BLOCKED_TAG: &str = "robots-blocked";
+
+#[derive(Clone)]
+struct Rule {
+    allow: bool,
+    path: String,
+}
+
+impl Rule {
+    /// `*` matches any run of characters and a trailing `$` anchors the
+    /// pattern to the end of the path.
+    fn matches(&self, path: &str) -> bool {
+        let (pattern, anchored) = match self.path.strip_suffix('$') {
+            Some(pattern) => (pattern, true),
+            None => (self.path.as_str(), false),
+        };
+        let mut parts = pattern.split('*');
+        let first = parts.next().unwrap_or("");
+        let Some(mut rest) = path.strip_prefix(first) else {
+            return false;
+        };
+        let parts: Vec<&str> = parts.collect();
+        for (index, part) in parts.iter().enumerate() {
+            let is_last = index + 1 == parts.len();
+            if is_last && anchored {
+                return rest.ends_with(part);
+            }
+            match rest.find(part) {
+                Some(position) => rest = &rest[position + part.len()..],
+                None => return false,
+            }
+        }
+        !anchored || rest.is_empty()
+    }
+}
+
+/// One `User-agent` group of a robots.txt.
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<f64>,
+}
+
+/// A parsed robots.txt: its groups and the `Sitemap` lines, which apply to
+/// every user agent.
+#[derive(Default)]
+pub struct RobotsFile {
+    groups: Vec<Group>,
+    pub sitemaps: Vec<String>,
+}
+
+impl RobotsFile {
+    pub fn parse(text: &str) -> Self {
+        let mut file = RobotsFile::default();
+        let mut in_rules = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((field, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (field, value) = (field.trim().to_lowercase(), value.trim());
+            match field.as_str() {
+                "user-agent" => {
+                    if in_rules || file.groups.is_empty() {
+                        file.groups.push(Group {
+                            agents: Vec::new(),
+                            rules: Vec::new(),
+                            crawl_delay: None,
+                        });
+                        in_rules = false;
+                    }
+                    // An empty value still opens a group, which then
+                    // applies to no one.
+                    if let Some(group) = file.groups.last_mut()
+                        && !value.is_empty()
+                    {
+                        group.agents.push(value.to_lowercase());
+                    }
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty Disallow allows everything.
+                    if value.is_empty() {
+                        continue;
+                    }
+                    if let Some(group) = file.groups.last_mut() {
+                        group.rules.push(Rule {
+                            allow: field == "allow",
+                            path: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    if let Some(group) = file.groups.last_mut() {
+                        group.crawl_delay = value.parse().ok().filter(|delay: &f64| delay.is_finite());
+                    }
+                }
+                "sitemap" if !value.is_empty() && !file.sitemaps.iter().any(|known| known == value) => {
+                    file.sitemaps.push(value.to_string());
+                }
+                _ => {}
+            }
+        }
+        file
+    }
+
+    /// The rules for `user_agent`: those of the groups naming its product
+    /// token (`sitemapper` in `Sitemapper/1.0`), or of the `*` group when no
+    /// group names it.
+    pub fn for_agent(&self, user_agent: &str) -> Robots {
+        let token = product_token(user_agent);
+        let names_us = |agents: &[String]| {
+            agents
+                .iter()
+                .any(|a| a != "*" && !token.is_empty() && product_token(a) == token)
+        };
+        let named = self.groups.iter().any(|group| names_us(&group.agents));
+        let groups: Vec<&Group> = self
+            .groups
+            .iter()
+            .filter(|group| {
+                if named {
+                    names_us(&group.agents)
+                } else {
+                    group.agents.iter().any(|a| a == "*")
+                }
+            })
+            .collect();
+        Robots {
+            rules: groups.iter().flat_map(|group| group.rules.iter().cloned()).collect(),
+            crawl_delay: groups.iter().find_map(|group| group.crawl_delay),
+        }
+    }
+
+    /// The user agents of each group with its rule count, for display.
+    pub fn group_summaries(&self) -> Vec<(String, usize)> {
+        self.groups
+            .iter()
+            .map(|group| (group.agents.join(", "), group.rules.len()))
+            .collect()
+    }
+
+    /// The paths any group disallows, cut before the first wildcard so they
+    /// can be requested. The site root is left out.
+    pub fn disallowed_paths(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.groups
+            .iter()
+            .flat_map(|group| group.rules.iter())
+            .filter(|rule| !rule.allow)
+            .map(|rule| {
+                let path = rule.path.split(['*', '$']).next().unwrap_or("");
+                if path.len() < rule.path.len() {
+                    // Keep whole segments only: `/private*.html` becomes `/`.
+                    path[..path.rfind('/').map_or(0, |slash| slash + 1)].to_string()
+                } else {
+                    path.to_string()
+                }
+            })
+            .filter(|path| path.starts_with('/') && path != "/")
+            .filter(|path| seen.insert(path.clone()))
+            .collect()
+    }
+}
+
+fn product_token(user_agent: &str) -> String {
+    user_agent.split('/').next().unwrap_or("").trim().to_lowercase()
+}
+
+/// The Allow/Disallow rules of a robots.txt that apply to one user agent.
+#[derive(Default)]
+pub struct Robots {
+    rules: Vec<Rule>,
+    pub crawl_delay: Option<f64>,
+}
+
+impl Robots {
+    /// Keeps the rules of the groups naming `user_agent`, or of the `*` group
+    /// when no group names it.
+    pub fn parse(text: &str, user_agent: &str) -> Self {
+        RobotsFile::parse(text).for_agent(user_agent)
+    }
+
+    /// Whether `path` may be fetched: the longest matching rule wins and
+    /// Allow wins ties.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(path))
+            .max_by_key(|rule| (rule.path.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+pub fn robots_url(url: &Url) -> Option<Url> {
+    url.join("/robots.txt").ok()
+}
+
+/// The part of `url` robots.txt rules are matched against.
+pub fn request_path(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+/// The outcome of checking one host's robots.txt.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RobotsReport {
+    pub host: String,
+    pub url: String,
+    pub status: Option<u16>,
+    pub user_agent: String,
+    /// User agents of each group with its rule count.
+    pub groups: Vec<(String, usize)>,
+    pub crawl_delay: Option<f64>,
+    pub sitemaps: Vec<String>,
+    pub disallowed: Vec<String>,
+    pub new_endpoints: usize,
+    /// Tree keys of the endpoints `user_agent` may not fetch.
+    pub blocked: Vec<String>,
+}
+
+pub enum RobotsProgress {
+    Checked(Box<RobotsReport>),
+    Finished(usize),
+    Errored(String),
+}
+
+/// Endpoint keys under `host`, with their schemes.
+fn host_endpoints(db: &sled::Db, host: &str) -> Vec<(String, NodeValue)> {
+    let prefix = format!("{}/", host);
+    db.iter()
+        .flatten()
+        .filter_map(|(key, value)| {
+            let key = String::from_utf8(key.to_vec()).ok()?;
+            if key != host && !key.starts_with(&prefix) {
+                return None;
+            }
+            let node: NodeValue = serde_json::from_slice(&value).ok()?;
+            node.is_endpoint.then_some((key, node))
+        })
+        .collect()
+}
+
+fn check_host(db: &sled::Db, config: &NetworkConfig, root: &Url, user_agent: &str) -> Result<RobotsReport, String> {
+    let host = root.host_str().ok_or_else(|| format!("No host in {}", root))?.to_string();
+    let robots_url = robots_url(root).ok_or_else(|| format!("Invalid URL: {}", root))?;
+    let (response, body) = network::fetch(config, robots_url.as_str());
+    let status = response.status;
+    if status.is_none() {
+        return Err(format!("Failed to fetch {}", robots_url));
+    }
+    let _ = response_store::record(db, response, &body);
+
+    // Anything but a 200 means there are no rules to follow.
+    let file = match status {
+        Some(200) => RobotsFile::parse(&String::from_utf8_lossy(&body)),
+        _ => RobotsFile::default(),
+    };
+    let mut discovered: Vec<Url> = file.sitemaps.iter().filter_map(|url| Url::parse(url).ok()).collect();
+    let disallowed = file.disallowed_paths();
+    discovered.extend(disallowed.iter().filter_map(|path| root.join(path).ok()));
+    let new_endpoints = file_processing::record_urls(db, &discovered, Source::Robots).map_err(|e| e.to_string())?;
+
+    let robots = file.for_agent(user_agent);
+    let mut blocked = Vec::new();
+    for (key, node) in host_endpoints(db, &host) {
+        let scheme = node.scheme.unwrap_or_else(|| root.scheme().to_string());
+        let Ok(url) = Url::parse(&format!("{}://{}", scheme, key)) else {
+            continue;
+        };
+        let is_blocked = !robots.is_allowed(&request_path(&url));
+        if is_blocked != node.tags.contains(ROBOTS_BLOCKED_TAG) {
+            file_processing::set_tag(db, &key, ROBOTS_BLOCKED_TAG, is_blocked).map_err(|e| e.to_string())?;
+        }
+        if is_blocked {
+            blocked.push(key);
+        }
+    }
+    blocked.sort();
+
+    let report = RobotsReport {
+        host: host.clone(),
+        url: robots_url.to_string(),
+        status,
+        user_agent: user_agent.to_string(),
+        groups: file.group_summaries(),
+        crawl_delay: robots.crawl_delay,
+        sitemaps: file.sitemaps,
+        disallowed,
+        new_endpoints,
+        blocked,
+    };
+    let tree = db.open_tree(ROBOTS_TREE).map_err(|e| e.to_string())?;
+    let encoded = serde_json::to_vec(&report).map_err(|e| e.to_string())?;
+    tree.insert(host.as_bytes(), encoded).map_err(|e| e.to_string())?;
+    Ok(report)
+}
+
+/// The stored report of every checked host, sorted by host.
+pub fn all(db: &sled::Db) -> Vec<RobotsReport> {
+    let Ok(tree) = db.open_tree(ROBOTS_TREE) else {
+        return Vec::new();
+    };
+    tree.iter()
+        .values()
+        .flatten()
+        .filter_map(|value| serde_json::from_slice(&value).ok())
+        .collect()
+}
+
+/// Fetches the robots.txt of each host root, adds its Sitemap URLs and
+/// disallowed paths to the tree and flags the host's endpoints that
+/// `user_agent` may not fetch.
+pub fn spawn_robots_thread(
+    db: Arc<sled::Db>,
+    config: NetworkConfig,
+    roots: Vec<Url>,
+    user_agent: String,
+) -> Receiver<RobotsProgress> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut total_new = 0;
+        for root in roots {
+            match check_host(&db, &config, &root, &user_agent) {
+                Ok(report) => {
+                    total_new += report.new_endpoints;
+                    let _ = sender.send(RobotsProgress::Checked(Box::new(report)));
+                }
+                Err(e) => {
+                    let _ = sender.send(RobotsProgress::Errored(e));
+                }
+            }
+        }
+        let _ = db.flush();
+        let _ = sender.send(RobotsProgress::Finished(total_new));
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "\
+# Example
+User-agent: *
+Disallow: /private/
+Allow: /private/public
+Disallow: /*.pdf$
+Disallow: /search*q=
+Crawl-delay: 2
+
+User-agent: Sitemapper
+User-agent: OtherBot
+Disallow: /
+Allow: /open
+
+Sitemap: https://example.com/sitemap.xml
+Sitemap: https://example.com/sitemap.xml
+";
+
+    #[test]
+    fn wildcards_and_anchors() {
+        let rule = |path: &str| Rule {
+            allow: false,
+            path: path.to_string(),
+        };
+        assert!(rule("/private/").matches("/private/a"));
+        assert!(!rule("/private/").matches("/privat"));
+        assert!(rule("/*.pdf$").matches("/docs/a.pdf"));
+        assert!(!rule("/*.pdf$").matches("/docs/a.pdf?x=1"));
+        assert!(rule("/search*q=").matches("/search?page=1&q=x"));
+        assert!(rule("/a$").matches("/a"));
+        assert!(!rule("/a$").matches("/ab"));
+    }
+
+    #[test]
+    fn longest_rule_wins_and_allow_breaks_ties() {
+        let robots = Robots::parse(ROBOTS, "curl/8.0");
+        assert!(!robots.is_allowed("/private/secret"));
+        assert!(robots.is_allowed("/private/public/page"));
+        assert!(!robots.is_allowed("/files/report.pdf"));
+        assert!(robots.is_allowed("/"));
+        assert_eq!(robots.crawl_delay, Some(2.0));
+
+        let tie = Robots::parse("User-agent: *\nDisallow: /page\nAllow: /page\n", "curl/8.0");
+        assert!(tie.is_allowed("/page"));
+    }
+
+    #[test]
+    fn named_groups_replace_the_wildcard_group() {
+        let robots = Robots::parse(ROBOTS, "Sitemapper/1.0");
+        assert!(!robots.is_allowed("/anything"));
+        assert!(robots.is_allowed("/open/door"));
+        assert_eq!(robots.crawl_delay, None);
+    }
+
+    #[test]
+    fn agents_match_on_the_product_token() {
+        let text = "User-agent:\nDisallow: /\n\nUser-agent: bot\nDisallow: /bot\n\nUser-agent: *\nDisallow: /all\n";
+        let robots = Robots::parse(text, "Sitemapper-robot/1.0");
+        assert!(robots.is_allowed("/"));
+        assert!(robots.is_allowed("/bot"));
+        assert!(!robots.is_allowed("/all"));
+
+        let robots = Robots::parse(text, "Bot/2.1");
+        assert!(!robots.is_allowed("/bot"));
+        assert!(robots.is_allowed("/all"));
+
+        let robots = Robots::parse(ROBOTS, "Mozilla/5.0 (compatible; Sitemapper)");
+        assert!(robots.is_allowed("/anything"));
+    }
+
+    #[test]
+    fn parses_groups_sitemaps_and_disallowed_paths() {
+        let file = RobotsFile::parse(ROBOTS);
+        assert_eq!(
+            file.group_summaries(),
+            [("*".to_string(), 4), ("sitemapper, otherbot".to_string(), 2)]
+        );
+        assert_eq!(file.sitemaps, ["https://example.com/sitemap.xml"]);
+        assert_eq!(file.disallowed_paths(), ["/private/"]);
+    }
+}
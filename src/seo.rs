@@ -0,0 +1,567 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use url::Url;
+
+use crate::links::{self, ContentKind};
+use crate::response_store::{self, StoredResponse};
+
+const AUDIT_TREE: &str = "audit";
+
+const TITLE_LENGTH: (usize, usize) = (10, 60);
+const DESCRIPTION_LENGTH: (usize, usize) = (50, 160);
+/// HTML documents heavier than this are flagged.
+const MAX_PAGE_BYTES: u64 = 1024 * 1024;
+
+lazy_static::lazy_static! {
+    static ref TITLE: Regex = Regex::new(r"(?is)<title\b[^>]*>(.*?)</title>").unwrap();
+    static ref META_TAG: Regex = Regex::new(r"(?is)<meta\b([^>]*)>").unwrap();
+    static ref LINK_TAG: Regex = Regex::new(r"(?is)<link\b([^>]*)>").unwrap();
+    static ref IMG_TAG: Regex = Regex::new(r"(?is)<img\b([^>]*)>").unwrap();
+    static ref H1_TAG: Regex = Regex::new(r"(?i)<h1[\s>]").unwrap();
+    static ref SUBRESOURCE_TAG: Regex =
+        Regex::new(r"(?is)<(script|img|iframe|link|source|video|audio|embed|object)\b([^>]*)>").unwrap();
+    static ref HREFLANG_CODE: Regex =
+        Regex::new(r"(?i)^(?:x-default|[a-z]{2,3}(?:-[a-z]{4})?(?:-(?:[a-z]{2}|\d{3}))?)$").unwrap();
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Notice,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 3] = [Severity::Error, Severity::Warning, Severity::Notice];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+            Severity::Notice => "Notice",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    pub check: String,
+    pub detail: String,
+}
+
+/// What the audit saw on one page, kept for the cross-page checks.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct PageAudit {
+    pub key: String,
+    pub url: String,
+    pub host: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub canonical: Option<String>,
+    /// `(language, href)` of every alternate link.
+    pub hreflang: Vec<(String, String)>,
+    pub issues: Vec<Issue>,
+}
+
+impl PageAudit {
+    fn issue(&mut self, severity: Severity, check: &str, detail: impl Into<String>) {
+        self.issues.push(Issue {
+            severity,
+            check: check.to_string(),
+            detail: detail.into(),
+        });
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues.iter().filter(|issue| issue.severity == severity).count()
+    }
+}
+
+#[derive(Clone)]
+pub struct HostSummary {
+    pub host: String,
+    pub pages: usize,
+    /// Issues per severity, in `Severity::ALL` order.
+    pub counts: [usize; 3],
+}
+
+pub enum AuditProgress {
+    Advanced { completed: usize, total: usize },
+    Finished(usize),
+    Errored(String),
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Runs the checks that need only this page.
+pub fn audit_page(response: &StoredResponse, body: &str) -> Option<PageAudit> {
+    let url = Url::parse(&response.url).ok()?;
+    let mut page = PageAudit {
+        key: response_store::endpoint_key(&response.url)?,
+        url: response.url.clone(),
+        host: url.host_str()?.to_string(),
+        ..Default::default()
+    };
+
+    let titles: Vec<String> = TITLE
+        .captures_iter(body)
+        .map(|capture| collapse_whitespace(&links::decode_entities(&capture[1])))
+        .collect();
+    match titles.first() {
+        None => page.issue(Severity::Error, "Missing title", "No <title> element"),
+        Some(title) if title.is_empty() => page.issue(Severity::Error, "Missing title", "The <title> element is empty"),
+        Some(title) => {
+            let length = title.chars().count();
+            if length < TITLE_LENGTH.0 {
+                page.issue(Severity::Warning, "Title too short", format!("{} characters", length));
+            } else if length > TITLE_LENGTH.1 {
+                page.issue(Severity::Warning, "Title too long", format!("{} characters", length));
+            }
+            page.title = Some(title.clone());
+        }
+    }
+    if titles.len() > 1 {
+        page.issue(Severity::Warning, "Multiple titles", format!("{} <title> elements", titles.len()));
+    }
+
+    let metas: Vec<HashMap<String, String>> = META_TAG
+        .captures_iter(body)
+        .map(|c| links::tag_attributes(&c[1]))
+        .collect();
+    let meta_content = |name: &str| {
+        metas
+            .iter()
+            .filter(|meta| meta.get("name").is_some_and(|n| n.eq_ignore_ascii_case(name)))
+            .filter_map(|meta| meta.get("content").map(|content| collapse_whitespace(content)))
+            .collect::<Vec<_>>()
+    };
+
+    let descriptions = meta_content("description");
+    match descriptions.first() {
+        None => page.issue(Severity::Warning, "Missing meta description", "No <meta name=\"description\">"),
+        Some(description) if description.is_empty() => {
+            page.issue(Severity::Warning, "Missing meta description", "The description is empty")
+        }
+        Some(description) => {
+            let length = description.chars().count();
+            if length < DESCRIPTION_LENGTH.0 {
+                page.issue(Severity::Notice, "Meta description too short", format!("{} characters", length));
+            } else if length > DESCRIPTION_LENGTH.1 {
+                page.issue(Severity::Warning, "Meta description too long", format!("{} characters", length));
+            }
+            page.description = Some(description.clone());
+        }
+    }
+    if descriptions.len() > 1 {
+        page.issue(
+            Severity::Warning,
+            "Multiple meta descriptions",
+            format!("{} description tags", descriptions.len()),
+        );
+    }
+
+    let mut directives: Vec<String> = meta_content("robots");
+    directives.extend(meta_content("googlebot"));
+    directives.extend(
+        response
+            .headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("x-robots-tag"))
+            .map(|(_, value)| value.clone()),
+    );
+    let directives = directives.join(",").to_lowercase();
+    if directives.contains("noindex") || directives.contains("none") {
+        page.issue(Severity::Warning, "Noindex", format!("Robots directives: {}", directives));
+    } else if directives.contains("nofollow") {
+        page.issue(Severity::Notice, "Nofollow", format!("Robots directives: {}", directives));
+    }
+
+    let link_tags: Vec<HashMap<String, String>> = LINK_TAG
+        .captures_iter(body)
+        .map(|c| links::tag_attributes(&c[1]))
+        .collect();
+    let has_rel = |link: &HashMap<String, String>, rel: &str| {
+        link.get("rel")
+            .is_some_and(|value| value.split_whitespace().any(|r| r.eq_ignore_ascii_case(rel)))
+    };
+
+    let canonicals: Vec<&String> = link_tags
+        .iter()
+        .filter(|link| has_rel(link, "canonical"))
+        .filter_map(|link| link.get("href"))
+        .collect();
+    match canonicals.as_slice() {
+        [] => page.issue(Severity::Notice, "Missing canonical", "No <link rel=\"canonical\">"),
+        [href] => match url.join(href) {
+            Ok(canonical) => {
+                if canonical.host_str() != url.host_str() {
+                    page.issue(Severity::Warning, "Cross-host canonical", canonical.to_string());
+                } else if canonical != url {
+                    page.issue(Severity::Notice, "Canonicalised elsewhere", canonical.to_string());
+                }
+                page.canonical = Some(canonical.to_string());
+            }
+            Err(_) => page.issue(Severity::Error, "Invalid canonical", href.to_string()),
+        },
+        _ => page.issue(
+            Severity::Error,
+            "Multiple canonicals",
+            format!("{} canonical links", canonicals.len()),
+        ),
+    }
+
+    let h1_count = H1_TAG.find_iter(body).count();
+    if h1_count == 0 {
+        page.issue(Severity::Warning, "Missing h1", "No <h1> element");
+    } else if h1_count > 1 {
+        page.issue(Severity::Notice, "Multiple h1", format!("{} <h1> elements", h1_count));
+    }
+
+    audit_hreflang(&mut page, &url, &link_tags, has_rel);
+
+    let missing_alt = IMG_TAG
+        .captures_iter(body)
+        .filter(|capture| !links::tag_attributes(&capture[1]).contains_key("alt"))
+        .count();
+    if missing_alt > 0 {
+        page.issue(Severity::Warning, "Missing alt text", format!("{} images without alt", missing_alt));
+    }
+
+    if url.scheme() == "https" {
+        let insecure: Vec<String> = SUBRESOURCE_TAG
+            .captures_iter(body)
+            .filter_map(|capture| {
+                let attributes = links::tag_attributes(&capture[2]);
+                // Plain links to http pages are not loaded, stylesheets are.
+                let is_resource = !capture[1].eq_ignore_ascii_case("link")
+                    || ["stylesheet", "icon", "preload", "modulepreload"]
+                        .iter()
+                        .any(|rel| has_rel(&attributes, rel));
+                let reference = attributes.get("src").or_else(|| attributes.get("data")).or(
+                    is_resource.then(|| attributes.get("href")).flatten(),
+                )?;
+                (is_resource && reference.to_lowercase().starts_with("http://")).then(|| reference.clone())
+            })
+            .collect();
+        if let Some(first) = insecure.first() {
+            page.issue(
+                Severity::Error,
+                "Mixed content",
+                format!("{} http:// resources, first: {}", insecure.len(), first),
+            );
+        }
+    }
+
+    if response.body_len > MAX_PAGE_BYTES {
+        page.issue(
+            Severity::Warning,
+            "Heavy page",
+            format!("{} KB of HTML", response.body_len / 1024),
+        );
+    }
+
+    Some(page)
+}
+
+fn audit_hreflang(
+    page: &mut PageAudit,
+    url: &Url,
+    link_tags: &[HashMap<String, String>],
+    has_rel: impl Fn(&HashMap<String, String>, &str) -> bool,
+) {
+    for link in link_tags.iter().filter(|link| has_rel(link, "alternate")) {
+        let (Some(language), Some(href)) = (link.get("hreflang"), link.get("href")) else {
+            continue;
+        };
+        match url.join(href) {
+            Ok(target) => page.hreflang.push((language.to_lowercase(), target.to_string())),
+            Err(_) => page.issue(Severity::Error, "Invalid hreflang URL", href.to_string()),
+        }
+    }
+    if page.hreflang.is_empty() {
+        return;
+    }
+
+    let mut by_language: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (language, href) in &page.hreflang {
+        by_language.entry(language.as_str()).or_default().push(href.as_str());
+    }
+    let mut issues = Vec::new();
+    for (language, hrefs) in &by_language {
+        if !HREFLANG_CODE.is_match(language) {
+            issues.push((Severity::Error, "Invalid hreflang code", language.to_string()));
+        }
+        if hrefs.len() > 1 {
+            issues.push((
+                Severity::Error,
+                "Conflicting hreflang",
+                format!("{} points to {} URLs", language, hrefs.len()),
+            ));
+        }
+    }
+    let own = [Some(url.as_str()), page.canonical.as_deref()];
+    if !page.hreflang.iter().any(|(_, href)| own.contains(&Some(href.as_str()))) {
+        issues.push((Severity::Warning, "Missing hreflang self-reference", String::new()));
+    }
+    if !by_language.contains_key("x-default") {
+        issues.push((Severity::Notice, "Missing x-default", String::new()));
+    }
+    for (severity, check, detail) in issues {
+        page.issue(severity, check, detail);
+    }
+}
+
+/// Adds the checks that compare pages: titles and descriptions shared
+/// within a host, and hreflang alternates that do not link back.
+pub fn audit_site(pages: &mut [PageAudit]) {
+    let mut titles: HashMap<(String, String), usize> = HashMap::new();
+    let mut descriptions: HashMap<(String, String), usize> = HashMap::new();
+    for page in pages.iter() {
+        if let Some(title) = &page.title {
+            *titles.entry((page.host.clone(), title.to_lowercase())).or_default() += 1;
+        }
+        if let Some(description) = &page.description {
+            *descriptions.entry((page.host.clone(), description.to_lowercase())).or_default() += 1;
+        }
+    }
+    let alternates: HashMap<String, Vec<String>> = pages
+        .iter()
+        .map(|page| {
+            let hrefs = page.hreflang.iter().map(|(_, href)| href.clone()).collect();
+            (page.url.clone(), hrefs)
+        })
+        .collect();
+
+    for page in pages.iter_mut() {
+        let shared = |counts: &HashMap<(String, String), usize>, text: &Option<String>| {
+            text.as_ref()
+                .and_then(|text| counts.get(&(page.host.clone(), text.to_lowercase())))
+                .copied()
+                .filter(|count| *count > 1)
+        };
+        let shared_title = shared(&titles, &page.title);
+        let shared_description = shared(&descriptions, &page.description);
+        if let Some(count) = shared_title {
+            page.issue(Severity::Warning, "Duplicate title", format!("Shared by {} pages", count));
+        }
+        if let Some(count) = shared_description {
+            page.issue(Severity::Warning, "Duplicate meta description", format!("Shared by {} pages", count));
+        }
+
+        let missing_return: Vec<String> = page
+            .hreflang
+            .iter()
+            .filter(|(_, href)| *href != page.url)
+            .filter(|(_, href)| {
+                alternates
+                    .get(href)
+                    .is_some_and(|back| !back.contains(&page.url))
+            })
+            .map(|(language, href)| format!("{} ({})", href, language))
+            .collect();
+        for target in missing_return {
+            page.issue(Severity::Warning, "Missing hreflang return link", target);
+        }
+    }
+}
+
+/// Audits the newest HTML response of every endpoint and replaces the
+/// stored results.
+pub fn spawn_audit_thread(db: Arc<sled::Db>, stop: Arc<AtomicBool>) -> Receiver<AuditProgress> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let responses: Vec<StoredResponse> = response_store::latest_all(&db)
+            .into_iter()
+            .filter(|response| response.status == Some(200))
+            .filter(|response| {
+                Url::parse(&response.url)
+                    .is_ok_and(|url| links::content_kind(&response.content_type, &url) == ContentKind::Html)
+            })
+            .collect();
+        let total = responses.len();
+
+        let mut pages = Vec::new();
+        for (index, response) in responses.iter().enumerate() {
+            if stop.load(Ordering::SeqCst) {
+                let _ = sender.send(AuditProgress::Errored("Audit stopped".to_string()));
+                return;
+            }
+            let body = response_store::body(&db, &response.body_hash).unwrap_or_default();
+            if let Some(page) = audit_page(response, &String::from_utf8_lossy(&body)) {
+                pages.push(page);
+            }
+            let _ = sender.send(AuditProgress::Advanced {
+                completed: index + 1,
+                total,
+            });
+        }
+        audit_site(&mut pages);
+
+        let result = db.open_tree(AUDIT_TREE).and_then(|tree| {
+            tree.clear()?;
+            for page in &pages {
+                if let Ok(encoded) = serde_json::to_vec(page) {
+                    tree.insert(page.key.as_bytes(), encoded)?;
+                }
+            }
+            tree.flush()
+        });
+        let _ = sender.send(match result {
+            Ok(_) => AuditProgress::Finished(pages.len()),
+            Err(e) => AuditProgress::Errored(format!("Failed to store audit: {}", e)),
+        });
+    });
+
+    receiver
+}
+
+/// The stored audit of every page, sorted by key.
+pub fn all(db: &sled::Db) -> Vec<PageAudit> {
+    let Ok(tree) = db.open_tree(AUDIT_TREE) else {
+        return Vec::new();
+    };
+    tree.iter()
+        .values()
+        .flatten()
+        .filter_map(|value| serde_json::from_slice(&value).ok())
+        .collect()
+}
+
+pub fn summarize(pages: &[PageAudit]) -> Vec<HostSummary> {
+    let mut hosts: BTreeMap<&str, HostSummary> = BTreeMap::new();
+    for page in pages {
+        let summary = hosts.entry(page.host.as_str()).or_insert_with(|| HostSummary {
+            host: page.host.clone(),
+            pages: 0,
+            counts: [0; 3],
+        });
+        summary.pages += 1;
+        for (count, severity) in summary.counts.iter_mut().zip(Severity::ALL) {
+            *count += page.count(severity);
+        }
+    }
+    hosts.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audit(url: &str, body: &str) -> PageAudit {
+        let response = StoredResponse {
+            url: url.to_string(),
+            status: Some(200),
+            content_type: "text/html".to_string(),
+            body_len: body.len() as u64,
+            ..StoredResponse::default()
+        };
+        audit_page(&response, body).unwrap()
+    }
+
+    fn checks(page: &PageAudit) -> Vec<&str> {
+        page.issues.iter().map(|issue| issue.check.as_str()).collect()
+    }
+
+    #[test]
+    fn flags_title_and_description_outside_the_bounds() {
+        let short = audit(
+            "https://example.com/",
+            r#"<title>Short</title><meta name="description" content="Too short">"#,
+        );
+        assert!(checks(&short).contains(&"Title too short"));
+        assert!(checks(&short).contains(&"Meta description too short"));
+
+        let long = audit(
+            "https://example.com/",
+            &format!(
+                r#"<title>{}</title><meta name="description" content="{}">"#,
+                "t".repeat(TITLE_LENGTH.1 + 1),
+                "d".repeat(DESCRIPTION_LENGTH.1 + 1)
+            ),
+        );
+        assert!(checks(&long).contains(&"Title too long"));
+        assert!(checks(&long).contains(&"Meta description too long"));
+
+        let fitting = audit(
+            "https://example.com/",
+            &format!(
+                r#"<title>{}</title><meta name="description" content="{}">"#,
+                "t".repeat(TITLE_LENGTH.1),
+                "d".repeat(DESCRIPTION_LENGTH.0)
+            ),
+        );
+        assert!(!checks(&fitting).iter().any(|check| check.starts_with("Title too")
+            || check.starts_with("Meta description too")));
+    }
+
+    #[test]
+    fn flags_multiple_canonicals() {
+        let page = audit(
+            "https://example.com/a",
+            r#"<link rel="canonical" href="/a"><link rel="canonical" href="/b">"#,
+        );
+        assert!(checks(&page).contains(&"Multiple canonicals"));
+        assert_eq!(page.canonical, None);
+    }
+
+    #[test]
+    fn mixed_content_counts_only_loaded_resources() {
+        let page = audit(
+            "https://example.com/",
+            r#"<a href="http://other.com/">link</a>
+               <link rel="alternate" href="http://example.com/feed">
+               <script src="http://cdn.example.com/app.js"></script>
+               <link rel="stylesheet" href="http://cdn.example.com/site.css">"#,
+        );
+        let mixed: Vec<&Issue> = page.issues.iter().filter(|issue| issue.check == "Mixed content").collect();
+        assert_eq!(mixed.len(), 1);
+        assert!(mixed[0].detail.starts_with("2 http:// resources"));
+
+        let plain = audit("http://example.com/", r#"<script src="http://cdn.example.com/app.js"></script>"#);
+        assert!(!checks(&plain).contains(&"Mixed content"));
+    }
+
+    #[test]
+    fn flags_missing_hreflang_return_links() {
+        let mut pages = vec![
+            audit(
+                "https://example.com/en",
+                r#"<link rel="alternate" hreflang="en" href="/en">
+                   <link rel="alternate" hreflang="de" href="/de">"#,
+            ),
+            audit("https://example.com/de", r#"<link rel="alternate" hreflang="de" href="/de">"#),
+        ];
+        audit_site(&mut pages);
+        let missing: Vec<&Issue> = pages[0]
+            .issues
+            .iter()
+            .filter(|issue| issue.check == "Missing hreflang return link")
+            .collect();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].detail, "https://example.com/de (de)");
+        assert!(!checks(&pages[1]).contains(&"Missing hreflang return link"));
+    }
+
+    #[test]
+    fn duplicate_titles_are_counted_per_host() {
+        let body = "<title>Welcome to the shop</title>";
+        let mut pages = vec![
+            audit("https://example.com/a", body),
+            audit("https://example.com/b", "<title>welcome to the SHOP</title>"),
+            audit("https://other.com/a", body),
+        ];
+        audit_site(&mut pages);
+        assert!(checks(&pages[0]).contains(&"Duplicate title"));
+        assert!(checks(&pages[1]).contains(&"Duplicate title"));
+        assert!(!checks(&pages[2]).contains(&"Duplicate title"));
+    }
+}
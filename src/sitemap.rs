@@ -0,0 +1,693 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use url::Url;
+
+use crate::calibration;
+use crate::file_processing::{NodeValue, IMAGE_EXTENSIONS};
+use crate::file_saver::SaveProgress;
+use crate::links::{self, ContentKind};
+use crate::response_store;
+use crate::robots;
+use crate::seo;
+
+/// Limits of a single sitemap file, from the sitemaps.org protocol.
+pub const MAX_URLS: usize = 50_000;
+pub const MAX_BYTES: usize = 50 * 1024 * 1024;
+/// Google reads at most this many images per page.
+const MAX_IMAGES_PER_URL: usize = 1000;
+const MAX_VIDEO_DESCRIPTION_CHARS: usize = 2048;
+
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+const URLSET_CLOSE: &str = "</urlset>\n";
+
+/// Endpoints with these extensions are not pages and are left out when
+/// only pages are wanted.
+const ASSET_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "ico", "bmp", "svg", "avif", "css", "js", "mjs", "map", "json",
+    "woff", "woff2", "ttf", "eot", "otf", "mp3", "mp4", "webm", "avi", "mov", "m4v", "ogv", "zip", "gz",
+    "tar", "rar", "7z", "exe", "dmg", "iso",
+];
+
+/// ISO 639-1 language codes, for spotting locale path prefixes.
+const LANGUAGE_CODES: &str = "aa ab ae af ak am an ar as av ay az ba be bg bh bi bm bn bo br bs ca ce ch co cr \
+    cs cu cv cy da de dv dz ee el en eo es et eu fa ff fi fj fo fr fy ga gd gl gn gu gv ha he hi ho hr ht hu hy \
+    hz ia id ie ig ii ik io is it iu ja jv ka kg ki kj kk kl km kn ko kr ks ku kv kw ky la lb lg li ln lo lt lu \
+    lv mg mh mi mk ml mn mr ms mt my na nb nd ne ng nl nn no nr nv ny oc oj om or os pa pi pl ps pt qu rm rn ro \
+    ru rw sa sc sd se sg si sk sl sm sn so sq sr ss st su sv sw ta te tg th ti tk tl tn to tr ts tt tw ty ug uk \
+    ur uz ve vi vo wa wo xh yi yo za zh zu";
+
+lazy_static::lazy_static! {
+    static ref VIDEO_TAG: Regex = Regex::new(r"(?is)<video\b([^>]*)>(.*?)</video>").unwrap();
+    static ref SOURCE_TAG: Regex = Regex::new(r"(?is)<source\b([^>]*)>").unwrap();
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ChangeFreq {
+    Always,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+    Never,
+}
+
+impl ChangeFreq {
+    pub const ALL: [ChangeFreq; 7] = [
+        ChangeFreq::Always,
+        ChangeFreq::Hourly,
+        ChangeFreq::Daily,
+        ChangeFreq::Weekly,
+        ChangeFreq::Monthly,
+        ChangeFreq::Yearly,
+        ChangeFreq::Never,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChangeFreq::Always => "always",
+            ChangeFreq::Hourly => "hourly",
+            ChangeFreq::Daily => "daily",
+            ChangeFreq::Weekly => "weekly",
+            ChangeFreq::Monthly => "monthly",
+            ChangeFreq::Yearly => "yearly",
+            ChangeFreq::Never => "never",
+        }
+    }
+}
+
+/// How localised variants of a page are found for `xhtml:link` groups.
+#[derive(Clone, Copy, PartialEq)]
+pub enum HreflangMode {
+    Off,
+    /// From a leading locale segment such as `/de/` or `/en-gb/`.
+    Detect,
+    /// From `language = /prefix/` lines in `hreflang_mapping`.
+    Manual,
+}
+
+impl HreflangMode {
+    pub const ALL: [HreflangMode; 3] = [HreflangMode::Off, HreflangMode::Detect, HreflangMode::Manual];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HreflangMode::Off => "Off",
+            HreflangMode::Detect => "Detect from locale path prefixes",
+            HreflangMode::Manual => "Manual mapping",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SitemapOptions {
+    /// One folder per host is created here.
+    pub directory: PathBuf,
+    /// Date of the newest stored response, for endpoints that have one.
+    pub lastmod: bool,
+    pub changefreq: Option<ChangeFreq>,
+    pub priority: Option<f32>,
+    pub gzip: bool,
+    pub pages_only: bool,
+    /// Leaves out endpoints the last robots.txt check flagged as blocked.
+    pub exclude_disallowed: bool,
+    pub images: bool,
+    pub videos: bool,
+    pub hreflang: HreflangMode,
+    pub hreflang_mapping: String,
+}
+
+impl Default for SitemapOptions {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("sitemaps"),
+            lastmod: true,
+            changefreq: None,
+            priority: None,
+            gzip: false,
+            pages_only: true,
+            exclude_disallowed: true,
+            images: false,
+            videos: false,
+            hreflang: HreflangMode::Off,
+            hreflang_mapping: String::new(),
+        }
+    }
+}
+
+pub struct VideoEntry {
+    pub content_loc: String,
+    pub thumbnail_loc: String,
+    pub title: String,
+    pub description: String,
+}
+
+pub struct SitemapEntry {
+    pub key: String,
+    pub loc: String,
+    pub lastmod: Option<String>,
+    pub images: Vec<String>,
+    pub videos: Vec<VideoEntry>,
+    /// `(hreflang, href)` of every variant in the page's group, itself included.
+    pub alternates: Vec<(String, String)>,
+}
+
+impl SitemapEntry {
+    fn new(key: String, loc: String, lastmod: Option<String>) -> Self {
+        Self {
+            key,
+            loc,
+            lastmod,
+            images: Vec::new(),
+            videos: Vec::new(),
+            alternates: Vec::new(),
+        }
+    }
+}
+
+/// What goes into one host's sitemap.
+pub struct HostSitemap {
+    pub scheme: String,
+    pub entries: Vec<SitemapEntry>,
+    /// Image endpoints in the tree, attached to pages when images are wanted.
+    image_urls: Vec<String>,
+}
+
+/// The sitemap endpoints of every host, keyed by host. Probable soft-404s
+/// are left out, as are endpoints blocked by robots.txt when asked.
+pub fn collect_entries(db: &sled::Db, options: &SitemapOptions) -> BTreeMap<String, HostSitemap> {
+    let mut hosts: BTreeMap<String, HostSitemap> = BTreeMap::new();
+    for (key, value) in db.iter().flatten() {
+        let Ok(key) = std::str::from_utf8(&key) else {
+            continue;
+        };
+        let Ok(node) = serde_json::from_slice::<NodeValue>(&value) else {
+            continue;
+        };
+        if key == "__ROOT__" || !node.is_endpoint || node.tags.contains(calibration::SOFT_404_TAG) {
+            continue;
+        }
+        if options.exclude_disallowed && node.tags.contains(robots::ROBOTS_BLOCKED_TAG) {
+            continue;
+        }
+
+        let scheme = node.scheme.unwrap_or_else(|| "https".to_string());
+        let host = key.split('/').next().unwrap_or(key).to_string();
+        // Fragments never reach the server, so `page#a` and `page#b` are one URL.
+        let target = key.split_once('#').map_or(key, |(target, _)| target);
+        let loc = if target.contains('/') {
+            format!("{}://{}", scheme, target)
+        } else {
+            format!("{}://{}/", scheme, target)
+        };
+        let sitemap = hosts.entry(host).or_insert_with(|| HostSitemap {
+            scheme,
+            entries: Vec::new(),
+            image_urls: Vec::new(),
+        });
+        if has_extension(target, IMAGE_EXTENSIONS) {
+            sitemap.image_urls.push(loc.clone());
+        }
+        if options.pages_only && has_extension(target, ASSET_EXTENSIONS) {
+            continue;
+        }
+
+        let lastmod = if options.lastmod {
+            response_store::latest(db, key).map(|response| response_store::format_date(response.timestamp))
+        } else {
+            None
+        };
+        sitemap.entries.push(SitemapEntry::new(key.to_string(), loc, lastmod));
+    }
+
+    let mapping = parse_hreflang_mapping(&options.hreflang_mapping);
+    for (host, sitemap) in hosts.iter_mut() {
+        sitemap.entries.sort_by(|a, b| a.loc.cmp(&b.loc));
+        sitemap.entries.dedup_by(|a, b| a.loc == b.loc);
+        if options.images || options.videos {
+            add_media(db, host, sitemap, options);
+        }
+        if options.hreflang != HreflangMode::Off {
+            add_alternates(&mut sitemap.entries, options.hreflang, &mapping);
+        }
+    }
+    hosts
+}
+
+fn has_extension(key: &str, extensions: &[&str]) -> bool {
+    key.rsplit('/')
+        .next()
+        .map(|name| name.split(['?', '#']).next().unwrap_or(name))
+        .and_then(|name| name.rsplit_once('.'))
+        .is_some_and(|(_, extension)| extensions.contains(&extension.to_lowercase().as_str()))
+}
+
+/// Attaches the images and videos each page's stored HTML references.
+/// Images in the tree that no page references go on the host's root page.
+fn add_media(db: &sled::Db, host: &str, sitemap: &mut HostSitemap, options: &SitemapOptions) {
+    let mut referenced = HashSet::new();
+    for entry in sitemap.entries.iter_mut() {
+        let Some(response) = response_store::latest(db, &entry.key).filter(|r| r.status == Some(200)) else {
+            continue;
+        };
+        let Ok(page_url) = Url::parse(&entry.loc) else {
+            continue;
+        };
+        if links::content_kind(&response.content_type, &page_url) != ContentKind::Html {
+            continue;
+        }
+        let body = response_store::body(db, &response.body_hash).unwrap_or_default();
+        let body = String::from_utf8_lossy(&body);
+
+        if options.images {
+            entry.images = links::extract_links(&page_url, &response.content_type, &body)
+                .into_iter()
+                .map(|url| url.to_string())
+                .filter(|url| has_extension(url, IMAGE_EXTENSIONS))
+                .take(MAX_IMAGES_PER_URL)
+                .collect();
+            referenced.extend(entry.images.iter().cloned());
+        }
+        if options.videos {
+            entry.videos = page_videos(&page_url, &response, &body);
+        }
+    }
+
+    if !options.images {
+        return;
+    }
+    let unreferenced: Vec<String> = sitemap
+        .image_urls
+        .iter()
+        .filter(|url| !referenced.contains(*url))
+        .cloned()
+        .collect();
+    if unreferenced.is_empty() {
+        return;
+    }
+    let root = format!("{}://{}/", sitemap.scheme, host);
+    let index = match sitemap.entries.iter().position(|entry| entry.loc == root) {
+        Some(index) => index,
+        None => {
+            sitemap.entries.insert(0, SitemapEntry::new(host.to_string(), root, None));
+            0
+        }
+    };
+    let images = &mut sitemap.entries[index].images;
+    for url in unreferenced {
+        if images.len() >= MAX_IMAGES_PER_URL {
+            break;
+        }
+        if !images.contains(&url) {
+            images.push(url);
+        }
+    }
+}
+
+/// `<video>` elements with a poster; Google requires a thumbnail, so
+/// videos without one are skipped.
+fn page_videos(page_url: &Url, response: &response_store::StoredResponse, body: &str) -> Vec<VideoEntry> {
+    let mut videos = Vec::new();
+    let mut page_text = None;
+    for video in VIDEO_TAG.captures_iter(body) {
+        let attributes = links::tag_attributes(&video[1]);
+        let source = attributes.get("src").cloned().or_else(|| {
+            SOURCE_TAG
+                .captures_iter(&video[2])
+                .find_map(|source| links::tag_attributes(&source[1]).remove("src"))
+        });
+        let (Some(source), Some(poster)) = (source, attributes.get("poster")) else {
+            continue;
+        };
+        let (Ok(content_loc), Ok(thumbnail_loc)) = (page_url.join(&source), page_url.join(poster)) else {
+            continue;
+        };
+
+        let (title, description) = page_text.get_or_insert_with(|| {
+            seo::audit_page(response, body).map_or((None, None), |page| (page.title, page.description))
+        });
+        let title = title.clone().unwrap_or_else(|| {
+            content_loc
+                .path_segments()
+                .and_then(|mut segments| segments.next_back().map(String::from))
+                .unwrap_or_else(|| content_loc.to_string())
+        });
+        let description: String = description
+            .clone()
+            .unwrap_or_else(|| title.clone())
+            .chars()
+            .take(MAX_VIDEO_DESCRIPTION_CHARS)
+            .collect();
+        videos.push(VideoEntry {
+            content_loc: content_loc.to_string(),
+            thumbnail_loc: thumbnail_loc.to_string(),
+            title,
+            description,
+        });
+    }
+    videos
+}
+
+/// `(hreflang, prefix)` pairs from `language = /prefix/` lines. Prefixes
+/// always start and end with `/`.
+fn parse_hreflang_mapping(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(language, prefix)| {
+            let prefix = prefix.trim().trim_matches('/');
+            let prefix = if prefix.is_empty() {
+                "/".to_string()
+            } else {
+                format!("/{}/", prefix)
+            };
+            (language.trim().to_string(), prefix)
+        })
+        .filter(|(language, _)| !language.is_empty())
+        .collect()
+}
+
+/// The language of a page and the part of its path shared by all of its
+/// variants.
+fn locale_of(url: &Url, mode: HreflangMode, mapping: &[(String, String)]) -> Option<(String, String)> {
+    let path = url.path();
+    let query = url.query().map(|query| format!("?{}", query)).unwrap_or_default();
+    match mode {
+        HreflangMode::Off => None,
+        HreflangMode::Detect => {
+            let trimmed = path.trim_start_matches('/');
+            let (segment, rest) = trimmed.split_once('/').unwrap_or((trimmed, ""));
+            let mut parts = segment.split(['-', '_']);
+            let language = parts.next()?;
+            let region = parts.next();
+            let is_language = language.len() == 2
+                && language.chars().all(|c| c.is_ascii_lowercase())
+                && LANGUAGE_CODES.split_whitespace().any(|code| code == language);
+            let is_region = region.is_none_or(|region| {
+                (region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()))
+                    || (region.len() == 3 && region.chars().all(|c| c.is_ascii_digit()))
+            });
+            if !is_language || !is_region || parts.next().is_some() {
+                return None;
+            }
+            let code = match region {
+                Some(region) => format!("{}-{}", language, region.to_uppercase()),
+                None => language.to_string(),
+            };
+            Some((code, format!("/{}{}", rest, query)))
+        }
+        HreflangMode::Manual => {
+            let with_slash = format!("{}/", path);
+            mapping
+                .iter()
+                .filter(|(_, prefix)| path.starts_with(prefix.as_str()) || with_slash == *prefix)
+                .max_by_key(|(_, prefix)| prefix.len())
+                .map(|(language, prefix)| {
+                    let rest = path.get(prefix.len()..).unwrap_or("");
+                    (language.clone(), format!("/{}{}", rest, query))
+                })
+        }
+    }
+}
+
+/// Groups pages that differ only in their locale and lists every variant
+/// on each member, as Google expects.
+fn add_alternates(entries: &mut [SitemapEntry], mode: HreflangMode, mapping: &[(String, String)]) {
+    let mut groups: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    let mut members: Vec<Option<String>> = Vec::with_capacity(entries.len());
+    for entry in entries.iter() {
+        let locale = Url::parse(&entry.loc)
+            .ok()
+            .and_then(|url| locale_of(&url, mode, mapping));
+        match locale {
+            Some((language, rest)) => {
+                let group = groups.entry(rest.clone()).or_default();
+                if !group.iter().any(|(existing, _)| existing.eq_ignore_ascii_case(&language)) {
+                    group.push((language, entry.loc.clone()));
+                }
+                members.push(Some(rest));
+            }
+            None => members.push(None),
+        }
+    }
+    for (entry, group) in entries.iter_mut().zip(members) {
+        if let Some(group) = group.and_then(|rest| groups.get(&rest))
+            && group.len() > 1
+            && group.iter().any(|(_, href)| *href == entry.loc)
+        {
+            entry.alternates = group.clone();
+        }
+    }
+}
+
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn urlset_open(options: &SitemapOptions) -> String {
+    let mut open = String::from("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\"");
+    if options.images {
+        open.push_str("\n        xmlns:image=\"http://www.google.com/schemas/sitemap-image/1.1\"");
+    }
+    if options.videos {
+        open.push_str("\n        xmlns:video=\"http://www.google.com/schemas/sitemap-video/1.1\"");
+    }
+    if options.hreflang != HreflangMode::Off {
+        open.push_str("\n        xmlns:xhtml=\"http://www.w3.org/1999/xhtml\"");
+    }
+    open.push_str(">\n");
+    open
+}
+
+fn url_element(entry: &SitemapEntry, options: &SitemapOptions) -> String {
+    let mut element = format!("  <url>\n    <loc>{}</loc>\n", escape_xml(&entry.loc));
+    if let Some(lastmod) = &entry.lastmod {
+        element.push_str(&format!("    <lastmod>{}</lastmod>\n", lastmod));
+    }
+    if let Some(changefreq) = options.changefreq {
+        element.push_str(&format!("    <changefreq>{}</changefreq>\n", changefreq.label()));
+    }
+    if let Some(priority) = options.priority {
+        element.push_str(&format!("    <priority>{:.1}</priority>\n", priority.clamp(0.0, 1.0)));
+    }
+    for (language, href) in &entry.alternates {
+        element.push_str(&format!(
+            "    <xhtml:link rel=\"alternate\" hreflang=\"{}\" href=\"{}\"/>\n",
+            escape_xml(language),
+            escape_xml(href)
+        ));
+    }
+    for image in &entry.images {
+        element.push_str(&format!(
+            "    <image:image>\n      <image:loc>{}</image:loc>\n    </image:image>\n",
+            escape_xml(image)
+        ));
+    }
+    for video in &entry.videos {
+        element.push_str(&format!(
+            "    <video:video>\n      <video:thumbnail_loc>{}</video:thumbnail_loc>\n      <video:title>{}</video:title>\n      <video:description>{}</video:description>\n      <video:content_loc>{}</video:content_loc>\n    </video:video>\n",
+            escape_xml(&video.thumbnail_loc),
+            escape_xml(&video.title),
+            escape_xml(&video.description),
+            escape_xml(&video.content_loc)
+        ));
+    }
+    element.push_str("  </url>\n");
+    element
+}
+
+/// Packs the elements into as few `urlset` documents as the URL and size
+/// limits allow.
+fn split_urlsets(elements: &[String], urlset_open: &str) -> Vec<String> {
+    let open = format!("{}{}", XML_DECLARATION, urlset_open);
+    let mut documents = Vec::new();
+    let mut current = open.clone();
+    let mut count = 0;
+    for element in elements {
+        if count > 0 && (count == MAX_URLS || current.len() + element.len() + URLSET_CLOSE.len() > MAX_BYTES) {
+            current.push_str(URLSET_CLOSE);
+            documents.push(std::mem::replace(&mut current, open.clone()));
+            count = 0;
+        }
+        current.push_str(element);
+        count += 1;
+    }
+    current.push_str(URLSET_CLOSE);
+    documents.push(current);
+    documents
+}
+
+fn sitemap_index(locations: &[String], lastmod: Option<&str>) -> String {
+    let mut index = format!(
+        "{}<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        XML_DECLARATION
+    );
+    for location in locations {
+        index.push_str(&format!("  <sitemap>\n    <loc>{}</loc>\n", escape_xml(location)));
+        if let Some(lastmod) = lastmod {
+            index.push_str(&format!("    <lastmod>{}</lastmod>\n", lastmod));
+        }
+        index.push_str("  </sitemap>\n");
+    }
+    index.push_str("</sitemapindex>\n");
+    index
+}
+
+fn write_document(path: &Path, document: &str, gzip: bool) -> Result<(), String> {
+    let result = if gzip {
+        fs::File::create(path).and_then(|file| {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            encoder.write_all(document.as_bytes())?;
+            encoder.finish().map(|_| ())
+        })
+    } else {
+        fs::write(path, document)
+    };
+    result.map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Writes the sitemap of one host into `directory`, returning the number
+/// of files written. Hosts that need more than one file get
+/// `sitemap-N.xml` parts and a `sitemap_index.xml` that points at them
+/// under the host's root, where they are expected to be uploaded.
+fn write_host(directory: &Path, host: &str, sitemap: &HostSitemap, options: &SitemapOptions) -> Result<usize, String> {
+    fs::create_dir_all(directory).map_err(|e| format!("Failed to create {}: {}", directory.display(), e))?;
+    let extension = if options.gzip { "xml.gz" } else { "xml" };
+    let elements: Vec<String> = sitemap
+        .entries
+        .iter()
+        .map(|entry| url_element(entry, options))
+        .collect();
+    let documents = split_urlsets(&elements, &urlset_open(options));
+
+    if let [document] = documents.as_slice() {
+        write_document(&directory.join(format!("sitemap.{}", extension)), document, options.gzip)?;
+        return Ok(1);
+    }
+
+    let mut locations = Vec::new();
+    for (index, document) in documents.iter().enumerate() {
+        let name = format!("sitemap-{}.{}", index + 1, extension);
+        write_document(&directory.join(&name), document, options.gzip)?;
+        locations.push(format!("{}://{}/{}", sitemap.scheme, host, name));
+    }
+    let today = options
+        .lastmod
+        .then(|| response_store::format_date(response_store::now_millis()));
+    let index = sitemap_index(&locations, today.as_deref());
+    write_document(&directory.join("sitemap_index.xml"), &index, false)?;
+    Ok(documents.len() + 1)
+}
+
+/// Writes a sitemap per host under `options.directory`.
+pub fn spawn_sitemap_thread(db: Arc<sled::Db>, options: SitemapOptions) -> Receiver<SaveProgress> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let hosts = collect_entries(&db, &options);
+        if hosts.values().all(|sitemap| sitemap.entries.is_empty()) {
+            let _ = sender.send(SaveProgress::Errored("No endpoints to write".to_string()));
+            return;
+        }
+
+        let mut files = 0;
+        let (mut urls, mut images, mut videos, mut localised) = (0, 0, 0, 0);
+        for (host, sitemap) in hosts.iter().filter(|(_, sitemap)| !sitemap.entries.is_empty()) {
+            // IPv6 hosts would put colons in the folder name.
+            let directory = options.directory.join(host.replace(':', "_"));
+            match write_host(&directory, host, sitemap, &options) {
+                Ok(written) => {
+                    files += written;
+                    urls += sitemap.entries.len();
+                    for entry in &sitemap.entries {
+                        images += entry.images.len();
+                        videos += entry.videos.len();
+                        localised += usize::from(!entry.alternates.is_empty());
+                    }
+                }
+                Err(e) => {
+                    let _ = sender.send(SaveProgress::Errored(e));
+                    return;
+                }
+            }
+        }
+
+        let mut extras = Vec::new();
+        if options.images {
+            extras.push(format!("{} images", images));
+        }
+        if options.videos {
+            extras.push(format!("{} videos", videos));
+        }
+        if options.hreflang != HreflangMode::Off {
+            extras.push(format!("{} pages with hreflang", localised));
+        }
+        let extras = if extras.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", extras.join(", "))
+        };
+        let _ = sender.send(SaveProgress::Finished(format!(
+            "Wrote {} URLs{} for {} hosts in {} files under {}",
+            urls,
+            extras,
+            hosts.len(),
+            files,
+            options.directory.display()
+        )));
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_processing;
+
+    #[test]
+    fn fragments_are_dropped_from_locations() {
+        let db = file_processing::test_db(&["https://example.com/page#a", "https://example.com/page#b", "https://example.com/#top"]);
+        let hosts = collect_entries(&db, &SitemapOptions { lastmod: false, ..SitemapOptions::default() });
+        let locations: Vec<&str> = hosts["example.com"].entries.iter().map(|entry| entry.loc.as_str()).collect();
+        assert_eq!(locations, ["https://example.com/", "https://example.com/page"]);
+    }
+
+    #[test]
+    fn escapes_markup_characters() {
+        assert_eq!(escape_xml("/a?b=1&c=\"<'>\""), "/a?b=1&amp;c=&quot;&lt;&apos;&gt;&quot;");
+    }
+
+    #[test]
+    fn splits_urlsets_at_the_url_limit() {
+        let elements = vec!["  <url></url>\n".to_string(); MAX_URLS + 1];
+        let documents = split_urlsets(&elements, "<urlset>\n");
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0].matches("<url>").count(), MAX_URLS);
+        assert_eq!(documents[1].matches("<url>").count(), 1);
+        assert!(documents.iter().all(|document| document.starts_with(XML_DECLARATION) && document.ends_with(URLSET_CLOSE)));
+    }
+
+    #[test]
+    fn splits_urlsets_at_the_size_limit() {
+        let element = format!("  <url>{}</url>\n", "a".repeat(1024 * 1024));
+        let documents = split_urlsets(&vec![element; 60], "<urlset>\n");
+        assert_eq!(documents.len(), 2);
+        assert!(documents.iter().all(|document| document.len() <= MAX_BYTES));
+    }
+}
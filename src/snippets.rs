@@ -0,0 +1,267 @@
+use url::Url;
+
+use crate::network::NetworkConfig;
+use crate::response_store::StoredResponse;
+
+/// Headers the client tools compute themselves and that would be wrong
+/// once the snippet is edited.
+const COMPUTED_HEADERS: &[&str] = &["host", "content-length", "connection"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SnippetFormat {
+    Curl,
+    Python,
+    RawHttp,
+    PowerShell,
+}
+
+impl SnippetFormat {
+    pub const ALL: [SnippetFormat; 4] = [
+        SnippetFormat::Curl,
+        SnippetFormat::Python,
+        SnippetFormat::RawHttp,
+        SnippetFormat::PowerShell,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SnippetFormat::Curl => "Copy as cURL",
+            SnippetFormat::Python => "Copy as Python requests",
+            SnippetFormat::RawHttp => "Copy as raw HTTP",
+            SnippetFormat::PowerShell => "Copy as PowerShell",
+        }
+    }
+}
+
+/// A request in the shape every snippet format needs.
+#[derive(Clone)]
+pub struct RequestSpec {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+impl RequestSpec {
+    /// The request that produced `response`, parsed from the request text
+    /// it was stored with.
+    pub fn from_stored(response: &StoredResponse) -> Self {
+        let mut lines = response.request.lines();
+        let method = lines
+            .next()
+            .and_then(|line| line.split_whitespace().next())
+            .filter(|method| method.chars().all(|c| c.is_ascii_uppercase()))
+            .unwrap_or(&response.method);
+        let mut headers = Vec::new();
+        for line in lines.by_ref() {
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+        let body: Vec<&str> = lines.collect();
+        Self {
+            method: if method.is_empty() { "GET".to_string() } else { method.to_string() },
+            url: response.url.clone(),
+            headers,
+            body: (!body.is_empty()).then(|| body.join("\n")),
+        }
+    }
+
+    /// A GET of `url` with the headers `config` sends by default, for
+    /// endpoints that have never been requested.
+    pub fn from_url(url: &str, config: &NetworkConfig) -> Self {
+        Self {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: config.default_headers.clone(),
+            body: None,
+        }
+    }
+
+    fn sent_headers(&self) -> impl Iterator<Item = &(String, String)> {
+        self.headers
+            .iter()
+            .filter(|(name, _)| !COMPUTED_HEADERS.contains(&name.to_ascii_lowercase().as_str()))
+    }
+
+    pub fn render(&self, format: SnippetFormat) -> String {
+        match format {
+            SnippetFormat::Curl => self.curl(),
+            SnippetFormat::Python => self.python(),
+            SnippetFormat::RawHttp => self.raw_http(),
+            SnippetFormat::PowerShell => self.powershell(),
+        }
+    }
+
+    fn curl(&self) -> String {
+        let mut parts = vec![format!("curl {}", shell_quote(&self.url))];
+        if self.method != "GET" || self.body.is_some() {
+            parts.push(format!("-X {}", self.method));
+        }
+        for (name, value) in self.sent_headers() {
+            parts.push(format!("-H {}", shell_quote(&format!("{}: {}", name, value))));
+        }
+        if let Some(body) = &self.body {
+            parts.push(format!("--data-raw {}", shell_quote(body)));
+        }
+        parts.join(" \\\n  ")
+    }
+
+    fn python(&self) -> String {
+        let mut script = String::from("import requests\n\n");
+        script.push_str("headers = {\n");
+        for (name, value) in self.sent_headers() {
+            script.push_str(&format!("    {}: {},\n", python_string(name), python_string(value)));
+        }
+        script.push_str("}\n");
+        let mut arguments = format!(
+            "{}, {}, headers=headers",
+            python_string(&self.method),
+            python_string(&self.url)
+        );
+        if let Some(body) = &self.body {
+            script.push_str(&format!("data = {}\n", python_string(body)));
+            arguments.push_str(", data=data");
+        }
+        script.push_str(&format!("\nresponse = requests.request({})\n", arguments));
+        script.push_str("print(response.status_code)\nprint(response.text)\n");
+        script
+    }
+
+    fn raw_http(&self) -> String {
+        let parsed = Url::parse(&self.url).ok();
+        let mut target = parsed.as_ref().map_or("/".to_string(), |url| url.path().to_string());
+        if let Some(query) = parsed.as_ref().and_then(Url::query) {
+            target.push('?');
+            target.push_str(query);
+        }
+        let mut request = format!("{} {} HTTP/1.1\r\n", self.method, target);
+        let host = parsed.as_ref().and_then(|url| {
+            let host = url.host_str()?;
+            Some(url.port().map_or(host.to_string(), |port| format!("{}:{}", host, port)))
+        });
+        let host = host.or_else(|| {
+            self.headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("host"))
+                .map(|(_, host)| host.clone())
+        });
+        if let Some(host) = host {
+            request.push_str(&format!("Host: {}\r\n", host));
+        }
+        for (name, value) in self.sent_headers() {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if let Some(body) = &self.body {
+            request.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+        } else {
+            request.push_str("\r\n");
+        }
+        request
+    }
+
+    fn powershell(&self) -> String {
+        // Windows PowerShell refuses these two in -Headers.
+        let mut user_agent = None;
+        let mut content_type = None;
+        let mut headers = Vec::new();
+        for (name, value) in self.sent_headers() {
+            match name.to_ascii_lowercase().as_str() {
+                "user-agent" => user_agent = Some(value),
+                "content-type" => content_type = Some(value),
+                _ => headers.push(format!("    {} = {}", powershell_quote(name), powershell_quote(value))),
+            }
+        }
+        let mut command = String::new();
+        if !headers.is_empty() {
+            command.push_str(&format!("$headers = @{{\n{}\n}}\n", headers.join("\n")));
+        }
+        command.push_str(&format!(
+            "Invoke-WebRequest -UseBasicParsing -Uri {} -Method {}",
+            powershell_quote(&self.url),
+            self.method
+        ));
+        if !headers.is_empty() {
+            command.push_str(" -Headers $headers");
+        }
+        if let Some(user_agent) = user_agent {
+            command.push_str(&format!(" -UserAgent {}", powershell_quote(user_agent)));
+        }
+        if let Some(content_type) = content_type {
+            command.push_str(&format!(" -ContentType {}", powershell_quote(content_type)));
+        }
+        if let Some(body) = &self.body {
+            command.push_str(&format!(" -Body {}", powershell_quote(body)));
+        }
+        command
+    }
+}
+
+/// Single-quotes `value` for a POSIX shell.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn powershell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// A double-quoted Python literal; JSON string escapes are valid Python.
+fn python_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> RequestSpec {
+        RequestSpec {
+            method: "POST".to_string(),
+            url: "http://example.com:8080/api?q=it's".to_string(),
+            headers: vec![
+                ("Host".to_string(), "ignored".to_string()),
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("User-Agent".to_string(), "test".to_string()),
+            ],
+            body: Some("{\"name\": \"O'Brien\"}".to_string()),
+        }
+    }
+
+    #[test]
+    fn quotes_for_each_shell() {
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("$HOME `x`"), "'$HOME `x`'");
+        assert_eq!(powershell_quote("it's"), "'it''s'");
+        assert_eq!(python_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+    }
+
+    #[test]
+    fn curl_leaves_out_computed_headers() {
+        let curl = request().render(SnippetFormat::Curl);
+        assert!(curl.starts_with("curl 'http://example.com:8080/api?q=it'\\''s'"));
+        assert!(curl.contains("-X POST"));
+        assert!(curl.contains("-H 'Content-Type: application/json'"));
+        assert!(!curl.contains("Host:"));
+        assert!(curl.contains("--data-raw '{\"name\": \"O'\\''Brien\"}'"));
+    }
+
+    #[test]
+    fn raw_http_takes_host_and_port_from_the_url() {
+        let raw = request().render(SnippetFormat::RawHttp);
+        assert!(raw.starts_with("POST /api?q=it%27s HTTP/1.1\r\nHost: example.com:8080\r\n"));
+        assert!(raw.ends_with("Content-Length: 19\r\n\r\n{\"name\": \"O'Brien\"}"));
+    }
+
+    #[test]
+    fn powershell_moves_restricted_headers_to_parameters() {
+        let command = request().render(SnippetFormat::PowerShell);
+        assert!(!command.contains("$headers"));
+        assert!(command.contains("-UserAgent 'test'"));
+        assert!(command.contains("-ContentType 'application/json'"));
+        assert!(command.contains("-Body '{\"name\": \"O''Brien\"}'"));
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use url::Url;
+
+use crate::calibration;
+use crate::file_processing::{self, Source};
+use crate::links;
+use crate::network::{self, ClientCache, NetworkConfig, RateLimiter};
+use crate::response_store;
+use crate::robots::{self, Robots};
+
+/// Extensions that are added to the tree but never fetched, since they
+/// cannot contain links.
+const NON_CRAWLABLE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "ico", "bmp", "svg", "woff", "woff2", "ttf", "eot", "otf",
+    "mp3", "mp4", "webm", "avi", "mov", "pdf", "zip", "gz", "tar", "rar", "7z", "exe", "dmg", "iso",
+];
+
+/// Longer Crawl-delay values are clamped to this, so a typo in robots.txt
+/// cannot stall a crawl indefinitely.
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(3600);
+
+#[derive(Clone)]
+pub struct SpiderOptions {
+    pub start_urls: Vec<String>,
+    pub max_depth: u32,
+    pub max_pages: usize,
+    pub respect_robots: bool,
+    pub threads: u32,
+    pub rate_limit: u32,
+    pub network: NetworkConfig,
+}
+
+pub enum SpiderProgress {
+    Fetched {
+        url: String,
+        status: Option<u16>,
+        new_endpoints: usize,
+        pages: usize,
+        queued: usize,
+    },
+    Blocked(String),
+    Finished(usize),
+    Errored(String),
+}
+
+#[derive(Default)]
+struct Frontier {
+    queue: VecDeque<(Url, u32)>,
+    seen: HashSet<String>,
+    scheduled: usize,
+    in_flight: usize,
+    pages: usize,
+}
+
+struct Crawl {
+    db: Arc<sled::Db>,
+    options: SpiderOptions,
+    scope: HashSet<String>,
+    robots: HashMap<String, Robots>,
+    /// When each origin with a Crawl-delay may next be requested.
+    next_request: Mutex<HashMap<String, Instant>>,
+    frontier: Mutex<Frontier>,
+    wakeup: Condvar,
+    limiter: RateLimiter,
+    stop: Arc<AtomicBool>,
+}
+
+/// Crawls from `options.start_urls`, staying on their hosts, and writes
+/// every link it finds into the tree as soon as a page is parsed.
+pub fn spawn_spider_thread(
+    db: Arc<sled::Db>,
+    options: SpiderOptions,
+    stop: Arc<AtomicBool>,
+) -> Receiver<SpiderProgress> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let start_urls: Vec<Url> = options
+            .start_urls
+            .iter()
+            .filter_map(|url| Url::parse(url).ok())
+            .collect();
+        if start_urls.is_empty() {
+            let _ = sender.send(SpiderProgress::Errored("No valid start URL".to_string()));
+            return;
+        }
+
+        let scope: HashSet<String> = start_urls
+            .iter()
+            .filter_map(|url| url.host_str().map(String::from))
+            .collect();
+        let robots = if options.respect_robots {
+            fetch_robots(&start_urls, &options.network)
+        } else {
+            HashMap::new()
+        };
+
+        // Calibrate each host so soft-404 pages are tagged as they are stored.
+        let mut calibrated = HashSet::new();
+        for url in &start_urls {
+            if calibrated.insert(url.origin().ascii_serialization())
+                && let Ok(root) = url.join("/")
+            {
+                let _ = calibration::calibrate(&db, &options.network, &root);
+            }
+        }
+
+        let mut frontier = Frontier::default();
+        for url in start_urls {
+            if frontier.scheduled < options.max_pages && frontier.seen.insert(url.to_string()) {
+                frontier.queue.push_back((url, 0));
+                frontier.scheduled += 1;
+            }
+        }
+
+        let crawl = Arc::new(Crawl {
+            db,
+            limiter: RateLimiter::new(options.rate_limit),
+            options,
+            scope,
+            robots,
+            next_request: Mutex::new(HashMap::new()),
+            frontier: Mutex::new(frontier),
+            wakeup: Condvar::new(),
+            stop,
+        });
+
+        let handles: Vec<_> = (0..crawl.options.threads.max(1))
+            .map(|_| {
+                let crawl = Arc::clone(&crawl);
+                let sender = sender.clone();
+                thread::spawn(move || crawl.work(&sender))
+            })
+            .collect();
+        for handle in handles {
+            let _ = handle.join();
+        }
+
+        let pages = crawl.frontier.lock().unwrap().pages;
+        let _ = crawl.db.flush();
+        let _ = sender.send(SpiderProgress::Finished(pages));
+    });
+
+    receiver
+}
+
+/// robots.txt rules per origin, for the user agent sent with every request.
+fn fetch_robots(start_urls: &[Url], config: &NetworkConfig) -> HashMap<String, Robots> {
+    let user_agent = config.user_agent();
+    let mut robots = HashMap::new();
+    for url in start_urls {
+        let origin = url.origin().ascii_serialization();
+        if robots.contains_key(&origin) {
+            continue;
+        }
+        let Some(robots_url) = robots::robots_url(url) else {
+            continue;
+        };
+        let (response, body) = network::fetch(config, robots_url.as_str());
+        let rules = match response.status {
+            Some(200) => Robots::parse(&String::from_utf8_lossy(&body), user_agent),
+            _ => Robots::default(),
+        };
+        robots.insert(origin, rules);
+    }
+    robots
+}
+
+fn is_crawlable(url: &Url) -> bool {
+    let extension = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_lowercase());
+    extension.is_none_or(|ext| !NON_CRAWLABLE_EXTENSIONS.contains(&ext.as_str()))
+}
+
+impl Crawl {
+    fn work(&self, sender: &Sender<SpiderProgress>) {
+        let mut clients = ClientCache::new(true);
+        while let Some((url, depth)) = self.next_url() {
+            self.visit(&mut clients, url, depth, sender);
+            let mut frontier = self.frontier.lock().unwrap();
+            frontier.in_flight -= 1;
+            self.wakeup.notify_all();
+        }
+    }
+
+    /// Waits for queued work; returns `None` once the queue is drained and
+    /// no other worker can add to it, or the crawl was stopped.
+    fn next_url(&self) -> Option<(Url, u32)> {
+        let mut frontier = self.frontier.lock().unwrap();
+        loop {
+            if self.stop.load(Ordering::SeqCst) {
+                return None;
+            }
+            if let Some(item) = frontier.queue.pop_front() {
+                frontier.in_flight += 1;
+                return Some(item);
+            }
+            if frontier.in_flight == 0 {
+                self.wakeup.notify_all();
+                return None;
+            }
+            frontier = self
+                .wakeup
+                .wait_timeout(frontier, Duration::from_millis(100))
+                .unwrap()
+                .0;
+        }
+    }
+
+    fn is_blocked(&self, url: &Url) -> bool {
+        self.robots
+            .get(&url.origin().ascii_serialization())
+            .is_some_and(|robots| !robots.is_allowed(&robots::request_path(url)))
+    }
+
+    /// Takes the next request slot of `url`'s origin and sleeps until it,
+    /// so requests to an origin are at least its Crawl-delay apart across
+    /// all workers. Returns early when the crawl is stopped.
+    fn wait_for_crawl_delay(&self, url: &Url) {
+        let origin = url.origin().ascii_serialization();
+        let Some(delay) = self
+            .robots
+            .get(&origin)
+            .and_then(|robots| robots.crawl_delay)
+            .and_then(|delay| Duration::try_from_secs_f64(delay).ok())
+            .map(|delay| delay.min(MAX_CRAWL_DELAY))
+        else {
+            return;
+        };
+        let slot = {
+            let mut next_request = self.next_request.lock().unwrap();
+            let now = Instant::now();
+            let slot = next_request.get(&origin).copied().filter(|next| *next > now).unwrap_or(now);
+            next_request.insert(origin, slot + delay);
+            slot
+        };
+        while !self.stop.load(Ordering::SeqCst) {
+            let now = Instant::now();
+            if now >= slot {
+                break;
+            }
+            thread::sleep((slot - now).min(Duration::from_millis(100)));
+        }
+    }
+
+    fn visit(&self, clients: &mut ClientCache, url: Url, depth: u32, sender: &Sender<SpiderProgress>) {
+        if self.options.respect_robots && self.is_blocked(&url) {
+            let _ = sender.send(SpiderProgress::Blocked(url.to_string()));
+            return;
+        }
+
+        if self.options.respect_robots {
+            self.wait_for_crawl_delay(&url);
+            if self.stop.load(Ordering::SeqCst) {
+                return;
+            }
+        }
+        self.limiter.wait();
+        let config = &self.options.network;
+        let (response, body) = match clients.client_for(config, url.as_str()) {
+            Ok((client, proxy)) => network::fetch_with(config, client, proxy.as_ref(), url.as_str()),
+            Err(_) => network::fetch(config, url.as_str()),
+        };
+        let status = response.status;
+        let content_type = response.content_type.clone();
+        if status.is_some() {
+            let _ = response_store::record(&self.db, response, &body);
+        }
+
+        let found: Vec<Url> = if status.is_some() {
+            links::extract_links(&url, &content_type, &String::from_utf8_lossy(&body))
+                .into_iter()
+                .filter(|link| link.host_str().is_some_and(|host| self.scope.contains(host)))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let new_endpoints = file_processing::record_urls(&self.db, &found, Source::Spider).unwrap_or(0);
+
+        let mut frontier = self.frontier.lock().unwrap();
+        frontier.pages += 1;
+        if depth < self.options.max_depth {
+            for link in found {
+                if frontier.scheduled >= self.options.max_pages {
+                    break;
+                }
+                if is_crawlable(&link) && frontier.seen.insert(link.to_string()) {
+                    frontier.queue.push_back((link, depth + 1));
+                    frontier.scheduled += 1;
+                }
+            }
+        }
+        let _ = sender.send(SpiderProgress::Fetched {
+            url: url.to_string(),
+            status,
+            new_endpoints,
+            pages: frontier.pages,
+            queued: frontier.queue.len(),
+        });
+    }
+}
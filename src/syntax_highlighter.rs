@@ -1,5 +1,6 @@
 use eframe::egui;
-use egui::text::LayoutJob;
+use egui::text::{LayoutJob, LayoutSection};
+use std::ops::Range;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::{Theme, ThemeSet};
 use syntect::parsing::SyntaxSet;
@@ -42,4 +43,62 @@ impl CodeTheme {
         }
         job
     }
+
+    /// Highlights `code` into one job per line, carrying the highlighter
+    /// state across lines so multi-line constructs keep their colours.
+    pub fn highlight_lines(&self, lang: &str, code: &str) -> Vec<LayoutJob> {
+        let syntax = SYNTAX_SET.find_syntax_by_extension(lang).unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+
+        let mut h = HighlightLines::new(syntax, self.theme);
+        let mut jobs = Vec::new();
+
+        for line in code.lines() {
+            let mut job = LayoutJob::default();
+            let line = format!("{}\n", line);
+            let ranges = h.highlight_line(&line, &SYNTAX_SET).unwrap();
+            for (style, text) in ranges {
+                let color = egui::Color32::from_rgb(
+                    style.foreground.r,
+                    style.foreground.g,
+                    style.foreground.b,
+                );
+                let text = text.trim_end_matches('\n');
+                if !text.is_empty() {
+                    job.append(text, 0.0, egui::TextFormat { font_id: egui::FontId::monospace(14.0), color, ..Default::default() });
+                }
+            }
+            jobs.push(job);
+        }
+        jobs
+    }
+
+    /// Paints `background` behind the byte `ranges` of an already
+    /// highlighted job, splitting sections where a range starts or ends.
+    /// `ranges` must be sorted and must not overlap.
+    pub fn mark_ranges(job: &mut LayoutJob, ranges: &[Range<usize>], background: egui::Color32) {
+        if ranges.is_empty() {
+            return;
+        }
+        let mut sections = Vec::with_capacity(job.sections.len() + ranges.len() * 2);
+        for section in job.sections.drain(..) {
+            let mut start = section.byte_range.start;
+            let end = section.byte_range.end;
+            let overlapping = ranges.iter().filter(|r| r.start < end && r.end > section.byte_range.start);
+            for range in overlapping {
+                if range.start > start {
+                    sections.push(LayoutSection { byte_range: start..range.start, ..section.clone() });
+                    start = range.start;
+                }
+                let marked_end = range.end.min(end);
+                let mut format = section.format.clone();
+                format.background = background;
+                sections.push(LayoutSection { byte_range: start..marked_end, format, ..section.clone() });
+                start = marked_end;
+            }
+            if start < end {
+                sections.push(LayoutSection { byte_range: start..end, ..section });
+            }
+        }
+        job.sections = sections;
+    }
 }
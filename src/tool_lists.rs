@@ -0,0 +1,207 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use url::Url;
+
+use crate::file_saver::SaveProgress;
+
+/// Target list layouts expected by common command-line tools.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ToolPreset {
+    Nuclei,
+    Ffuf,
+    Httpx,
+    Sqlmap,
+}
+
+impl ToolPreset {
+    pub const ALL: [ToolPreset; 4] = [ToolPreset::Nuclei, ToolPreset::Ffuf, ToolPreset::Httpx, ToolPreset::Sqlmap];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ToolPreset::Nuclei => "nuclei",
+            ToolPreset::Ffuf => "ffuf",
+            ToolPreset::Httpx => "httpx",
+            ToolPreset::Sqlmap => "sqlmap",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            ToolPreset::Nuclei => "One base URL per scheme and host, for nuclei -l",
+            ToolPreset::Ffuf => "A FUZZ-marked URL for every directory level, for ffuf -u",
+            ToolPreset::Httpx => "One host:port per host, for httpx -l",
+            ToolPreset::Sqlmap => "Endpoints with query parameters, one per path and parameter set, for sqlmap -m",
+        }
+    }
+
+    pub fn default_file_name(self) -> &'static str {
+        match self {
+            ToolPreset::Nuclei => "nuclei_targets.txt",
+            ToolPreset::Ffuf => "ffuf_urls.txt",
+            ToolPreset::Httpx => "httpx_hosts.txt",
+            ToolPreset::Sqlmap => "sqlmap_bulk.txt",
+        }
+    }
+}
+
+/// The URL of an endpoint as listed by `get_all_children`, which leaves the
+/// scheme off when it is unknown.
+fn parse(endpoint: &str) -> Option<Url> {
+    if endpoint.contains("://") {
+        Url::parse(endpoint).ok()
+    } else {
+        Url::parse(&format!("https://{}", endpoint)).ok()
+    }
+}
+
+fn base_url(url: &Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+/// `base/FUZZ` for the host root and every directory above each endpoint.
+/// The last segment counts as a directory when it has no file extension.
+fn ffuf_urls(url: &Url, lines: &mut BTreeSet<String>) {
+    let base = base_url(url);
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map_or(Vec::new(), |segments| segments.filter(|segment| !segment.is_empty()).collect());
+    let directories = match segments.last() {
+        Some(last) if last.contains('.') => segments.len() - 1,
+        _ => segments.len(),
+    };
+    let mut path = String::new();
+    lines.insert(format!("{}/FUZZ", base));
+    for segment in &segments[..directories] {
+        path.push('/');
+        path.push_str(segment);
+        lines.insert(format!("{}{}/FUZZ", base, path));
+    }
+}
+
+/// The target list for `preset`, built from full endpoint URLs, deduplicated
+/// and sorted.
+pub fn target_list(preset: ToolPreset, endpoints: &[String]) -> Vec<String> {
+    let mut lines = BTreeSet::new();
+    // sqlmap only needs one URL per path and set of parameter names.
+    let mut sqlmap_seen = BTreeSet::new();
+    for url in endpoints.iter().filter_map(|endpoint| parse(endpoint)) {
+        match preset {
+            ToolPreset::Nuclei => {
+                lines.insert(base_url(&url));
+            }
+            ToolPreset::Ffuf => ffuf_urls(&url, &mut lines),
+            ToolPreset::Httpx => {
+                if let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) {
+                    lines.insert(format!("{}:{}", host, port));
+                }
+            }
+            ToolPreset::Sqlmap => {
+                let mut names: Vec<String> = url.query_pairs().map(|(name, _)| name.into_owned()).collect();
+                if names.is_empty() {
+                    continue;
+                }
+                names.sort();
+                names.dedup();
+                if sqlmap_seen.insert((base_url(&url), url.path().to_string(), names)) {
+                    lines.insert(url.to_string());
+                }
+            }
+        }
+    }
+    lines.into_iter().collect()
+}
+
+pub fn spawn_tool_list_thread(preset: ToolPreset, endpoints: Vec<String>, path: PathBuf) -> Receiver<SaveProgress> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let lines = target_list(preset, &endpoints);
+        if lines.is_empty() {
+            let _ = sender.send(SaveProgress::Errored(format!(
+                "No {} targets in the {} selected endpoints",
+                preset.label(),
+                endpoints.len()
+            )));
+            return;
+        }
+        let result = File::create(&path).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            for line in &lines {
+                writeln!(out, "{}", line)?;
+            }
+            out.flush()
+        });
+        let _ = sender.send(match result {
+            Ok(()) => SaveProgress::Finished(format!(
+                "Saved {} {} targets from {} endpoints to {}",
+                lines.len(),
+                preset.label(),
+                endpoints.len(),
+                path.display()
+            )),
+            Err(e) => SaveProgress::Errored(format!("Failed to write {}: {}", path.display(), e)),
+        });
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints() -> Vec<String> {
+        [
+            "https://example.com/api/v1/users?id=1",
+            "https://example.com/api/v1/users?id=2",
+            "https://example.com/api/v1/users?id=2&sort=name",
+            "http://example.com:8080/static/app.js",
+            "example.org/admin",
+        ]
+        .iter()
+        .map(|url| url.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn nuclei_and_httpx_list_each_host_once() {
+        assert_eq!(
+            target_list(ToolPreset::Nuclei, &endpoints()),
+            ["http://example.com:8080", "https://example.com", "https://example.org"]
+        );
+        assert_eq!(
+            target_list(ToolPreset::Httpx, &endpoints()),
+            ["example.com:443", "example.com:8080", "example.org:443"]
+        );
+    }
+
+    #[test]
+    fn ffuf_marks_every_directory_level() {
+        assert_eq!(
+            target_list(ToolPreset::Ffuf, &endpoints()),
+            [
+                "http://example.com:8080/FUZZ",
+                "http://example.com:8080/static/FUZZ",
+                "https://example.com/FUZZ",
+                "https://example.com/api/FUZZ",
+                "https://example.com/api/v1/FUZZ",
+                "https://example.com/api/v1/users/FUZZ",
+                "https://example.org/FUZZ",
+                "https://example.org/admin/FUZZ",
+            ]
+        );
+    }
+
+    #[test]
+    fn sqlmap_keeps_one_url_per_parameter_set() {
+        assert_eq!(
+            target_list(ToolPreset::Sqlmap, &endpoints()),
+            [
+                "https://example.com/api/v1/users?id=1",
+                "https://example.com/api/v1/users?id=2&sort=name",
+            ]
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+use crate::response_store;
+
+/// Matching endpoints are counted but branches are only opened
+/// automatically below this many, so a broad filter does not lay out the
+/// whole tree.
+pub const AUTO_EXPAND_LIMIT: usize = 500;
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum MatchMode {
+    #[default]
+    Substring,
+    Glob,
+    Regex,
+}
+
+impl MatchMode {
+    pub const ALL: [MatchMode; 3] = [MatchMode::Substring, MatchMode::Glob, MatchMode::Regex];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MatchMode::Substring => "Contains",
+            MatchMode::Glob => "Glob",
+            MatchMode::Regex => "Regex",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum ParameterFilter {
+    #[default]
+    Any,
+    With,
+    Without,
+}
+
+impl ParameterFilter {
+    pub const ALL: [ParameterFilter; 3] = [ParameterFilter::Any, ParameterFilter::With, ParameterFilter::Without];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ParameterFilter::Any => "any",
+            ParameterFilter::With => "with parameters",
+            ParameterFilter::Without => "without parameters",
+        }
+    }
+}
+
+/// What the sitemap panel's filter bar asks for. Every non-empty field must
+/// match; the text is matched against the full URL of each endpoint.
+#[derive(Clone, PartialEq, Default)]
+pub struct TreeFilter {
+    pub text: String,
+    pub mode: MatchMode,
+    /// Comma-separated extensions without the dot.
+    pub extensions: String,
+    pub parameters: ParameterFilter,
+    /// `http` or `https`; any scheme when empty.
+    pub scheme: String,
+    /// Comma-separated codes or classes such as `200, 3xx`.
+    pub statuses: String,
+    pub tag: String,
+}
+
+impl TreeFilter {
+    pub fn is_active(&self) -> bool {
+        !self.text.trim().is_empty()
+            || !self.extensions.trim().is_empty()
+            || self.parameters != ParameterFilter::Any
+            || !self.scheme.is_empty()
+            || !self.statuses.trim().is_empty()
+            || !self.tag.trim().is_empty()
+    }
+}
+
+/// The matching endpoints, with the number of matches under every key on
+/// the way to them.
+#[derive(Default)]
+pub struct FilterResult {
+    pub matches: usize,
+    pub counts: HashMap<String, usize>,
+    matched: HashSet<String>,
+}
+
+impl FilterResult {
+    /// Whether `key` is a match or has matches below it.
+    pub fn contains(&self, key: &str) -> bool {
+        self.counts.contains_key(key)
+    }
+
+    /// Whether the endpoint at `key` matches itself, rather than only
+    /// having matches below it.
+    pub fn is_match(&self, key: &str) -> bool {
+        self.matched.contains(key)
+    }
+
+    pub fn count(&self, key: &str) -> usize {
+        self.counts.get(key).copied().unwrap_or(0)
+    }
+}
+
+/// The parts of a `NodeValue` the filter looks at. Leaving out `children`
+/// keeps large folders cheap to decode during a scan.
+#[derive(Deserialize)]
+struct NodeSummary {
+    is_endpoint: bool,
+    scheme: Option<String>,
+    #[serde(default)]
+    tags: HashSet<String>,
+}
+
+enum Pattern {
+    Substring(String),
+    Regex(Regex),
+}
+
+enum StatusMatch {
+    Code(u16),
+    Class(u16),
+}
+
+struct Matcher {
+    pattern: Option<Pattern>,
+    extensions: Vec<String>,
+    parameters: ParameterFilter,
+    scheme: String,
+    statuses: Vec<StatusMatch>,
+    tag: String,
+}
+
+fn glob_regex(glob: &str) -> String {
+    let mut pattern = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    pattern
+}
+
+/// The last line of a regex error, which names the problem; the lines
+/// before it repeat the pattern.
+fn regex_error(error: &regex::Error) -> String {
+    let message = error.to_string();
+    message.lines().last().unwrap_or("invalid regex").trim().to_string()
+}
+
+fn parse_statuses(text: &str) -> Result<Vec<StatusMatch>, String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let lower = part.to_lowercase();
+            if let Some(class) = lower.strip_suffix("xx") {
+                class.parse().map(StatusMatch::Class)
+            } else {
+                lower.parse().map(StatusMatch::Code)
+            }
+            .map_err(|_| format!("Invalid status: {}", part))
+        })
+        .collect()
+}
+
+impl Matcher {
+    fn new(filter: &TreeFilter) -> Result<Self, String> {
+        let text = filter.text.trim();
+        let pattern = if text.is_empty() {
+            None
+        } else {
+            Some(match filter.mode {
+                MatchMode::Substring => Pattern::Substring(text.to_lowercase()),
+                MatchMode::Glob => Pattern::Regex(
+                    RegexBuilder::new(&glob_regex(text))
+                        .case_insensitive(true)
+                        .build()
+                        .map_err(|e| regex_error(&e))?,
+                ),
+                MatchMode::Regex => Pattern::Regex(Regex::new(text).map_err(|e| regex_error(&e))?),
+            })
+        };
+        Ok(Self {
+            pattern,
+            extensions: filter
+                .extensions
+                .split(',')
+                .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+                .filter(|ext| !ext.is_empty())
+                .collect(),
+            parameters: filter.parameters,
+            scheme: filter.scheme.clone(),
+            statuses: parse_statuses(&filter.statuses)?,
+            tag: filter.tag.trim().to_string(),
+        })
+    }
+
+    fn matches(&self, key: &str, node: &NodeSummary, statuses: &HashMap<String, u16>) -> bool {
+        let path_end = key.find(['?', '#']).unwrap_or(key.len());
+        let scheme = node.scheme.as_deref().unwrap_or("https");
+        if !self.scheme.is_empty() && scheme != self.scheme {
+            return false;
+        }
+        match self.parameters {
+            ParameterFilter::Any => {}
+            ParameterFilter::With if !key[path_end..].starts_with('?') => return false,
+            ParameterFilter::Without if key[path_end..].starts_with('?') => return false,
+            _ => {}
+        }
+        if !self.extensions.is_empty() {
+            let name = key[..path_end].rsplit('/').next().unwrap_or("");
+            let extension = name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase());
+            if !extension.is_some_and(|ext| self.extensions.contains(&ext)) {
+                return false;
+            }
+        }
+        if !self.tag.is_empty() && !node.tags.contains(&self.tag) {
+            return false;
+        }
+        if !self.statuses.is_empty() {
+            let Some(status) = statuses.get(key) else {
+                return false;
+            };
+            let matched = self.statuses.iter().any(|expected| match expected {
+                StatusMatch::Code(code) => code == status,
+                StatusMatch::Class(class) => status / 100 == *class,
+            });
+            if !matched {
+                return false;
+            }
+        }
+        match &self.pattern {
+            None => true,
+            Some(pattern) => {
+                let url = format!("{}://{}", scheme, key);
+                match pattern {
+                    Pattern::Substring(text) => url.to_lowercase().contains(text),
+                    Pattern::Regex(regex) => regex.is_match(&url),
+                }
+            }
+        }
+    }
+}
+
+/// `key` and the keys of its ancestors. Only the path part of a key is
+/// split, since queries may contain slashes.
+fn with_ancestors(key: &str) -> impl Iterator<Item = &str> {
+    let path_end = key.find(['?', '#']).unwrap_or(key.len());
+    key[..path_end]
+        .match_indices('/')
+        .map(move |(index, _)| &key[..index])
+        .chain(std::iter::once(key))
+}
+
+/// Scans every node of the tree once, in the background. Returns early
+/// without a result once `cancel` is set, so a filter typed over can be
+/// abandoned.
+pub fn spawn_filter_thread(
+    db: Arc<sled::Db>,
+    filter: TreeFilter,
+    cancel: Arc<AtomicBool>,
+) -> Receiver<Result<FilterResult, String>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let matcher = match Matcher::new(&filter) {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                let _ = sender.send(Err(e));
+                return;
+            }
+        };
+        let mut statuses = HashMap::new();
+        if !matcher.statuses.is_empty() {
+            for response in response_store::latest_all(&db) {
+                if let (Some(key), Some(status)) = (response_store::endpoint_key(&response.url), response.status) {
+                    statuses.insert(key, status);
+                }
+            }
+        }
+
+        let mut result = FilterResult::default();
+        for (index, (key, value)) in db.iter().flatten().enumerate() {
+            if index % 10_000 == 0 && cancel.load(Ordering::SeqCst) {
+                return;
+            }
+            let Ok(key) = std::str::from_utf8(&key) else {
+                continue;
+            };
+            if key == "__ROOT__" {
+                continue;
+            }
+            let Ok(node) = serde_json::from_slice::<NodeSummary>(&value) else {
+                continue;
+            };
+            if !node.is_endpoint || !matcher.matches(key, &node, &statuses) {
+                continue;
+            }
+            result.matches += 1;
+            result.matched.insert(key.to_string());
+            for ancestor in with_ancestors(key) {
+                *result.counts.entry(ancestor.to_string()).or_default() += 1;
+            }
+        }
+        if !cancel.load(Ordering::SeqCst) {
+            let _ = sender.send(Ok(result));
+        }
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_processing;
+
+    #[test]
+    fn ancestors_split_only_the_path() {
+        let keys: Vec<&str> = with_ancestors("example.com/api/users?next=/a/b").collect();
+        assert_eq!(keys, ["example.com", "example.com/api", "example.com/api/users?next=/a/b"]);
+        assert_eq!(with_ancestors("example.com").collect::<Vec<_>>(), ["example.com"]);
+    }
+
+    #[test]
+    fn counts_matches_under_every_ancestor() {
+        let db = Arc::new(file_processing::test_db(&[
+            "https://example.com/api/users",
+            "https://example.com/api/users/1",
+            "https://example.com/api/orders",
+            "https://example.com/static/app.js",
+        ]));
+
+        let filter = TreeFilter {
+            text: "users".to_string(),
+            ..TreeFilter::default()
+        };
+        let result = spawn_filter_thread(db, filter, Arc::new(AtomicBool::new(false)))
+            .recv()
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.matches, 2);
+        assert_eq!(result.count("example.com"), 2);
+        assert_eq!(result.count("example.com/api/users"), 2);
+        assert!(result.is_match("example.com/api/users"));
+        assert!(result.is_match("example.com/api/users/1"));
+        assert!(!result.contains("example.com/api/orders"));
+        assert!(!result.contains("example.com/static"));
+    }
+}